@@ -0,0 +1,218 @@
+//! A dudect style timing leakage test for the operations that handle secrets
+//!
+//! Each target is timed on two classes of inputs, one fixed secret and fresh random
+//! secrets, interleaved at random. If the running time does not depend on the secret,
+//! both classes have the same timing distribution, which is checked with Welch's t-test
+//! on the raw timings and on timings cropped at several percentiles to cut off noise.
+//! A |t| above 4.5 is strong evidence of a leak.
+//!
+//! Run it with `cargo bench --bench dudect`, optionally followed by `-- <samples>`
+use std::hint::black_box;
+use std::time::Instant;
+
+use bitcoin::{PrivateKey, S256Point, Scalar};
+
+const DEFAULT_SAMPLES: usize = 20_000;
+const THRESHOLD: f64 = 4.5;
+const PERCENTILES: [f64; 5] = [0.5, 0.75, 0.9, 0.95, 0.99];
+const Z: &[u8] = b"bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423";
+
+/// splitmix64, enough to draw test inputs and the class order without a dependency
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_bytes(&mut self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Returns a random scalar in the range 1 to n - 1
+    fn next_scalar(&mut self) -> Scalar {
+        loop {
+            let scalar = Scalar::from_bytes_reduced(&self.next_bytes());
+            if !scalar.is_zero() {
+                return scalar;
+            }
+        }
+    }
+}
+
+/// Running mean and variance of one class of timings (Welford's algorithm)
+#[derive(Default)]
+struct Moments {
+    count: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        self.count += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.count;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        self.m2 / (self.count - 1.0)
+    }
+}
+
+/// Welch's t statistic between the two classes
+fn welch_t(a: &Moments, b: &Moments) -> f64 {
+    if a.count < 2.0 || b.count < 2.0 {
+        return 0.0;
+    }
+    let se = (a.variance() / a.count + b.variance() / b.count).sqrt();
+    if se == 0.0 {
+        0.0
+    } else {
+        (a.mean - b.mean) / se
+    }
+}
+
+/// Returns the t statistic of the raw timings and of every cropped set of timings
+fn t_statistics(timings: &[(bool, f64)]) -> Vec<(String, f64)> {
+    let mut sorted: Vec<f64> = timings.iter().map(|&(_, t)| t).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut limits = vec![("raw".to_string(), f64::INFINITY)];
+    for p in PERCENTILES {
+        let index = ((sorted.len() as f64 * p) as usize).min(sorted.len() - 1);
+        limits.push((format!("p{}", (p * 100.0) as u32), sorted[index]));
+    }
+
+    limits
+        .into_iter()
+        .map(|(name, limit)| {
+            let mut classes = [Moments::default(), Moments::default()];
+            for &(class, t) in timings.iter().filter(|&&(_, t)| t <= limit) {
+                classes[class as usize].push(t);
+            }
+            (name, welch_t(&classes[0], &classes[1]))
+        })
+        .collect()
+}
+
+/// Times `operation` on the fixed input and on random inputs, and reports whether the
+/// two classes can be told apart
+/// # Arguments
+/// * `name` - The name of the target
+/// * `samples` - The number of measurements
+/// * `rng` - The source of random inputs and class order
+/// * `fixed` - The input of the fixed class
+/// * `random` - Draws an input of the random class
+/// * `operation` - The operation under test
+/// # Returns
+/// * `bool` - True if no leak was detected
+fn test<T, R, O>(
+    name: &str,
+    samples: usize,
+    rng: &mut Rng,
+    fixed: T,
+    mut random: R,
+    operation: O,
+) -> bool
+where
+    T: Clone,
+    R: FnMut(&mut Rng) -> T,
+    O: Fn(&T),
+{
+    // Prepare every input up front so that only the operation is timed
+    let inputs: Vec<(bool, T)> = (0..samples)
+        .map(|_| {
+            let class = rng.next_u64() & 1 == 1;
+            let input = if class { random(rng) } else { fixed.clone() };
+            (class, input)
+        })
+        .collect();
+
+    // Warm up caches and the branch predictor before measuring
+    for (_, input) in inputs.iter().take(samples / 100) {
+        operation(input);
+    }
+
+    let timings: Vec<(bool, f64)> = inputs
+        .iter()
+        .map(|(class, input)| {
+            let start = Instant::now();
+            operation(black_box(input));
+            (*class, start.elapsed().as_nanos() as f64)
+        })
+        .collect();
+
+    let statistics = t_statistics(&timings);
+    let (worst, max_t) = statistics
+        .iter()
+        .max_by(|a, b| a.1.abs().partial_cmp(&b.1.abs()).unwrap())
+        .unwrap();
+    let passed = max_t.abs() < THRESHOLD;
+    println!(
+        "{:<14} samples {:>7}  max |t| {:>7.2} ({})  {}",
+        name,
+        samples,
+        max_t.abs(),
+        worst,
+        if passed {
+            "no leak detected"
+        } else {
+            "possible leak"
+        }
+    );
+    passed
+}
+
+fn main() {
+    let samples = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_SAMPLES);
+    let mut rng = Rng(0x5eed_d0de_c0de_1234);
+    let generator = S256Point::generator();
+
+    let mut passed = test(
+        "scalar_mul",
+        samples,
+        &mut rng,
+        Scalar::ONE,
+        Rng::next_scalar,
+        |k| {
+            black_box(generator * *k);
+        },
+    );
+    passed &= test(
+        "scalar_invert",
+        samples,
+        &mut rng,
+        Scalar::ONE,
+        Rng::next_scalar,
+        |k| {
+            black_box(k.invert());
+        },
+    );
+    passed &= test(
+        "sign",
+        samples,
+        &mut rng,
+        PrivateKey::new(b"1"),
+        |rng| PrivateKey::new(rng.next_scalar().to_string().as_bytes()),
+        |key| {
+            black_box(key.sign(Z));
+        },
+    );
+
+    if !passed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,134 @@
+use bitcoin::{
+    BatchVerifier, FieldElement, Point, PrecomputedPoint, PrivateKey, S256Point, XOnlyPublicKey,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use num_bigint::BigUint;
+
+const SECRET: &[u8] = b"8b387de39861728c92ec9f589c303b1038ff60eb3963b12cd212263a1d1e0f00";
+const Z: &[u8] = b"bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423";
+
+/// The affine double-and-add path, with one modular inversion per point addition
+/// With `strauss`, both products share their doublings through `Point::multi_mul`
+fn affine_verify(point: &S256Point, z: &BigUint, r: &BigUint, s: &BigUint, strauss: bool) -> bool {
+    let n = BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap();
+    let s_inv = s.modpow(&(&n - 2u32), &n);
+    let u = (z * &s_inv) % &n;
+    let v = (r * &s_inv) % &n;
+    let total = if strauss {
+        Point::multi_mul(&[
+            (u, S256Point::generator().get_point()),
+            (v, point.get_point()),
+        ])
+    } else {
+        (S256Point::generator().get_point() * u) + (point.get_point() * v)
+    };
+    total.get_x().map(|x| x.get_number() % &n) == Some(r.clone())
+}
+
+fn field_mul(c: &mut Criterion) {
+    let mut group = c.benchmark_group("field_mul");
+    let a = FieldElement::from_bytes(S256Point::G_X, S256Point::PRIME);
+    let b = FieldElement::from_bytes(S256Point::G_Y, S256Point::PRIME);
+    group.bench_function("field_element", |bench| {
+        bench.iter(|| black_box(a.clone()) * black_box(b.clone()))
+    });
+    let generator = S256Point::generator();
+    let (a, b) = (*generator.get_x().unwrap(), *generator.get_y().unwrap());
+    group.bench_function("fe256", |bench| bench.iter(|| black_box(a) * black_box(b)));
+    group.finish();
+}
+
+fn scalar_mul(c: &mut Criterion) {
+    let k = BigUint::parse_bytes(SECRET, 16).unwrap();
+    let mut group = c.benchmark_group("scalar_mul");
+    group.bench_function("affine", |b| {
+        b.iter(|| S256Point::generator().get_point().clone() * black_box(k.clone()))
+    });
+    let point = *PrivateKey::new(SECRET).get_public_key();
+    group.bench_function("projective", |b| b.iter(|| point * black_box(k.clone())));
+    group.bench_function("precomputed", |b| {
+        b.iter(|| S256Point::generator() * black_box(k.clone()))
+    });
+    group.finish();
+}
+
+fn verify(c: &mut Criterion) {
+    let private_key = PrivateKey::new(SECRET);
+    let point = *private_key.get_public_key();
+    let signature = private_key.sign(Z);
+    let z = BigUint::parse_bytes(Z, 16).unwrap();
+    let mut group = c.benchmark_group("verify");
+    group.sample_size(10);
+    for (name, strauss) in [("affine", false), ("affine_strauss", true)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                assert!(affine_verify(
+                    &point,
+                    black_box(&z),
+                    &signature.get_r().to_biguint(),
+                    &signature.get_s().to_biguint(),
+                    strauss
+                ))
+            })
+        });
+    }
+    group.bench_function("jacobian", |b| {
+        b.iter(|| assert!(point.verify(black_box(z.clone()), signature.clone())))
+    });
+    let precomputed = PrecomputedPoint::new(&point);
+    group.bench_function("precomputed", |b| {
+        b.iter(|| assert!(precomputed.verify(black_box(z.clone()), signature.clone())))
+    });
+    group.finish();
+}
+
+fn batch_verify(c: &mut Criterion) {
+    let private_key = PrivateKey::new(SECRET);
+    let point = *private_key.get_public_key();
+    let entries: Vec<_> = (0..64u32)
+        .map(|i| {
+            let z = BigUint::parse_bytes(Z, 16).unwrap() + i;
+            let signature = private_key.sign(z.to_str_radix(16).as_bytes());
+            (z, signature)
+        })
+        .collect();
+    let mut batch = BatchVerifier::new();
+    for (z, signature) in &entries {
+        batch.add(point, z.clone(), signature.clone());
+    }
+    let mut group = c.benchmark_group("batch_verify_64");
+    group.sample_size(10);
+    group.bench_function("single", |b| {
+        b.iter(|| {
+            for (z, signature) in &entries {
+                assert!(point.verify(z.clone(), signature.clone()));
+            }
+        })
+    });
+    group.bench_function("batch", |b| b.iter(|| assert!(batch.verify().is_ok())));
+
+    let (public_key, _) = XOnlyPublicKey::from_point(&point);
+    let messages: Vec<[u8; 32]> = (0..64u8).map(|i| [i; 32]).collect();
+    let signatures: Vec<_> = messages
+        .iter()
+        .map(|message| private_key.sign_schnorr(message, &[0; 32]))
+        .collect();
+    let mut batch = BatchVerifier::new();
+    for (message, signature) in messages.iter().zip(&signatures) {
+        batch.add_schnorr(public_key, message, *signature);
+    }
+    group.bench_function("schnorr_single", |b| {
+        b.iter(|| {
+            for (message, signature) in messages.iter().zip(&signatures) {
+                assert!(public_key.verify_schnorr(message, signature));
+            }
+        })
+    });
+    group.bench_function("schnorr_batch", |b| {
+        b.iter(|| assert!(batch.verify().is_ok()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, field_mul, scalar_mul, verify, batch_verify);
+criterion_main!(benches);
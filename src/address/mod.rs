@@ -0,0 +1,609 @@
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+use crate::base58::{self, Base58Error};
+use crate::bech32::{self, Bech32Error, Variant};
+use crate::hash::{hash160, Hash160, Hash256};
+use crate::network::Network;
+use crate::s256point::S256Point;
+use crate::schnorr::XOnlyPublicKey;
+use crate::script::Script;
+
+/// The maximum length of a witness program, from BIP141
+const MAX_PROGRAM_LENGTH: usize = 40;
+
+/// Errors that can occur when parsing an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    /// The address is not valid Base58Check
+    Base58(Base58Error),
+    /// The address is not valid Bech32 or Bech32m
+    Bech32(Bech32Error),
+    /// The decoded address does not hold a version byte and a 20 byte hash
+    InvalidLength(usize),
+    /// The version byte does not belong to any known network and address type
+    UnknownPrefix(u8),
+    /// The human readable part of a segwit address does not belong to any known network
+    UnknownHrp(String),
+    /// A segwit address has no data besides its checksum
+    MissingWitnessVersion,
+    /// The witness version is above 16
+    InvalidWitnessVersion(u8),
+    /// The witness program is not 2 to 40 bytes long, or not 20 or 32 bytes for version 0
+    InvalidWitnessProgramLength(usize),
+    /// The checksum is Bech32m for version 0, or Bech32 for a later version
+    InvalidChecksumVariant,
+}
+
+// Formats the AddressError
+impl Display for AddressError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            AddressError::Base58(err) => write!(f, "{}", err),
+            AddressError::Bech32(err) => write!(f, "{}", err),
+            AddressError::InvalidLength(len) => write!(f, "Invalid address length {}", len),
+            AddressError::UnknownPrefix(prefix) => {
+                write!(f, "Unknown address prefix 0x{:02x}", prefix)
+            }
+            AddressError::UnknownHrp(hrp) => {
+                write!(f, "Unknown address human readable part {:?}", hrp)
+            }
+            AddressError::MissingWitnessVersion => write!(f, "Missing witness version"),
+            AddressError::InvalidWitnessVersion(version) => {
+                write!(f, "Invalid witness version {}", version)
+            }
+            AddressError::InvalidWitnessProgramLength(len) => {
+                write!(f, "Invalid witness program length {}", len)
+            }
+            AddressError::InvalidChecksumVariant => {
+                write!(f, "Checksum variant does not match the witness version")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AddressError::Base58(err) => Some(err),
+            AddressError::Bech32(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Base58Error> for AddressError {
+    fn from(err: Base58Error) -> Self {
+        AddressError::Base58(err)
+    }
+}
+
+impl From<Bech32Error> for AddressError {
+    fn from(err: Bech32Error) -> Self {
+        AddressError::Bech32(err)
+    }
+}
+
+/// WitnessProgram is the version and program a segwit output commits to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WitnessProgram {
+    version: u8,
+    len: u8,
+    program: [u8; MAX_PROGRAM_LENGTH],
+}
+
+impl WitnessProgram {
+    /// Creates a witness program, checking the rules of BIP141
+    /// # Arguments
+    /// * `version` - The witness version, 0 to 16
+    /// * `program` - The program, 2 to 40 bytes long, and 20 or 32 bytes for version 0
+    /// # Returns
+    /// * `Result<WitnessProgram, AddressError>` - The witness program, or
+    ///   `AddressError::InvalidWitnessVersion` or
+    ///   `AddressError::InvalidWitnessProgramLength`
+    pub fn new(version: u8, program: &[u8]) -> std::result::Result<Self, AddressError> {
+        if version > 16 {
+            return Err(AddressError::InvalidWitnessVersion(version));
+        }
+        let len = program.len();
+        if !(2..=MAX_PROGRAM_LENGTH).contains(&len) || (version == 0 && len != 20 && len != 32) {
+            return Err(AddressError::InvalidWitnessProgramLength(len));
+        }
+        let mut bytes = [0; MAX_PROGRAM_LENGTH];
+        bytes[..len].copy_from_slice(program);
+        Ok(WitnessProgram {
+            version,
+            len: len as u8,
+            program: bytes,
+        })
+    }
+
+    pub fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn get_program(&self) -> &[u8] {
+        &self.program[..self.len as usize]
+    }
+
+    /// Returns the checksum variant of the version: Bech32 for version 0, and Bech32m
+    /// for later versions
+    fn variant(&self) -> Variant {
+        if self.version == 0 {
+            Variant::Bech32
+        } else {
+            Variant::Bech32m
+        }
+    }
+}
+
+/// Payload is what an address commits to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Payload {
+    /// The hash160 of a SEC encoded public key (P2PKH)
+    PubkeyHash(Hash160),
+    /// The hash160 of a redeem script (P2SH)
+    ScriptHash(Hash160),
+    /// A segwit witness program (P2WPKH, P2WSH, P2TR and future versions)
+    WitnessProgram(WitnessProgram),
+}
+
+/// Address is a Bitcoin address for a given network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address {
+    network: Network,
+    payload: Payload,
+}
+
+impl Address {
+    /// Creates a P2PKH address from a public key
+    /// # Arguments
+    /// * `point` - The public key
+    /// * `compressed` - Whether the public key is hashed in its compressed SEC format
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2PKH address
+    pub fn p2pkh(point: &S256Point, compressed: bool, network: Network) -> Self {
+        Address {
+            network,
+            payload: Payload::PubkeyHash(point.hash160(compressed)),
+        }
+    }
+
+    /// Creates a P2SH address from the hash160 of a redeem script
+    /// # Arguments
+    /// * `script_hash` - The hash160 of the redeem script
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2SH address
+    pub fn p2sh(script_hash: Hash160, network: Network) -> Self {
+        Address {
+            network,
+            payload: Payload::ScriptHash(script_hash),
+        }
+    }
+
+    /// Creates a P2SH address wrapping a P2WPKH output of a public key (P2SH-P2WPKH)
+    /// The redeem script is OP_0 followed by the hash160 of the compressed SEC key
+    /// # Arguments
+    /// * `point` - The public key
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2SH-P2WPKH address
+    pub fn p2sh_p2wpkh(point: &S256Point, network: Network) -> Self {
+        let redeem_script = Script::witness_program(0, point.hash160(true).as_bytes());
+        Self::p2sh(hash160(&redeem_script.raw_serialize()), network)
+    }
+
+    /// Creates a native segwit P2WPKH address from a public key
+    /// The witness program is the hash160 of the compressed SEC key
+    /// # Arguments
+    /// * `point` - The public key
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2WPKH address
+    pub fn p2wpkh(point: &S256Point, network: Network) -> Self {
+        let program = WitnessProgram::new(0, point.hash160(true).as_bytes())
+            .expect("A 20 byte version 0 program is valid");
+        Address {
+            network,
+            payload: Payload::WitnessProgram(program),
+        }
+    }
+
+    /// Creates a native segwit P2WSH address from the sha256 of a witness script
+    /// # Arguments
+    /// * `script_hash` - The single sha256 of the serialized witness script
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2WSH address
+    pub fn p2wsh(script_hash: Hash256, network: Network) -> Self {
+        let program = WitnessProgram::new(0, script_hash.as_bytes())
+            .expect("A 32 byte version 0 program is valid");
+        Address {
+            network,
+            payload: Payload::WitnessProgram(program),
+        }
+    }
+
+    /// Creates a segwit version 1 P2TR address from a taproot output key
+    /// The key is used as given, so any BIP341 tweak must already be applied to it
+    /// # Arguments
+    /// * `output_key` - The x-only taproot output key
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2TR address
+    pub fn p2tr(output_key: &XOnlyPublicKey, network: Network) -> Self {
+        let program = WitnessProgram::new(1, &output_key.serialize())
+            .expect("A 32 byte version 1 program is valid");
+        Address {
+            network,
+            payload: Payload::WitnessProgram(program),
+        }
+    }
+
+    /// Returns the script_pubkey that locks outputs paying to the address
+    pub fn script_pubkey(&self) -> Script {
+        match &self.payload {
+            Payload::PubkeyHash(hash) => Script::p2pkh(hash),
+            Payload::ScriptHash(hash) => Script::p2sh(hash),
+            Payload::WitnessProgram(program) => {
+                Script::witness_program(program.get_version(), program.get_program())
+            }
+        }
+    }
+
+    /// Returns the network of the address
+    pub fn get_network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the payload of the address
+    pub fn get_payload(&self) -> &Payload {
+        &self.payload
+    }
+}
+
+// Formats the Address in Base58Check, or in lower case Bech32 or Bech32m for segwit
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let (prefix, hash) = match &self.payload {
+            Payload::PubkeyHash(hash) => (self.network.p2pkh_prefix(), hash),
+            Payload::ScriptHash(hash) => (self.network.p2sh_prefix(), hash),
+            Payload::WitnessProgram(program) => {
+                let mut data = vec![program.get_version()];
+                data.extend(
+                    bech32::convert_bits(program.get_program(), 8, 5, true)
+                        .expect("Padding is always valid"),
+                );
+                let hrp = self.network.bech32_hrp();
+                return write!(f, "{}", bech32::encode(hrp, &data, program.variant()));
+            }
+        };
+        let mut data = vec![prefix];
+        data.extend_from_slice(hash.as_bytes());
+        write!(f, "{}", base58::encode_check(&data))
+    }
+}
+
+// Parses an Address from its Base58Check, Bech32 or Bech32m string, detecting the network
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        for network in [Network::Mainnet, Network::Testnet] {
+            if lower.rsplit_once('1').map(|(hrp, _)| hrp) == Some(network.bech32_hrp()) {
+                return parse_segwit(s, network);
+            }
+        }
+
+        let data = match base58::decode_check(s) {
+            Ok(data) => data,
+            // A valid Bech32 string is a segwit address for a network we do not know
+            Err(err) => match bech32::decode(s) {
+                Ok((hrp, _, _)) => return Err(AddressError::UnknownHrp(hrp)),
+                Err(_) => return Err(err.into()),
+            },
+        };
+        if data.len() != 21 {
+            return Err(AddressError::InvalidLength(data.len()));
+        }
+        let hash = Hash160::from_slice(&data[1..]).expect("length checked above");
+
+        for network in [Network::Mainnet, Network::Testnet] {
+            if data[0] == network.p2pkh_prefix() {
+                return Ok(Address {
+                    network,
+                    payload: Payload::PubkeyHash(hash),
+                });
+            }
+            if data[0] == network.p2sh_prefix() {
+                return Ok(Address {
+                    network,
+                    payload: Payload::ScriptHash(hash),
+                });
+            }
+        }
+        Err(AddressError::UnknownPrefix(data[0]))
+    }
+}
+
+/// Parses a segwit address whose human readable part is that of the network, following
+/// the rules of BIP173 and BIP350
+fn parse_segwit(s: &str, network: Network) -> std::result::Result<Address, AddressError> {
+    let (_, data, variant) = bech32::decode(s)?;
+    let (&version, data) = data
+        .split_first()
+        .ok_or(AddressError::MissingWitnessVersion)?;
+    let program = bech32::convert_bits(data, 5, 8, false)?;
+    let program = WitnessProgram::new(version, &program)?;
+    if variant != program.variant() {
+        return Err(AddressError::InvalidChecksumVariant);
+    }
+    Ok(Address {
+        network,
+        payload: Payload::WitnessProgram(program),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::sha256;
+    use crate::hex;
+    use num_bigint::BigUint;
+
+    fn decode_hash160(s: &str) -> Hash160 {
+        Hash160::from_slice(&hex::decode(s).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_p2pkh() {
+        let cases = vec![
+            (
+                BigUint::from(5002u32),
+                false,
+                Network::Testnet,
+                "mmTPbXQFxboEtNRkwfh6K51jvdtHLxGeMA",
+            ),
+            (
+                BigUint::from(2020u32).pow(5),
+                true,
+                Network::Testnet,
+                "mopVkxp8UhXqRYbCYJsbeE1h1fiF64jcoH",
+            ),
+            (
+                BigUint::parse_bytes(b"12345deadbeef", 16).unwrap(),
+                true,
+                Network::Mainnet,
+                "1F1Pn2y6pDb68E5nYJJeba4TLg2U7B6KF1",
+            ),
+        ];
+
+        for (secret, compressed, network, expected) in cases {
+            let point = S256Point::generator() * secret;
+            let address = point.address(compressed, network);
+            assert_eq!(address.to_string(), expected);
+            assert_eq!(expected.parse::<Address>(), Ok(address));
+        }
+    }
+
+    #[test]
+    fn test_from_hash160() {
+        let hash = decode_hash160("74d691da1574e6b3c192ecfb52cc8984ee7b6c56");
+        let cases = vec![
+            (
+                Payload::PubkeyHash(hash),
+                Network::Mainnet,
+                "1BenRpVUFK65JFWcQSuHnJKzc4M8ZP8Eqa",
+            ),
+            (
+                Payload::PubkeyHash(hash),
+                Network::Testnet,
+                "mrAjisaT4LXL5MzE81sfcDYKU3wqWSvf9q",
+            ),
+            (
+                Payload::ScriptHash(hash),
+                Network::Mainnet,
+                "3CLoMMyuoDQTPRD3XYZtCvgvkadrAdvdXh",
+            ),
+            (
+                Payload::ScriptHash(hash),
+                Network::Testnet,
+                "2N3u1R6uwQfuobCqbCgBkpsgBxvr1tZpe7B",
+            ),
+        ];
+
+        for (payload, network, expected) in cases {
+            let address = Address { network, payload };
+            assert_eq!(address.to_string(), expected);
+            let parsed: Address = expected.parse().unwrap();
+            assert_eq!(parsed.get_network(), network);
+            assert_eq!(parsed.get_payload(), &payload);
+        }
+        assert_eq!(
+            Address::p2sh(hash, Network::Mainnet).to_string(),
+            "3CLoMMyuoDQTPRD3XYZtCvgvkadrAdvdXh"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "3CLoMMyuoDQTPRD3XYZtCvgvkadrAdvdXi".parse::<Address>(),
+            Err(AddressError::Base58(Base58Error::InvalidChecksum))
+        );
+        assert_eq!(
+            base58::encode_check(&[0x00; 20]).parse::<Address>(),
+            Err(AddressError::InvalidLength(20))
+        );
+        assert_eq!(
+            base58::encode_check(&[0x42; 21]).parse::<Address>(),
+            Err(AddressError::UnknownPrefix(0x42))
+        );
+    }
+
+    #[test]
+    fn test_segwit_vectors() {
+        // The valid segwit addresses of BIP173 and BIP350 for mainnet and testnet, with
+        // their script_pubkeys
+        let cases = [
+            (
+                "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+                Network::Mainnet,
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                Network::Testnet,
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                Network::Mainnet,
+                "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            ("BC1SW50QGDZ25J", Network::Mainnet, "6002751e"),
+            (
+                "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                Network::Mainnet,
+                "5210751e76e8199196d454941c45d1b3a323",
+            ),
+            (
+                "tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy",
+                Network::Testnet,
+                "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+            (
+                "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+                Network::Testnet,
+                "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                Network::Mainnet,
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+        ];
+
+        for (s, network, script_pubkey) in cases {
+            let address: Address = s.parse().unwrap();
+            assert_eq!(address.get_network(), network);
+            assert_eq!(
+                hex::encode(&address.script_pubkey().raw_serialize()),
+                script_pubkey
+            );
+            assert_eq!(address.to_string(), s.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_segwit_invalid() {
+        // The invalid segwit addresses of BIP173 and BIP350
+        let cases = [
+            (
+                "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+                AddressError::UnknownHrp(String::from("tc")),
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+                AddressError::InvalidChecksumVariant,
+            ),
+            (
+                "tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+                AddressError::InvalidChecksumVariant,
+            ),
+            (
+                "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+                AddressError::InvalidChecksumVariant,
+            ),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+                AddressError::InvalidChecksumVariant,
+            ),
+            (
+                "tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+                AddressError::InvalidChecksumVariant,
+            ),
+            (
+                "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+                AddressError::Bech32(Bech32Error::InvalidCharacter {
+                    character: 'o',
+                    index: 59,
+                }),
+            ),
+            (
+                "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+                AddressError::InvalidWitnessVersion(17),
+            ),
+            ("bc1pw5dgrnzv", AddressError::InvalidWitnessProgramLength(1)),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+                AddressError::InvalidWitnessProgramLength(41),
+            ),
+            (
+                "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+                AddressError::InvalidWitnessProgramLength(16),
+            ),
+            (
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+                AddressError::Bech32(Bech32Error::MixedCase),
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+                AddressError::Bech32(Bech32Error::InvalidPadding),
+            ),
+            (
+                "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+                AddressError::Bech32(Bech32Error::InvalidPadding),
+            ),
+            ("bc1gmk9yu", AddressError::MissingWitnessVersion),
+            (
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+                AddressError::Bech32(Bech32Error::InvalidChecksum),
+            ),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<Address>(), Err(expected), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_segwit_constructors() {
+        let point = S256Point::generator()
+            * BigUint::parse_bytes(
+                b"8b387de39861728c92ec9f589c303b1038ff60eb3963b12cd212263a1d1e0f00",
+                16,
+            )
+            .unwrap();
+        assert_eq!(
+            Address::p2wpkh(&point, Network::Mainnet).to_string(),
+            "bc1qccfx56j0spl2jpah3yrwzp7eqd2pp0scryq4a8"
+        );
+        let (output_key, _) = XOnlyPublicKey::from_point(&point);
+        assert_eq!(
+            Address::p2tr(&output_key, Network::Mainnet).to_string(),
+            "bc1pq2xsq04t9epg6yvc8ulf0sl6ptwl8dp8gr0s6gghjhlm8030d3fqxjg59y"
+        );
+
+        let witness_script =
+            hex::decode("21026a3e3f1c8d7ab2a8a3e9e62f3b0e1f9b8d6c5e4f3a2b1c0d9e8f7a6b5c4d3e2fac")
+                .unwrap();
+        let address = Address::p2wsh(sha256(&witness_script), Network::Testnet);
+        assert_eq!(
+            address.to_string(),
+            "tb1qnyrkyp70egcggxnrqcrt65azrxtfat7aa7n8tlcv993dhvltt3qqersam6"
+        );
+        assert_eq!(address.to_string().parse(), Ok(address));
+
+        assert_eq!(
+            WitnessProgram::new(17, &[0; 32]),
+            Err(AddressError::InvalidWitnessVersion(17))
+        );
+        assert_eq!(
+            WitnessProgram::new(0, &[0; 24]),
+            Err(AddressError::InvalidWitnessProgramLength(24))
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use num_bigint::BigUint;
+use std::fmt::{Display, Formatter, Result};
+
+use crate::hash::hash256;
+
+/// The Bitcoin Base58 alphabet, which leaves out 0, O, I and l
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Errors that can occur when decoding a Base58 or Base58Check string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Base58Error {
+    /// The string contains a character outside the Base58 alphabet
+    InvalidCharacter { character: char, index: usize },
+    /// The checksum does not match the payload
+    InvalidChecksum,
+    /// The decoded data is too short to contain a checksum
+    InvalidLength(usize),
+}
+
+// Formats the Base58Error
+impl Display for Base58Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Base58Error::InvalidCharacter { character, index } => write!(
+                f,
+                "Invalid Base58 character {:?} at position {}",
+                character, index
+            ),
+            Base58Error::InvalidChecksum => write!(f, "Invalid Base58Check checksum"),
+            Base58Error::InvalidLength(len) => {
+                write!(f, "Invalid Base58Check data length {}", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Base58Error {}
+
+/// Encodes bytes in Base58, keeping each leading zero byte as a '1'
+/// # Arguments
+/// * `data` - The bytes to encode
+/// # Returns
+/// * `String` - The Base58 encoded string
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    let mut num = BigUint::from_bytes_be(data);
+    let base = BigUint::from(58u32);
+
+    let mut result = Vec::new();
+    while num > BigUint::ZERO {
+        let remainder = (&num % &base).to_u32_digits().first().copied().unwrap_or(0);
+        result.push(ALPHABET[remainder as usize]);
+        num /= &base;
+    }
+    result.extend(std::iter::repeat_n(ALPHABET[0], zeros));
+    result.reverse();
+
+    String::from_utf8(result).expect("The Base58 alphabet is ASCII")
+}
+
+/// Decodes a Base58 string, turning each leading '1' back into a zero byte
+/// # Arguments
+/// * `s` - The Base58 encoded string
+/// # Returns
+/// * `Result<Vec<u8>, Base58Error>` - The decoded bytes, or `Base58Error::InvalidCharacter`
+pub fn decode(s: &str) -> std::result::Result<Vec<u8>, Base58Error> {
+    let zeros = s.bytes().take_while(|&c| c == ALPHABET[0]).count();
+    let mut num = BigUint::ZERO;
+
+    for (index, character) in s.chars().enumerate() {
+        let digit = ALPHABET
+            .iter()
+            .position(|&c| c as char == character)
+            .ok_or(Base58Error::InvalidCharacter { character, index })?;
+        num = num * 58u32 + digit as u32;
+    }
+
+    let mut result = vec![0u8; zeros];
+    if num > BigUint::ZERO {
+        result.extend(num.to_bytes_be());
+    }
+    Ok(result)
+}
+
+/// Encodes bytes in Base58Check, appending the first four bytes of their double SHA-256
+/// # Arguments
+/// * `data` - The bytes to encode, usually a version byte followed by a payload
+/// # Returns
+/// * `String` - The Base58Check encoded string
+pub fn encode_check(data: &[u8]) -> String {
+    let mut payload = data.to_vec();
+    payload.extend_from_slice(&checksum(data));
+    encode(&payload)
+}
+
+/// Decodes a Base58Check string and verifies its checksum
+/// # Arguments
+/// * `s` - The Base58Check encoded string
+/// # Returns
+/// * `Result<Vec<u8>, Base58Error>` - The decoded bytes without the checksum, or why they are invalid
+pub fn decode_check(s: &str) -> std::result::Result<Vec<u8>, Base58Error> {
+    let decoded = decode(s)?;
+    if decoded.len() < 4 {
+        return Err(Base58Error::InvalidLength(decoded.len()));
+    }
+
+    let (data, check) = decoded.split_at(decoded.len() - 4);
+    if checksum(data) != check {
+        return Err(Base58Error::InvalidChecksum);
+    }
+    Ok(data.to_vec())
+}
+
+/// Returns the first four bytes of the double SHA-256 of the data
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = hash256(data);
+    let bytes = hash.as_bytes();
+    [bytes[0], bytes[1], bytes[2], bytes[3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_hex(s: &[u8]) -> Vec<u8> {
+        crate::hex::decode(std::str::from_utf8(s).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_encode() {
+        let cases: Vec<(&[u8], &str)> = vec![
+            (
+                b"7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+                "9MA8fRQrT4u8Zj8ZRd6MAiiyaxb2Y1CMpvVkHQu5hVM6",
+            ),
+            (
+                b"eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c",
+                "4fE3H2E6XMp4SsxtwinF7w9a34ooUrwWe4WsW1458Pd",
+            ),
+            (
+                b"c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab6",
+                "EQJsjkd6JaGwxrjEhfeqPenqHwrBmPQZjJGNSCHBkcF7",
+            ),
+        ];
+
+        for (hex, expected) in cases {
+            let data = decode_hex(hex);
+            assert_eq!(encode(&data), expected);
+            assert_eq!(decode(expected).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_leading_zeros() {
+        assert_eq!(encode(&[]), "");
+        assert_eq!(encode(&[0, 0]), "11");
+        assert_eq!(encode(&[0, 0, 1]), "112");
+        assert_eq!(decode("").unwrap(), Vec::<u8>::new());
+        assert_eq!(decode("11").unwrap(), vec![0, 0]);
+        assert_eq!(decode("112").unwrap(), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut data = vec![0x00];
+        data.extend(decode_hex(b"f54a5851e9372b87810a8e60cdd2e7cfd80b6e31"));
+        assert_eq!(encode_check(&data), "1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs");
+        assert_eq!(
+            decode_check("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs").unwrap(),
+            data
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            decode("12O3"),
+            Err(Base58Error::InvalidCharacter {
+                character: 'O',
+                index: 2
+            })
+        );
+        assert_eq!(
+            decode_check("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAt"),
+            Err(Base58Error::InvalidChecksum)
+        );
+        assert_eq!(decode_check("111"), Err(Base58Error::InvalidLength(3)));
+        assert_eq!(
+            decode_check("1PMycacnJaSqwwJqjawXBErnLsZ7RkXUA0"),
+            Err(Base58Error::InvalidCharacter {
+                character: '0',
+                index: 33
+            })
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::Error;
+
+/// The standard Base64 alphabet from RFC 4648
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes in standard Base64, padded with '='
+/// # Arguments
+/// * `data` - The bytes to encode
+/// # Returns
+/// * `String` - Four characters for every three bytes, or part of them
+pub fn encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let mut group = [0u8; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+/// Decodes a padded standard Base64 string into bytes
+/// # Arguments
+/// * `s` - The Base64 string, with a length that is a multiple of four
+/// # Returns
+/// * `Result<Vec<u8>, Error>` - The decoded bytes, or `Error::InvalidBase64`
+pub fn decode(s: &str) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::InvalidBase64(s.to_string());
+    let bytes = s.as_bytes();
+    if !bytes.len().is_multiple_of(4) {
+        return Err(invalid());
+    }
+    let padding = bytes.iter().rev().take_while(|&&c| c == b'=').count();
+    if padding > 2 {
+        return Err(invalid());
+    }
+
+    let mut result = Vec::with_capacity(bytes.len() / 4 * 3);
+    let groups = bytes.len() / 4;
+    for (index, chunk) in bytes.chunks(4).enumerate() {
+        let last = index + 1 == groups;
+        let digits = if last { 4 - padding } else { 4 };
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = if i < digits {
+                ALPHABET.iter().position(|&a| a == c).ok_or_else(invalid)? as u32
+            } else {
+                0
+            };
+            bits = bits << 6 | value;
+        }
+        let group = bits.to_be_bytes();
+        let len = digits - 1;
+        // The bits left over after the last byte must be zero, so every string has
+        // exactly one encoding
+        if group[1 + len..].iter().any(|&byte| byte != 0) {
+            return Err(invalid());
+        }
+        result.extend_from_slice(&group[1..1 + len]);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        // The test vectors from RFC 4648 section 10
+        let cases = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in cases {
+            assert_eq!(encode(data.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), data.as_bytes());
+        }
+        let data: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode(&encode(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        for s in [
+            "Zg=", "Zg", "Z===", "Zm9v!A==", "Zh==", "Zm9=", "Zg==Zg==", "====",
+        ] {
+            assert_eq!(
+                decode(s),
+                Err(Error::InvalidBase64(String::from(s))),
+                "{}",
+                s
+            );
+        }
+    }
+}
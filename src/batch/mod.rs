@@ -0,0 +1,367 @@
+use num_bigint::BigUint;
+
+use crate::fe256::Fe256;
+use crate::hash::{sha256, tagged_hash};
+use crate::jacobian::JacobianPoint;
+use crate::s256point::S256Point;
+use crate::scalar::Scalar;
+use crate::schnorr::{challenge, SchnorrSignature, XOnlyPublicKey};
+use crate::signature::Signature;
+
+/// BatchVerifier collects ECDSA and BIP340 Schnorr signatures and verifies them together
+/// ECDSA signatures cannot be folded into a single equation, because r only gives the
+/// x coordinate of the nonce point, so the batch shares work instead:
+/// * one scalar inversion for all the s values, with Montgomery's trick
+/// * one Strauss multi-scalar multiplication per entry for u * G + v * P, sharing the
+///   doublings between both terms
+/// * a check of the x coordinate in Jacobian coordinates, without any field inversion
+///
+/// Schnorr signatures fix R completely, so they are checked with a single random linear
+/// combination of their equations, as described in BIP340:
+/// (a_1 s_1 + ... + a_u s_u) G = a_1 R_1 + a_1 e_1 P_1 + ... + a_u R_u + a_u e_u P_u
+/// with a_1 = 1 and the other a_i 128-bit values derived from a hash of the whole batch. If the
+/// combination fails, the Schnorr signatures are checked one by one to find the invalid ones
+#[derive(Debug, Clone, Default)]
+pub struct BatchVerifier {
+    entries: Vec<Entry>,
+}
+
+/// A signature waiting in the batch
+#[derive(Debug, Clone)]
+enum Entry {
+    Ecdsa(S256Point, BigUint, Signature),
+    Schnorr(XOnlyPublicKey, Vec<u8>, SchnorrSignature),
+}
+
+impl BatchVerifier {
+    pub fn new() -> BatchVerifier {
+        BatchVerifier::default()
+    }
+
+    /// Adds an ECDSA signature to the batch
+    /// # Arguments
+    /// * `public_key` - The public key the signature must verify against
+    /// * `z` - The hash of the message
+    /// * `signature` - The signature
+    pub fn add(&mut self, public_key: S256Point, z: BigUint, signature: Signature) {
+        self.entries.push(Entry::Ecdsa(public_key, z, signature));
+    }
+
+    /// Adds a BIP340 Schnorr signature to the batch
+    /// # Arguments
+    /// * `public_key` - The x-only public key the signature must verify against
+    /// * `message` - The signed message
+    /// * `signature` - The signature
+    pub fn add_schnorr(
+        &mut self,
+        public_key: XOnlyPublicKey,
+        message: &[u8],
+        signature: SchnorrSignature,
+    ) {
+        self.entries
+            .push(Entry::Schnorr(public_key, message.to_vec(), signature));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Verifies every signature in the batch
+    /// # Returns
+    /// * `Result<(), Vec<usize>>` - Ok if all the signatures are valid, or the indexes of
+    ///   the invalid ones, in the order they were added
+    pub fn verify(&self) -> Result<(), Vec<usize>> {
+        let mut ecdsa = vec![];
+        let mut schnorr = vec![];
+        for (index, entry) in self.entries.iter().enumerate() {
+            match entry {
+                Entry::Ecdsa(public_key, z, signature) => {
+                    ecdsa.push((index, public_key, z, signature))
+                }
+                Entry::Schnorr(public_key, message, signature) => {
+                    schnorr.push((index, public_key, message.as_slice(), signature))
+                }
+            }
+        }
+
+        let mut invalid = verify_ecdsa(&ecdsa);
+        invalid.extend(verify_schnorr(&schnorr));
+        invalid.sort_unstable();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
+    }
+}
+
+/// Verifies the ECDSA entries, returning the indexes of the invalid ones
+fn verify_ecdsa(entries: &[(usize, &S256Point, &BigUint, &Signature)]) -> Vec<usize> {
+    let s_inverses = batch_invert(
+        &entries
+            .iter()
+            .map(|(_, _, _, signature)| *signature.get_s())
+            .collect::<Vec<_>>(),
+    );
+
+    entries
+        .iter()
+        .zip(s_inverses)
+        .filter(|((_, public_key, z, signature), s_inv)| {
+            let r = *signature.get_r();
+            if r.is_zero() || s_inv.is_zero() || public_key.is_infinity() {
+                return true;
+            }
+            let u = Scalar::from_biguint_reduced(z) * *s_inv;
+            let v = r * *s_inv;
+            let total = JacobianPoint::multi_mul_with_generator(
+                &u,
+                &[(v, JacobianPoint::from_affine(public_key))],
+            );
+            !has_x_mod_n(&total, &r)
+        })
+        .map(|((index, _, _, _), _)| *index)
+        .collect()
+}
+
+/// Verifies the Schnorr entries with one random linear combination, returning the
+/// indexes of the invalid ones
+fn verify_schnorr(entries: &[(usize, &XOnlyPublicKey, &[u8], &SchnorrSignature)]) -> Vec<usize> {
+    if entries.is_empty() {
+        return vec![];
+    }
+
+    // Seed the coefficients with a hash of the whole batch, so that a forger cannot choose
+    // signatures whose errors cancel out
+    let mut batch_data = vec![];
+    for (_, public_key, message, signature) in entries {
+        batch_data.extend_from_slice(&public_key.serialize());
+        batch_data.extend_from_slice(&signature.serialize());
+        batch_data.extend_from_slice(sha256(message).as_bytes());
+    }
+    let seed = tagged_hash("BIP0340/batch", &batch_data);
+
+    let mut s_sum = Scalar::ZERO;
+    let mut terms = Vec::with_capacity(2 * entries.len());
+    for (i, (_, public_key, message, signature)) in entries.iter().enumerate() {
+        let mut r_sec = vec![0x02];
+        r_sec.extend_from_slice(&signature.get_r().to_bytes());
+        let Ok(nonce_point) = S256Point::parse_sec(&r_sec) else {
+            // r is not the x coordinate of a point, so that signature alone fails
+            return individually(entries);
+        };
+        let a = if i == 0 {
+            Scalar::ONE
+        } else {
+            let mut data = seed.as_bytes().to_vec();
+            data.extend_from_slice(&(i as u32).to_be_bytes());
+            // 128 bits are enough to make a cancellation as unlikely as a forgery, and
+            // halve the additions for the nonce points
+            let mut bytes = *sha256(&data).as_bytes();
+            bytes[..16].fill(0);
+            Scalar::from_bytes_reduced(&bytes)
+        };
+        let e = challenge(signature.get_r(), &public_key.serialize(), message);
+
+        s_sum = s_sum + a * *signature.get_s();
+        terms.push((a, JacobianPoint::from_affine(&nonce_point)));
+        terms.push((a * e, JacobianPoint::from_affine(&public_key.to_point())));
+    }
+
+    if JacobianPoint::multi_mul_with_generator(&-s_sum, &terms).is_infinity() {
+        vec![]
+    } else {
+        individually(entries)
+    }
+}
+
+/// Verifies the Schnorr entries one by one, returning the indexes of the invalid ones
+fn individually(entries: &[(usize, &XOnlyPublicKey, &[u8], &SchnorrSignature)]) -> Vec<usize> {
+    entries
+        .iter()
+        .filter(|(_, public_key, message, signature)| {
+            !public_key.verify_schnorr(message, signature)
+        })
+        .map(|(index, _, _, _)| *index)
+        .collect()
+}
+
+/// Inverts every scalar with a single inversion (Montgomery's trick)
+/// Zero scalars are left as zero, without spoiling the inverses of the others
+fn batch_invert(scalars: &[Scalar]) -> Vec<Scalar> {
+    // prefix[i] is the product of the non-zero scalars before index i
+    let mut prefix = Vec::with_capacity(scalars.len());
+    let mut product = Scalar::ONE;
+    for scalar in scalars {
+        prefix.push(product);
+        if !scalar.is_zero() {
+            product = product * *scalar;
+        }
+    }
+
+    let mut inverse = product.invert();
+    let mut result = vec![Scalar::ZERO; scalars.len()];
+    for i in (0..scalars.len()).rev() {
+        if !scalars[i].is_zero() {
+            result[i] = inverse * prefix[i];
+            inverse = inverse * scalars[i];
+        }
+    }
+    result
+}
+
+/// Returns whether the affine x coordinate of the point, reduced modulo n, equals r
+/// Since n < p, x can be either r or r + n when r + n is still below p
+fn has_x_mod_n(point: &JacobianPoint, r: &Scalar) -> bool {
+    let x = Fe256::from_bytes(&r.to_bytes()).expect("n is below p");
+    if point.has_x(&x) {
+        return true;
+    }
+    let n = BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap();
+    match Fe256::from_biguint(&(r.to_biguint() + n)) {
+        Some(x) => point.has_x(&x),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private_key::PrivateKey;
+
+    #[test]
+    fn test_batch_invert() {
+        let scalars = [
+            Scalar::from_u64(3),
+            Scalar::ZERO,
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&[0x77; 32]),
+        ];
+        let inverses = batch_invert(&scalars);
+        for (scalar, inverse) in scalars.iter().zip(&inverses) {
+            assert_eq!(*inverse, scalar.invert());
+        }
+        assert!(batch_invert(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut batch = BatchVerifier::new();
+        assert!(batch.is_empty());
+        assert_eq!(batch.verify(), Ok(()));
+
+        for secret in [b"1".as_slice(), b"deadbeef", b"12345"] {
+            let private_key = PrivateKey::new(secret);
+            for z in 1..4u32 {
+                let z = BigUint::from(z) << 200u32;
+                let signature = private_key.sign_biguint(z.clone(), None);
+                batch.add(*private_key.get_public_key(), z, signature);
+            }
+        }
+        assert_eq!(batch.len(), 9);
+        assert_eq!(batch.verify(), Ok(()));
+
+        let private_key = PrivateKey::new(b"abc");
+        let z = BigUint::from(42u32);
+        let signature = private_key.sign_biguint(z.clone(), None);
+        // Wrong hash
+        batch.add(
+            *private_key.get_public_key(),
+            z.clone() + 1u32,
+            signature.clone(),
+        );
+        // Wrong key
+        batch.add(S256Point::generator(), z.clone(), signature.clone());
+        // Zero s
+        batch.add(
+            *private_key.get_public_key(),
+            z.clone(),
+            Signature::new(*signature.get_r(), Scalar::ZERO),
+        );
+        batch.add(*private_key.get_public_key(), z, signature);
+        assert_eq!(batch.verify(), Err(vec![9, 10, 11]));
+    }
+
+    #[test]
+    fn test_verify_schnorr() {
+        let mut batch = BatchVerifier::new();
+        let mut keys = vec![];
+        for secret in [b"1".as_slice(), b"deadbeef", b"12345", b"abc"] {
+            let private_key = PrivateKey::new(secret);
+            let (public_key, _) = XOnlyPublicKey::from_point(private_key.get_public_key());
+            keys.push(public_key);
+            for message in [b"".as_slice(), b"hello", &[0x99; 100]] {
+                let signature = private_key.sign_schnorr(message, &[7; 32]);
+                batch.add_schnorr(public_key, message, signature);
+            }
+        }
+        assert_eq!(batch.len(), 12);
+        assert_eq!(batch.verify(), Ok(()));
+
+        // Mixed with ECDSA
+        let private_key = PrivateKey::new(b"abc");
+        let z = BigUint::from(42u32);
+        let signature = private_key.sign_biguint(z.clone(), None);
+        batch.add(*private_key.get_public_key(), z, signature);
+        assert_eq!(batch.verify(), Ok(()));
+
+        let signature = PrivateKey::new(b"1").sign_schnorr(b"hello", &[0; 32]);
+        // Wrong message
+        batch.add_schnorr(keys[0], b"hellO", signature);
+        // Wrong key
+        batch.add_schnorr(keys[1], b"hello", signature);
+        // r is not an x coordinate
+        let bad_r = SchnorrSignature::new(Fe256::from_u64(5), *signature.get_s());
+        batch.add_schnorr(keys[0], b"hello", bad_r);
+        batch.add_schnorr(keys[0], b"hello", signature);
+        assert_eq!(batch.verify(), Err(vec![13, 14, 15]));
+    }
+
+    #[test]
+    fn test_schnorr_errors_do_not_cancel() {
+        // Two invalid signatures whose errors cancel in an unweighted sum
+        let private_key = PrivateKey::new(b"1");
+        let (public_key, _) = XOnlyPublicKey::from_point(private_key.get_public_key());
+        let first = private_key.sign_schnorr(b"a", &[0; 32]);
+        let second = private_key.sign_schnorr(b"b", &[0; 32]);
+        let delta = Scalar::from_u64(1000);
+        let mut batch = BatchVerifier::new();
+        batch.add_schnorr(
+            public_key,
+            b"a",
+            SchnorrSignature::new(*first.get_r(), *first.get_s() + delta),
+        );
+        batch.add_schnorr(
+            public_key,
+            b"b",
+            SchnorrSignature::new(*second.get_r(), *second.get_s() - delta),
+        );
+        assert_eq!(batch.verify(), Err(vec![0, 1]));
+    }
+
+    #[test]
+    fn test_verify_matches_single() {
+        let private_key =
+            PrivateKey::new(b"8b387de39861728c92ec9f589c303b1038ff60eb3963b12cd212263a1d1e0f00");
+        let point = *private_key.get_public_key();
+        let mut batch = BatchVerifier::new();
+        let mut expected = vec![];
+        for i in 0..6u32 {
+            let z = BigUint::from(1000 + i);
+            let mut signature = private_key.sign_biguint(z.clone(), None);
+            if i % 2 == 1 {
+                signature = Signature::new(*signature.get_r(), -*signature.get_s() + Scalar::ONE);
+            }
+            if !point.verify(z.clone(), signature.clone()) {
+                expected.push(i as usize);
+            }
+            batch.add(point, z, signature);
+        }
+        assert_eq!(expected, vec![1, 3, 5]);
+        assert_eq!(batch.verify(), Err(expected));
+    }
+}
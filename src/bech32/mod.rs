@@ -0,0 +1,318 @@
+use std::fmt::{Display, Formatter, Result};
+
+/// The Bech32 alphabet, indexed by 5-bit value, which leaves out 1, b, i and o
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The generator of the BCH code behind the checksum
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// The separator between the human readable part and the data
+const SEPARATOR: char = '1';
+
+/// The maximum length of a whole string, from BIP173
+const MAX_LENGTH: usize = 90;
+
+/// The number of 5-bit values in the checksum
+const CHECKSUM_LENGTH: usize = 6;
+
+/// Variant is the checksum a string uses: Bech32 (BIP173) for segwit version 0, and
+/// Bech32m (BIP350) for later versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    /// Returns the constant the checksum polymod must end at
+    fn constant(&self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+}
+
+/// Errors that can occur when decoding a Bech32 or Bech32m string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bech32Error {
+    /// The string is longer than 90 characters
+    InvalidLength(usize),
+    /// The string mixes upper and lower case letters
+    MixedCase,
+    /// The string has no '1' separating the human readable part from the data
+    MissingSeparator,
+    /// The human readable part is empty or has a character outside ASCII 33 to 126
+    InvalidHrp,
+    /// The data part has a character outside the Bech32 alphabet
+    InvalidCharacter { character: char, index: usize },
+    /// The data part is shorter than the six character checksum
+    MissingChecksum,
+    /// The checksum is neither a valid Bech32 nor a valid Bech32m checksum
+    InvalidChecksum,
+    /// Regrouping 5-bit values into bytes left more than 4 bits, or nonzero bits
+    InvalidPadding,
+}
+
+// Formats the Bech32Error
+impl Display for Bech32Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Bech32Error::InvalidLength(len) => write!(f, "Invalid Bech32 string length {}", len),
+            Bech32Error::MixedCase => write!(f, "Bech32 string mixes upper and lower case"),
+            Bech32Error::MissingSeparator => write!(f, "Bech32 string has no separator"),
+            Bech32Error::InvalidHrp => write!(f, "Invalid Bech32 human readable part"),
+            Bech32Error::InvalidCharacter { character, index } => write!(
+                f,
+                "Invalid Bech32 character {:?} at position {}",
+                character, index
+            ),
+            Bech32Error::MissingChecksum => write!(f, "Bech32 data shorter than its checksum"),
+            Bech32Error::InvalidChecksum => write!(f, "Invalid Bech32 checksum"),
+            Bech32Error::InvalidPadding => write!(f, "Invalid Bech32 padding"),
+        }
+    }
+}
+
+impl std::error::Error for Bech32Error {}
+
+/// Encodes 5-bit values in Bech32 or Bech32m, in lower case
+/// # Arguments
+/// * `hrp` - The human readable part, such as "bc"
+/// * `data` - The 5-bit values to encode
+/// * `variant` - The checksum to append
+/// # Returns
+/// * `String` - The encoded string
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+    let hrp = hrp.to_lowercase();
+    let checksum = create_checksum(&hrp, data, variant);
+    let mut result = hrp;
+    result.push(SEPARATOR);
+    result.extend(
+        data.iter()
+            .chain(&checksum)
+            .map(|&value| CHARSET[value as usize] as char),
+    );
+    result
+}
+
+/// Decodes a Bech32 or Bech32m string, detecting which checksum it uses
+/// # Arguments
+/// * `s` - The encoded string, all in lower case or all in upper case
+/// # Returns
+/// * `Result<(String, Vec<u8>, Variant), Bech32Error>` - The human readable part in
+///   lower case, the 5-bit values without the checksum and the checksum variant, or
+///   why the string is not valid
+pub fn decode(s: &str) -> std::result::Result<(String, Vec<u8>, Variant), Bech32Error> {
+    if s.len() > MAX_LENGTH {
+        return Err(Bech32Error::InvalidLength(s.len()));
+    }
+    if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err(Bech32Error::MixedCase);
+    }
+    let lower = s.to_lowercase();
+    let separator = lower
+        .rfind(SEPARATOR)
+        .ok_or(Bech32Error::MissingSeparator)?;
+    let (hrp, data) = (&lower[..separator], &lower[separator + 1..]);
+    if hrp.is_empty() || !hrp.bytes().all(|c| (33..=126).contains(&c)) {
+        return Err(Bech32Error::InvalidHrp);
+    }
+
+    let values = data
+        .chars()
+        .enumerate()
+        .map(|(i, character)| {
+            CHARSET
+                .iter()
+                .position(|&c| c as char == character)
+                .map(|value| value as u8)
+                .ok_or(Bech32Error::InvalidCharacter {
+                    character,
+                    index: separator + 1 + i,
+                })
+        })
+        .collect::<std::result::Result<Vec<u8>, _>>()?;
+    if values.len() < CHECKSUM_LENGTH {
+        return Err(Bech32Error::MissingChecksum);
+    }
+
+    let residue = polymod(&[expand_hrp(hrp), values.clone()].concat());
+    let variant = [Variant::Bech32, Variant::Bech32m]
+        .into_iter()
+        .find(|variant| variant.constant() == residue)
+        .ok_or(Bech32Error::InvalidChecksum)?;
+    Ok((
+        hrp.to_string(),
+        values[..values.len() - CHECKSUM_LENGTH].to_vec(),
+        variant,
+    ))
+}
+
+/// Regroups the bits of a sequence of values, such as bytes into 5-bit values
+/// # Arguments
+/// * `data` - The values, each below 2^from
+/// * `from` - The number of bits of each input value
+/// * `to` - The number of bits of each output value
+/// * `pad` - Whether to pad the last output value with zero bits; without padding, any
+///   leftover bits must be fewer than `from` and all zero
+/// # Returns
+/// * `Result<Vec<u8>, Bech32Error>` - The regrouped values, or
+///   `Bech32Error::InvalidPadding`
+pub fn convert_bits(
+    data: &[u8],
+    from: u32,
+    to: u32,
+    pad: bool,
+) -> std::result::Result<Vec<u8>, Bech32Error> {
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    let max = (1 << to) - 1;
+    let mut result = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        accumulator = accumulator << from | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push((accumulator >> bits & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            result.push((accumulator << (to - bits) & max) as u8);
+        }
+    } else if bits >= from || accumulator << (to - bits) & max != 0 {
+        return Err(Bech32Error::InvalidPadding);
+    }
+    Ok(result)
+}
+
+/// Computes the BCH checksum polynomial of 5-bit values
+fn polymod(values: &[u8]) -> u32 {
+    let mut checksum = 1u32;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Expands the human readable part for the checksum: the high bits of each character,
+/// a zero, then the low bits of each character
+fn expand_hrp(hrp: &str) -> Vec<u8> {
+    let mut result: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    result.push(0);
+    result.extend(hrp.bytes().map(|c| c & 31));
+    result
+}
+
+/// Computes the six 5-bit values of the checksum
+fn create_checksum(hrp: &str, data: &[u8], variant: Variant) -> [u8; CHECKSUM_LENGTH] {
+    let values = [expand_hrp(hrp), data.to_vec(), vec![0; CHECKSUM_LENGTH]].concat();
+    let residue = polymod(&values) ^ variant.constant();
+    std::array::from_fn(|i| (residue >> (5 * (5 - i)) & 31) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_strings() {
+        // The valid strings from BIP173 and BIP350
+        let cases = [
+            ("A12UEL5L", Variant::Bech32),
+            ("a12uel5l", Variant::Bech32),
+            (
+                "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+                Variant::Bech32,
+            ),
+            ("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", Variant::Bech32),
+            (
+                "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+                Variant::Bech32,
+            ),
+            (
+                "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+                Variant::Bech32,
+            ),
+            ("?1ezyfcl", Variant::Bech32),
+            ("A1LQFN3A", Variant::Bech32m),
+            ("a1lqfn3a", Variant::Bech32m),
+            (
+                "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+                Variant::Bech32m,
+            ),
+            ("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx", Variant::Bech32m),
+            (
+                "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+                Variant::Bech32m,
+            ),
+            (
+                "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+                Variant::Bech32m,
+            ),
+            ("?1v759aa", Variant::Bech32m),
+        ];
+        for (s, expected) in cases {
+            let (hrp, data, variant) = decode(s).unwrap();
+            assert_eq!(variant, expected, "{}", s);
+            assert_eq!(encode(&hrp, &data, variant), s.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn test_invalid_strings() {
+        let cases = [
+            ("\x201nwldj5", Bech32Error::InvalidHrp),
+            ("\x7f1axkwrx", Bech32Error::InvalidHrp),
+            (
+                "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+                Bech32Error::InvalidLength(91),
+            ),
+            ("pzry9x0s0muk", Bech32Error::MissingSeparator),
+            ("1pzry9x0s0muk", Bech32Error::InvalidHrp),
+            (
+                "x1b4n0q5v",
+                Bech32Error::InvalidCharacter {
+                    character: 'b',
+                    index: 2,
+                },
+            ),
+            ("li1dgmt3", Bech32Error::MissingChecksum),
+            ("A1G7SGD8", Bech32Error::InvalidChecksum),
+            ("10a06t8", Bech32Error::InvalidHrp),
+            ("1qzzfhee", Bech32Error::InvalidHrp),
+            ("a12UEL5L", Bech32Error::MixedCase),
+            ("a12uel5m", Bech32Error::InvalidChecksum),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(decode(s), Err(expected), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_convert_bits() {
+        let data = [0x75, 0x1e, 0x76, 0xe8, 0x19];
+        let values = convert_bits(&data, 8, 5, true).unwrap();
+        assert_eq!(values, vec![14, 20, 15, 7, 13, 26, 0, 25]);
+        assert_eq!(convert_bits(&values, 5, 8, false).unwrap(), data);
+
+        // One byte leaves 2 padding bits in two 5-bit values, which must be zero
+        assert_eq!(convert_bits(&[0x01], 8, 5, true).unwrap(), vec![0, 4]);
+        assert_eq!(
+            convert_bits(&[0, 5], 5, 8, false),
+            Err(Bech32Error::InvalidPadding)
+        );
+        // Three 5-bit values leave 7 bits, more than a value can pad
+        assert_eq!(
+            convert_bits(&[0, 0, 0], 5, 8, false),
+            Err(Bech32Error::InvalidPadding)
+        );
+    }
+}
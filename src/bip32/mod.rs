@@ -0,0 +1,915 @@
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+
+use crate::base58::{self, Base58Error};
+use crate::hash::{hmac_sha512, Hash160};
+use crate::hex;
+use crate::network::Network;
+use crate::private_key::PrivateKey;
+use crate::s256point::{S256Point, SecError};
+use crate::scalar::Scalar;
+
+/// The first hardened child number, 2^31
+pub const HARDENED: u32 = 0x8000_0000;
+
+/// The length of a serialized extended key, before Base58Check encoding
+const EXTENDED_KEY_LEN: usize = 78;
+
+/// Errors that can occur when deriving, parsing or serializing BIP32 keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bip32Error {
+    /// The seed is not between 16 and 64 bytes long
+    InvalidSeedLength(usize),
+    /// The seed gives a master secret that is zero or not smaller than n
+    InvalidSeed,
+    /// A derivation path is not of the form m/0'/1/2h
+    InvalidPath(String),
+    /// A hardened child was requested from a public key
+    HardenedFromPublic(u32),
+    /// The child number has no valid key, which happens with probability below 2^-127
+    InvalidChild(u32),
+    /// A key at depth 255 has no children
+    MaxDepthExceeded,
+    /// The extended key is not valid Base58Check
+    Base58(Base58Error),
+    /// The decoded extended key is not 78 bytes long
+    InvalidLength(usize),
+    /// The version bytes do not belong to any known network and key type
+    UnknownVersion([u8; 4]),
+    /// A master key, at depth 0, has a parent fingerprint or a child number
+    InvalidMasterKey,
+    /// The private key data does not start with a zero byte
+    InvalidPrivateKeyPrefix(u8),
+    /// The private key is zero or not smaller than n
+    InvalidPrivateKey,
+    /// The public key is not a valid compressed SEC point
+    InvalidPublicKey(SecError),
+}
+
+// Formats the Bip32Error
+impl Display for Bip32Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Bip32Error::InvalidSeedLength(len) => write!(f, "Invalid BIP32 seed length {}", len),
+            Bip32Error::InvalidSeed => write!(f, "Seed gives an invalid master key"),
+            Bip32Error::InvalidPath(path) => write!(f, "Invalid derivation path {:?}", path),
+            Bip32Error::HardenedFromPublic(index) => write!(
+                f,
+                "Cannot derive hardened child {} from a public key",
+                index
+            ),
+            Bip32Error::InvalidChild(index) => write!(f, "Child {} is not a valid key", index),
+            Bip32Error::MaxDepthExceeded => write!(f, "Extended key depth above 255"),
+            Bip32Error::Base58(err) => write!(f, "{}", err),
+            Bip32Error::InvalidLength(len) => write!(f, "Invalid extended key length {}", len),
+            Bip32Error::UnknownVersion(version) => {
+                write!(f, "Unknown extended key version 0x{}", hex::encode(version))
+            }
+            Bip32Error::InvalidMasterKey => {
+                write!(f, "Master key with a parent fingerprint or child number")
+            }
+            Bip32Error::InvalidPrivateKeyPrefix(prefix) => {
+                write!(f, "Invalid extended private key prefix 0x{:02x}", prefix)
+            }
+            Bip32Error::InvalidPrivateKey => {
+                write!(f, "Extended private key not in range 1 to n - 1")
+            }
+            Bip32Error::InvalidPublicKey(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Bip32Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Bip32Error::Base58(err) => Some(err),
+            Bip32Error::InvalidPublicKey(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Base58Error> for Bip32Error {
+    fn from(err: Base58Error) -> Self {
+        Bip32Error::Base58(err)
+    }
+}
+
+impl From<SecError> for Bip32Error {
+    fn from(err: SecError) -> Self {
+        Bip32Error::InvalidPublicKey(err)
+    }
+}
+
+/// DerivationPath is a list of child numbers leading from a key to one of its
+/// descendants, written like m/84'/0'/0'/0/5
+/// Hardened child numbers have the 2^31 bit set, and are written with ' or h
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Creates a path from its child numbers, with the hardened bit already set
+    pub fn new(indexes: Vec<u32>) -> Self {
+        DerivationPath(indexes)
+    }
+
+    /// Returns the child numbers of the path
+    pub fn get_indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+// Formats the DerivationPath with ' for hardened child numbers
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            if *index >= HARDENED {
+                write!(f, "/{}'", index - HARDENED)?;
+            } else {
+                write!(f, "/{}", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Parses a DerivationPath such as m/84'/0'/0'/0/5
+impl FromStr for DerivationPath {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || Bip32Error::InvalidPath(s.to_string());
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid());
+        }
+        parts
+            .map(|part| {
+                let (number, hardened) = match part.strip_suffix(['\'', 'h', 'H']) {
+                    Some(number) => (number, true),
+                    None => (part, false),
+                };
+                // Only plain decimal digits, so that "+1" is rejected
+                if number.is_empty() || !number.bytes().all(|c| c.is_ascii_digit()) {
+                    return Err(invalid());
+                }
+                match number.parse::<u32>() {
+                    Ok(index) if index < HARDENED => {
+                        Ok(if hardened { index + HARDENED } else { index })
+                    }
+                    _ => Err(invalid()),
+                }
+            })
+            .collect::<std::result::Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+/// ExtendedPrivKey is a BIP32 private key along with the chain code needed to derive
+/// its children, and where it sits in its tree
+#[derive(Debug, Clone)]
+pub struct ExtendedPrivKey {
+    network: Network,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+    chain_code: [u8; 32],
+    private_key: PrivateKey,
+}
+
+impl ExtendedPrivKey {
+    /// Creates the master key of a tree from a seed, such as a BIP39 seed
+    /// # Arguments
+    /// * `seed` - The seed, 16 to 64 bytes long
+    /// * `network` - The network the keys are for
+    /// # Returns
+    /// * `Result<ExtendedPrivKey, Bip32Error>` - The master key,
+    ///   `Bip32Error::InvalidSeedLength`, or `Bip32Error::InvalidSeed` in the very
+    ///   unlikely case the seed gives no valid key
+    pub fn new_master(seed: &[u8], network: Network) -> std::result::Result<Self, Bip32Error> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err(Bip32Error::InvalidSeedLength(seed.len()));
+        }
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let private_key = Scalar::from_bytes(i[..32].try_into().unwrap())
+            .and_then(|secret| PrivateKey::from_scalar(secret).ok())
+            .ok_or(Bip32Error::InvalidSeed)?;
+        Ok(ExtendedPrivKey {
+            network,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: 0,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    pub fn get_network(&self) -> Network {
+        self.network
+    }
+
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn get_parent_fingerprint(&self) -> &[u8; 4] {
+        &self.parent_fingerprint
+    }
+
+    pub fn get_child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    pub fn get_chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    pub fn get_private_key(&self) -> &PrivateKey {
+        &self.private_key
+    }
+
+    /// Returns the hash160 of the compressed public key, which identifies the key
+    pub fn identifier(&self) -> Hash160 {
+        self.private_key.get_public_key().hash160(true)
+    }
+
+    /// Returns the first four bytes of the identifier, stored in the children of the key
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.identifier())
+    }
+
+    /// Returns the extended public key with the same chain code and position
+    pub fn to_extended_pub_key(&self) -> ExtendedPubKey {
+        ExtendedPubKey {
+            network: self.network,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+            chain_code: self.chain_code,
+            public_key: *self.private_key.get_public_key(),
+        }
+    }
+
+    /// Derives a child key, hardened if the index is at least 2^31
+    /// A hardened child hashes the parent private key, and a normal child the parent
+    /// public key, so that normal children can also be derived from the extended public key
+    /// # Arguments
+    /// * `index` - The child number
+    /// # Returns
+    /// * `Result<ExtendedPrivKey, Bip32Error>` - The child key, or
+    ///   `Bip32Error::InvalidChild` in the very unlikely case the index has no valid child
+    pub fn derive_child(&self, index: u32) -> std::result::Result<Self, Bip32Error> {
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or(Bip32Error::MaxDepthExceeded)?;
+        let mut data = if index >= HARDENED {
+            let mut data = vec![0];
+            data.extend_from_slice(&self.private_key.get_secret().to_bytes());
+            data
+        } else {
+            self.private_key.get_public_key().sec(true)
+        };
+        data.extend_from_slice(&index.to_be_bytes());
+        let i = hmac_sha512(&self.chain_code, &data);
+
+        let private_key = Scalar::from_bytes(i[..32].try_into().unwrap())
+            .and_then(|tweak| PrivateKey::from_scalar(tweak + *self.private_key.get_secret()).ok())
+            .ok_or(Bip32Error::InvalidChild(index))?;
+        Ok(ExtendedPrivKey {
+            network: self.network,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code: i[32..].try_into().unwrap(),
+            private_key,
+        })
+    }
+
+    /// Derives the descendant at the end of a path
+    /// # Arguments
+    /// * `path` - The path from this key
+    /// # Returns
+    /// * `Result<ExtendedPrivKey, Bip32Error>` - The descendant, or the error of the
+    ///   first step that failed
+    pub fn derive_path(&self, path: &DerivationPath) -> std::result::Result<Self, Bip32Error> {
+        path.get_indexes()
+            .iter()
+            .try_fold(self.clone(), |key, index| key.derive_child(*index))
+    }
+}
+
+// Formats the ExtendedPrivKey as a Base58Check xprv or tprv string
+impl Display for ExtendedPrivKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut key = vec![0];
+        key.extend_from_slice(&self.private_key.get_secret().to_bytes());
+        let data = serialize(
+            self.network.xprv_version(),
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key,
+        );
+        write!(f, "{}", base58::encode_check(&data))
+    }
+}
+
+// Parses an ExtendedPrivKey from its Base58Check xprv or tprv string
+impl FromStr for ExtendedPrivKey {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (version, depth, parent_fingerprint, child_number, chain_code, key) = deserialize(s)?;
+        let network = [Network::Mainnet, Network::Testnet]
+            .into_iter()
+            .find(|network| network.xprv_version() == version)
+            .ok_or(Bip32Error::UnknownVersion(version))?;
+        if key[0] != 0 {
+            return Err(Bip32Error::InvalidPrivateKeyPrefix(key[0]));
+        }
+        let private_key = Scalar::from_bytes(key[1..].try_into().unwrap())
+            .and_then(|secret| PrivateKey::from_scalar(secret).ok())
+            .ok_or(Bip32Error::InvalidPrivateKey)?;
+        Ok(ExtendedPrivKey {
+            network,
+            depth,
+            parent_fingerprint,
+            child_number,
+            chain_code,
+            private_key,
+        })
+    }
+}
+
+/// ExtendedPubKey is a BIP32 public key along with the chain code needed to derive
+/// its normal children, and where it sits in its tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtendedPubKey {
+    network: Network,
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: u32,
+    chain_code: [u8; 32],
+    public_key: S256Point,
+}
+
+impl ExtendedPubKey {
+    pub fn get_network(&self) -> Network {
+        self.network
+    }
+
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn get_parent_fingerprint(&self) -> &[u8; 4] {
+        &self.parent_fingerprint
+    }
+
+    pub fn get_child_number(&self) -> u32 {
+        self.child_number
+    }
+
+    pub fn get_chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    pub fn get_public_key(&self) -> &S256Point {
+        &self.public_key
+    }
+
+    /// Returns the hash160 of the compressed public key, which identifies the key
+    pub fn identifier(&self) -> Hash160 {
+        self.public_key.hash160(true)
+    }
+
+    /// Returns the first four bytes of the identifier, stored in the children of the key
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.identifier())
+    }
+
+    /// Derives a normal child key
+    /// # Arguments
+    /// * `index` - The child number, below 2^31
+    /// # Returns
+    /// * `Result<ExtendedPubKey, Bip32Error>` - The child key,
+    ///   `Bip32Error::HardenedFromPublic` for a hardened index, or
+    ///   `Bip32Error::InvalidChild` in the very unlikely case the index has no valid child
+    pub fn derive_child(&self, index: u32) -> std::result::Result<Self, Bip32Error> {
+        let depth = self
+            .depth
+            .checked_add(1)
+            .ok_or(Bip32Error::MaxDepthExceeded)?;
+        let (public_key, chain_code) = self.public_key.derive_child(&self.chain_code, index)?;
+        Ok(ExtendedPubKey {
+            network: self.network,
+            depth,
+            parent_fingerprint: self.fingerprint(),
+            child_number: index,
+            chain_code,
+            public_key,
+        })
+    }
+
+    /// Derives the descendant at the end of a path of normal child numbers
+    /// # Arguments
+    /// * `path` - The path from this key
+    /// # Returns
+    /// * `Result<ExtendedPubKey, Bip32Error>` - The descendant, or the error of the
+    ///   first step that failed
+    pub fn derive_path(&self, path: &DerivationPath) -> std::result::Result<Self, Bip32Error> {
+        path.get_indexes()
+            .iter()
+            .try_fold(*self, |key, index| key.derive_child(*index))
+    }
+}
+
+// Formats the ExtendedPubKey as a Base58Check xpub or tpub string
+impl Display for ExtendedPubKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let data = serialize(
+            self.network.xpub_version(),
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &self.public_key.sec(true),
+        );
+        write!(f, "{}", base58::encode_check(&data))
+    }
+}
+
+// Parses an ExtendedPubKey from its Base58Check xpub or tpub string
+impl FromStr for ExtendedPubKey {
+    type Err = Bip32Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (version, depth, parent_fingerprint, child_number, chain_code, key) = deserialize(s)?;
+        let network = [Network::Mainnet, Network::Testnet]
+            .into_iter()
+            .find(|network| network.xpub_version() == version)
+            .ok_or(Bip32Error::UnknownVersion(version))?;
+        // Only the compressed format fits, so an 0x04 prefix is rejected by its length
+        let public_key = S256Point::parse_sec(&key)?;
+        Ok(ExtendedPubKey {
+            network,
+            depth,
+            parent_fingerprint,
+            child_number,
+            chain_code,
+            public_key,
+        })
+    }
+}
+
+/// Returns the first four bytes of a key identifier
+fn fingerprint(identifier: &Hash160) -> [u8; 4] {
+    identifier.as_bytes()[..4].try_into().unwrap()
+}
+
+/// Serializes the 78 bytes of an extended key
+fn serialize(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: &[u8; 4],
+    child_number: u32,
+    chain_code: &[u8; 32],
+    key: &[u8],
+) -> Vec<u8> {
+    let mut data = Vec::with_capacity(EXTENDED_KEY_LEN);
+    data.extend_from_slice(&version);
+    data.push(depth);
+    data.extend_from_slice(parent_fingerprint);
+    data.extend_from_slice(&child_number.to_be_bytes());
+    data.extend_from_slice(chain_code);
+    data.extend_from_slice(key);
+    data
+}
+
+/// The fields of a serialized extended key: version, depth, parent fingerprint,
+/// child number, chain code and the 33 bytes of key data
+type ExtendedKeyFields = ([u8; 4], u8, [u8; 4], u32, [u8; 32], [u8; 33]);
+
+/// Decodes a Base58Check extended key into its fields, checking what is common to
+/// private and public keys
+fn deserialize(s: &str) -> std::result::Result<ExtendedKeyFields, Bip32Error> {
+    let data = base58::decode_check(s)?;
+    if data.len() != EXTENDED_KEY_LEN {
+        return Err(Bip32Error::InvalidLength(data.len()));
+    }
+    let version = data[0..4].try_into().unwrap();
+    let depth = data[4];
+    let parent_fingerprint: [u8; 4] = data[5..9].try_into().unwrap();
+    let child_number = u32::from_be_bytes(data[9..13].try_into().unwrap());
+    if depth == 0 && (parent_fingerprint != [0; 4] || child_number != 0) {
+        return Err(Bip32Error::InvalidMasterKey);
+    }
+    Ok((
+        version,
+        depth,
+        parent_fingerprint,
+        child_number,
+        data[13..45].try_into().unwrap(),
+        data[45..].try_into().unwrap(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A derivation path with the xprv and xpub at its end
+    type Case = (&'static str, &'static str, &'static str);
+
+    /// The test vectors 1 to 4 from BIP32: seed, then path, xprv and xpub
+    const VECTORS: [(&str, &[Case]); 4] = [
+        (
+            "000102030405060708090a0b0c0d0e0f",
+            &[
+                (
+                    "m",
+                    "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+                    "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+                ),
+                (
+                    "m/0'",
+                    "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+                    "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+                ),
+                (
+                    "m/0'/1",
+                    "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+                    "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+                ),
+                (
+                    "m/0'/1/2'",
+                    "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+                    "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+                ),
+                (
+                    "m/0'/1/2'/2",
+                    "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+                    "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+                ),
+                (
+                    "m/0'/1/2'/2/1000000000",
+                    "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+                    "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+                ),
+            ],
+        ),
+        (
+            "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542",
+            &[
+                (
+                    "m",
+                    "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+                    "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+                ),
+                (
+                    "m/0",
+                    "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+                    "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+                ),
+                (
+                    "m/0/2147483647'",
+                    "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+                    "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+                ),
+                (
+                    "m/0/2147483647'/1",
+                    "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+                    "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+                ),
+                (
+                    "m/0/2147483647'/1/2147483646'",
+                    "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+                    "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+                ),
+                (
+                    "m/0/2147483647'/1/2147483646'/2",
+                    "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+                    "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+                ),
+            ],
+        ),
+        (
+            "4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be",
+            &[
+                (
+                    "m",
+                    "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+                    "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+                ),
+                (
+                    "m/0'",
+                    "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+                    "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+                ),
+            ],
+        ),
+        (
+            "3ddd5602285899a946114506157c7997e5444528f3003f6134712147db19b678",
+            &[
+                (
+                    "m",
+                    "xprv9s21ZrQH143K48vGoLGRPxgo2JNkJ3J3fqkirQC2zVdk5Dgd5w14S7fRDyHH4dWNHUgkvsvNDCkvAwcSHNAQwhwgNMgZhLtQC63zxwhQmRv",
+                    "xpub661MyMwAqRbcGczjuMoRm6dXaLDEhW1u34gKenbeYqAix21mdUKJyuyu5F1rzYGVxyL6tmgBUAEPrEz92mBXjByMRiJdba9wpnN37RLLAXa",
+                ),
+                (
+                    "m/0'",
+                    "xprv9vB7xEWwNp9kh1wQRfCCQMnZUEG21LpbR9NPCNN1dwhiZkjjeGRnaALmPXCX7SgjFTiCTT6bXes17boXtjq3xLpcDjzEuGLQBM5ohqkao9G",
+                    "xpub69AUMk3qDBi3uW1sXgjCmVjJ2G6WQoYSnNHyzkmdCHEhSZ4tBok37xfFEqHd2AddP56Tqp4o56AePAgCjYdvpW2PU2jbUPFKsav5ut6Ch1m",
+                ),
+                (
+                    "m/0'/1'",
+                    "xprv9xJocDuwtYCMNAo3Zw76WENQeAS6WGXQ55RCy7tDJ8oALr4FWkuVoHJeHVAcAqiZLE7Je3vZJHxspZdFHfnBEjHqU5hG1Jaj32dVoS6XLT1",
+                    "xpub6BJA1jSqiukeaesWfxe6sNK9CCGaujFFSJLomWHprUL9DePQ4JDkM5d88n49sMGJxrhpjazuXYWdMf17C9T5XnxkopaeS7jGk1GyyVziaMt",
+                ),
+            ],
+        ),
+    ];
+
+    /// The xprv strings of test vector 5 from BIP32 with the error each one fails with
+    const INVALID_XPRVS: [(&str, Bip32Error); 9] = [
+        // Private key version with public key data
+        (
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGTQQD3dC4H2D5GBj7vWvSQaaBv5cxi9gafk7NF3pnBju6dwKvH",
+            Bip32Error::InvalidPrivateKeyPrefix(0x03),
+        ),
+        (
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGpWnsj83BHtEy5Zt8CcDr1UiRXuWCmTQLxEK9vbz5gPstX92JQ",
+            Bip32Error::InvalidPrivateKeyPrefix(0x04),
+        ),
+        (
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fEQ3Qen6J",
+            Bip32Error::InvalidPrivateKeyPrefix(0x01),
+        ),
+        // Zero depth with a parent fingerprint, then with a child number
+        (
+            "xprv9s2SPatNQ9Vc6GTbVMFPFo7jsaZySyzk7L8n2uqKXJen3KUmvQNTuLh3fhZMBoG3G4ZW1N2kZuHEPY53qmbZzCHshoQnNf4GvELZfqTUrcv",
+            Bip32Error::InvalidMasterKey,
+        ),
+        (
+            "xprv9s21ZrQH4r4TsiLvyLXqM9P7k1K3EYhA1kkD6xuquB5i39AU8KF42acDyL3qsDbU9NmZn6MsGSUYZEsuoePmjzsB3eFKSUEh3Gu1N3cqVUN",
+            Bip32Error::InvalidMasterKey,
+        ),
+        (
+            "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHGMQzT7ayAmfo4z3gY5KfbrZWZ6St24UVf2Qgo6oujFktLHdHY4",
+            Bip32Error::UnknownVersion([0x01; 4]),
+        ),
+        // Private keys of 0 and n
+        (
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx",
+            Bip32Error::InvalidPrivateKey,
+        ),
+        (
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD5SDKr24z3aiUvKr9bJpdrcLg1y3G",
+            Bip32Error::InvalidPrivateKey,
+        ),
+        (
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL",
+            Bip32Error::Base58(Base58Error::InvalidChecksum),
+        ),
+    ];
+
+    /// The xpub strings of test vector 5 from BIP32 with the error each one fails with
+    const INVALID_XPUBS: [(&str, Bip32Error); 7] = [
+        // Public key version with private key data
+        (
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm",
+            Bip32Error::InvalidPublicKey(SecError::InvalidPrefix(0x00)),
+        ),
+        (
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Txnt3siSujt9RCVYsx4qHZGc62TG4McvMGcAUjeuwZdduYEvFn",
+            Bip32Error::InvalidPublicKey(SecError::InvalidLength(33)),
+        ),
+        (
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6N8ZMMXctdiCjxTNq964yKkwrkBJJwpzZS4HS2fxvyYUA4q2Xe4",
+            Bip32Error::InvalidPublicKey(SecError::InvalidPrefix(0x01)),
+        ),
+        // Zero depth with a parent fingerprint, then with a child number
+        (
+            "xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ",
+            Bip32Error::InvalidMasterKey,
+        ),
+        (
+            "xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8",
+            Bip32Error::InvalidMasterKey,
+        ),
+        (
+            "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHPmHJiEDXkTiJTVV9rHEBUem2mwVbbNfvT2MTcAqj3nesx8uBf9",
+            Bip32Error::UnknownVersion([0x01; 4]),
+        ),
+        // The x coordinate 7 is not on the curve
+        (
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Q5JXayek4PRsn35jii4veMimro1xefsM58PgBMrvdYre8QyULY",
+            Bip32Error::InvalidPublicKey(SecError::NotOnCurve),
+        ),
+    ];
+
+    #[test]
+    fn test_vectors() {
+        for (seed, cases) in VECTORS {
+            let master =
+                ExtendedPrivKey::new_master(&hex::decode(seed).unwrap(), Network::Mainnet).unwrap();
+            for (path, xprv, xpub) in cases {
+                let path: DerivationPath = path.parse().unwrap();
+                let key = master.derive_path(&path).unwrap();
+                assert_eq!(key.to_string(), *xprv, "{}", path);
+                assert_eq!(key.to_extended_pub_key().to_string(), *xpub, "{}", path);
+
+                let parsed: ExtendedPrivKey = xprv.parse().unwrap();
+                assert_eq!(parsed.to_string(), *xprv);
+                let parsed: ExtendedPubKey = xpub.parse().unwrap();
+                assert_eq!(parsed, key.to_extended_pub_key());
+
+                // Normal children can also be derived from the parent public key
+                if let Some((&last, parent)) = path.get_indexes().split_last() {
+                    let parent = master
+                        .derive_path(&DerivationPath::new(parent.to_vec()))
+                        .unwrap();
+                    let public = parent.to_extended_pub_key().derive_child(last);
+                    if last < HARDENED {
+                        assert_eq!(public.unwrap().to_string(), *xpub, "{}", path);
+                    } else {
+                        assert_eq!(public, Err(Bip32Error::HardenedFromPublic(last)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_vector_5() {
+        for (xprv, err) in INVALID_XPRVS {
+            assert_eq!(
+                xprv.parse::<ExtendedPrivKey>().unwrap_err(),
+                err,
+                "{}",
+                xprv
+            );
+        }
+        for (xpub, err) in INVALID_XPUBS {
+            assert_eq!(xpub.parse::<ExtendedPubKey>().unwrap_err(), err, "{}", xpub);
+        }
+    }
+
+    #[test]
+    fn test_testnet() {
+        // Derived with rust-bitcoin
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(&seed, Network::Testnet).unwrap();
+        let key = master
+            .derive_path(&"m/84'/1'/0'/0/5".parse().unwrap())
+            .unwrap();
+        let tprv = "tprv8k7UWWmyp8CBEZykZc9QxRTpcrMT5S5C3tKDKM5pn8ewHaAt7rTVMb3g9qC6jHAqCpqALTtCp2YhU587j2oNRDBewwgJ3pwRpkeH8TbV7Xb";
+        let tpub = "tpubDGoWevpDxVsr831YTFp1Mq7wBssPEmG6dBuzbs88CQTL84RekFH5Y5fYKzkkFNnAuRnPXtwSAcGMr4V22vw33f47Tr57iGHdjiFxVKSPMAi";
+        assert_eq!(key.to_string(), tprv);
+        assert_eq!(key.to_extended_pub_key().to_string(), tpub);
+        assert_eq!(key.get_depth(), 5);
+        assert_eq!(key.get_child_number(), 5);
+
+        let parsed: ExtendedPrivKey = tprv.parse().unwrap();
+        assert_eq!(parsed.get_network(), Network::Testnet);
+        assert_eq!(
+            parsed.get_private_key().get_secret(),
+            key.get_private_key().get_secret()
+        );
+        let parsed: ExtendedPubKey = tpub.parse().unwrap();
+        assert_eq!(parsed.get_network(), Network::Testnet);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(&seed, Network::Mainnet).unwrap();
+        assert_eq!(
+            master.identifier().to_string(),
+            "3442193e1bb70916e914552172cd4e2dbc9df811"
+        );
+        assert_eq!(master.fingerprint(), [0x34, 0x42, 0x19, 0x3e]);
+        assert_eq!(
+            master.to_extended_pub_key().fingerprint(),
+            master.fingerprint()
+        );
+
+        let child = master.derive_child(HARDENED).unwrap();
+        assert_eq!(child.get_parent_fingerprint(), &master.fingerprint());
+        assert_eq!(master.get_parent_fingerprint(), &[0; 4]);
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/84'/0h/0H/0/5".parse().unwrap();
+        assert_eq!(
+            path.get_indexes(),
+            &[84 + HARDENED, HARDENED, HARDENED, 0, 5]
+        );
+        assert_eq!(path.to_string(), "m/84'/0'/0'/0/5");
+        assert_eq!("m".parse(), Ok(DerivationPath::default()));
+
+        for invalid in [
+            "",
+            "0/1",
+            "m/",
+            "m//1",
+            "m/x",
+            "m/+1",
+            "m/1''",
+            "m/2147483648",
+            "M/1",
+        ] {
+            assert_eq!(
+                invalid.parse::<DerivationPath>(),
+                Err(Bip32Error::InvalidPath(invalid.to_string())),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        assert_eq!(
+            ExtendedPrivKey::new_master(&seed[..15], Network::Mainnet).unwrap_err(),
+            Bip32Error::InvalidSeedLength(15)
+        );
+
+        let master = ExtendedPrivKey::new_master(&seed, Network::Mainnet).unwrap();
+        let child = master.derive_child(1).unwrap();
+        let xprv = base58::decode_check(&child.to_string()).unwrap();
+        let xpub = base58::decode_check(&child.to_extended_pub_key().to_string()).unwrap();
+        let with = |data: &[u8], index: usize, bytes: &[u8]| {
+            let mut data = data.to_vec();
+            data[index..index + bytes.len()].copy_from_slice(bytes);
+            base58::encode_check(&data)
+        };
+
+        assert_eq!(
+            base58::encode_check(&xprv[..77])
+                .parse::<ExtendedPrivKey>()
+                .unwrap_err(),
+            Bip32Error::InvalidLength(77)
+        );
+        // An xpub version on private key data, and the other way around
+        assert_eq!(
+            with(&xprv, 0, &xpub[..4])
+                .parse::<ExtendedPrivKey>()
+                .unwrap_err(),
+            Bip32Error::UnknownVersion([0x04, 0x88, 0xb2, 0x1e])
+        );
+        assert_eq!(
+            with(&xpub, 0, &xprv[..4])
+                .parse::<ExtendedPubKey>()
+                .unwrap_err(),
+            Bip32Error::UnknownVersion([0x04, 0x88, 0xad, 0xe4])
+        );
+        // A master key with a parent fingerprint
+        assert_eq!(
+            with(&xprv, 4, &[0]).parse::<ExtendedPrivKey>().unwrap_err(),
+            Bip32Error::InvalidMasterKey
+        );
+        assert_eq!(
+            with(&xprv, 45, &[1])
+                .parse::<ExtendedPrivKey>()
+                .unwrap_err(),
+            Bip32Error::InvalidPrivateKeyPrefix(1)
+        );
+        assert_eq!(
+            with(&xprv, 46, &[0; 32])
+                .parse::<ExtendedPrivKey>()
+                .unwrap_err(),
+            Bip32Error::InvalidPrivateKey
+        );
+        assert_eq!(
+            with(&xprv, 46, &[0xff; 32])
+                .parse::<ExtendedPrivKey>()
+                .unwrap_err(),
+            Bip32Error::InvalidPrivateKey
+        );
+        assert_eq!(
+            with(&xpub, 45, &[0x04])
+                .parse::<ExtendedPubKey>()
+                .unwrap_err(),
+            Bip32Error::InvalidPublicKey(SecError::InvalidLength(33))
+        );
+        assert_eq!(
+            with(&xpub, 45, &[0x05])
+                .parse::<ExtendedPubKey>()
+                .unwrap_err(),
+            Bip32Error::InvalidPublicKey(SecError::InvalidPrefix(0x05))
+        );
+        assert!(matches!(
+            child
+                .to_string()
+                .replace('A', "B")
+                .parse::<ExtendedPrivKey>(),
+            Err(Bip32Error::Base58(_))
+        ));
+    }
+}
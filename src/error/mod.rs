@@ -0,0 +1,212 @@
+use num_bigint::BigUint;
+use std::fmt::{Display, Formatter, Result};
+
+use crate::address::AddressError;
+use crate::base58::Base58Error;
+use crate::bech32::Bech32Error;
+use crate::bip32::Bip32Error;
+use crate::field_element::FieldElement;
+use crate::mnemonic::MnemonicError;
+use crate::private_key::WifError;
+use crate::recoverable::RecoveryError;
+use crate::s256point::SecError;
+use crate::schnorr::SchnorrError;
+use crate::signature::DerError;
+
+/// Error is the error type returned by the fallible operations of the crate
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The input is not a valid hexadecimal number
+    InvalidHex(String),
+    /// The input is not valid padded Base64
+    InvalidBase64(String),
+    /// A number is not smaller than the prime of its finite field
+    NumberOutOfRange { number: BigUint, prime: BigUint },
+    /// Two FieldElement values belong to different finite fields
+    DifferentFields,
+    /// The coordinates do not describe a point on the curve
+    NotOnCurve { x: FieldElement, y: FieldElement },
+    /// Only one of the two coordinates of a point was given
+    IncompletePoint,
+    /// Two points belong to different curves
+    DifferentCurves,
+    /// A multi-scalar multiplication was given no terms
+    EmptyMultiMul,
+    /// A private key secret is not in the range 1 to n - 1
+    InvalidSecret,
+    /// A script pushes more data than it contains
+    InvalidScript,
+    /// A transaction has no input at the index
+    InputIndexOutOfRange(usize),
+    /// The output spent by the transaction input at the index is not known
+    MissingPrevOutput(usize),
+    /// The script is not of a type that can be signed
+    UnsupportedScript,
+    /// A segwit transaction has a flag other than 1 after its marker
+    InvalidSegwitFlag(u8),
+    /// Transaction amounts add up to more than 21 million bitcoins
+    AmountOutOfRange,
+    /// A SEC encoded public key could not be parsed
+    Sec(SecError),
+    /// A DER encoded signature could not be parsed
+    Der(DerError),
+    /// A BIP340 Schnorr signature could not be parsed
+    Schnorr(SchnorrError),
+    /// A recoverable signature could not be parsed or its public key recovered
+    Recovery(RecoveryError),
+    /// A Base58 or Base58Check string could not be decoded
+    Base58(Base58Error),
+    /// A Bech32 or Bech32m string could not be decoded
+    Bech32(Bech32Error),
+    /// An address could not be parsed
+    Address(AddressError),
+    /// A private key in WIF format could not be parsed
+    Wif(WifError),
+    /// A BIP32 key could not be derived or parsed
+    Bip32(Bip32Error),
+    /// A BIP39 mnemonic could not be created or parsed
+    Mnemonic(MnemonicError),
+    /// Reading serialized data failed, usually because it ended early
+    Io(std::io::ErrorKind),
+}
+
+// Formats the Error
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Error::InvalidHex(input) => write!(f, "Invalid hex number {:?}", input),
+            Error::InvalidBase64(input) => write!(f, "Invalid Base64 string {:?}", input),
+            Error::NumberOutOfRange { number, prime } => write!(
+                f,
+                "Num {} not in field range 0 to {}",
+                number,
+                prime - BigUint::from(1u32)
+            ),
+            Error::DifferentFields => {
+                write!(f, "Cannot operate with two numbers in different Fields")
+            }
+            Error::NotOnCurve { x, y } => write!(f, "({}, {}) is not on the curve", x, y),
+            Error::IncompletePoint => write!(f, "Incomplete point coordinates"),
+            Error::DifferentCurves => write!(f, "Points are not on the same curve"),
+            Error::EmptyMultiMul => write!(f, "Multi-scalar multiplication with no terms"),
+            Error::InvalidSecret => write!(f, "Secret not in range 1 to n - 1"),
+            Error::InvalidScript => write!(f, "Parsing script failed"),
+            Error::InputIndexOutOfRange(index) => write!(f, "No transaction input {}", index),
+            Error::MissingPrevOutput(index) => {
+                write!(
+                    f,
+                    "Previous output of transaction input {} not known",
+                    index
+                )
+            }
+            Error::UnsupportedScript => write!(f, "Unsupported script type"),
+            Error::InvalidSegwitFlag(flag) => write!(f, "Invalid segwit flag {}", flag),
+            Error::AmountOutOfRange => write!(f, "Amount above 21 million bitcoins"),
+            Error::Sec(err) => write!(f, "{}", err),
+            Error::Der(err) => write!(f, "{}", err),
+            Error::Schnorr(err) => write!(f, "{}", err),
+            Error::Recovery(err) => write!(f, "{}", err),
+            Error::Base58(err) => write!(f, "{}", err),
+            Error::Bech32(err) => write!(f, "{}", err),
+            Error::Address(err) => write!(f, "{}", err),
+            Error::Wif(err) => write!(f, "{}", err),
+            Error::Bip32(err) => write!(f, "{}", err),
+            Error::Mnemonic(err) => write!(f, "{}", err),
+            Error::Io(kind) => write!(f, "I/O error: {}", kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Sec(err) => Some(err),
+            Error::Der(err) => Some(err),
+            Error::Schnorr(err) => Some(err),
+            Error::Recovery(err) => Some(err),
+            Error::Base58(err) => Some(err),
+            Error::Bech32(err) => Some(err),
+            Error::Address(err) => Some(err),
+            Error::Wif(err) => Some(err),
+            Error::Bip32(err) => Some(err),
+            Error::Mnemonic(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SecError> for Error {
+    fn from(err: SecError) -> Self {
+        Error::Sec(err)
+    }
+}
+
+impl From<DerError> for Error {
+    fn from(err: DerError) -> Self {
+        Error::Der(err)
+    }
+}
+
+impl From<SchnorrError> for Error {
+    fn from(err: SchnorrError) -> Self {
+        Error::Schnorr(err)
+    }
+}
+
+impl From<RecoveryError> for Error {
+    fn from(err: RecoveryError) -> Self {
+        Error::Recovery(err)
+    }
+}
+
+impl From<Base58Error> for Error {
+    fn from(err: Base58Error) -> Self {
+        Error::Base58(err)
+    }
+}
+
+impl From<Bech32Error> for Error {
+    fn from(err: Bech32Error) -> Self {
+        Error::Bech32(err)
+    }
+}
+
+impl From<AddressError> for Error {
+    fn from(err: AddressError) -> Self {
+        Error::Address(err)
+    }
+}
+
+impl From<WifError> for Error {
+    fn from(err: WifError) -> Self {
+        Error::Wif(err)
+    }
+}
+
+impl From<Bip32Error> for Error {
+    fn from(err: Bip32Error) -> Self {
+        Error::Bip32(err)
+    }
+}
+
+impl From<MnemonicError> for Error {
+    fn from(err: MnemonicError) -> Self {
+        Error::Mnemonic(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err.kind())
+    }
+}
+
+/// Parses a hexadecimal number
+/// # Arguments
+/// * `hex` - The hexadecimal digits, as ASCII bytes
+/// # Returns
+/// * `Result<BigUint, Error>` - The parsed number, or `Error::InvalidHex`
+pub(crate) fn parse_hex(hex: &[u8]) -> std::result::Result<BigUint, Error> {
+    BigUint::parse_bytes(hex, 16)
+        .ok_or_else(|| Error::InvalidHex(String::from_utf8_lossy(hex).into_owned()))
+}
@@ -0,0 +1,365 @@
+use num_bigint::BigUint;
+use std::fmt::{Display, Formatter, Result};
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::hex;
+
+/// The field prime p = 2^256 - 2^32 - 977, as little endian 64-bit limbs
+const P: [u64; 4] = [
+    0xfffffffefffffc2f,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+];
+
+/// 2^256 mod p = 2^32 + 977, used to fold the high half of a product into the low half
+const R: u64 = 0x1000003d1;
+
+/// p - 2, the exponent of the inverse by Fermat's little theorem
+const P_MINUS_2: [u64; 4] = [
+    0xfffffffefffffc2d,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+];
+
+/// (p + 1) / 4, the exponent of the square root since p = 3 mod 4
+const SQRT_EXPONENT: [u64; 4] = [
+    0xffffffffbfffff0c,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+    0x3fffffffffffffff,
+];
+
+/// Fe256 is an element of the secp256k1 base field, the integers modulo p
+/// It is stored on the stack as four little endian 64-bit limbs, always reduced below p
+/// Addition, subtraction and multiplication run in constant time, without branches on the value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Fe256([u64; 4]);
+
+impl Fe256 {
+    pub const ZERO: Fe256 = Fe256([0, 0, 0, 0]);
+    pub const ONE: Fe256 = Fe256([1, 0, 0, 0]);
+
+    /// The cube root of unity beta such that (beta * x, y) = lambda * (x, y) on the curve
+    pub(crate) const BETA: Fe256 = Fe256([
+        0xc1396c28719501ee,
+        0x9cf0497512f58995,
+        0x6e64479eac3434e9,
+        0x7ae96a2b657c0710,
+    ]);
+
+    /// Creates a field element from a small number
+    pub fn from_u64(number: u64) -> Self {
+        Fe256([number, 0, 0, 0])
+    }
+
+    /// Creates a field element from 32 big endian bytes
+    /// # Arguments
+    /// * `bytes` - The number, big endian
+    /// # Returns
+    /// * `Option<Fe256>` - The field element, or None if the number is not smaller than p
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let limbs = limbs_from_bytes(bytes);
+        if geq(&limbs, &P) {
+            return None;
+        }
+        Some(Fe256(limbs))
+    }
+
+    /// Creates a field element from a number
+    /// # Arguments
+    /// * `number` - The number
+    /// # Returns
+    /// * `Option<Fe256>` - The field element, or None if the number is not smaller than p
+    pub fn from_biguint(number: &BigUint) -> Option<Self> {
+        Self::from_bytes(&biguint_to_bytes(number)?)
+    }
+
+    /// Returns the element as 32 big endian bytes
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs_to_bytes(&self.0)
+    }
+
+    /// Returns the element as a number
+    pub fn to_biguint(&self) -> BigUint {
+        BigUint::from_bytes_be(&self.to_bytes())
+    }
+
+    /// Returns whether the element is zero
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0, 0, 0, 0]
+    }
+
+    /// Returns whether the element, as a number below p, is odd
+    pub fn is_odd(&self) -> bool {
+        self.0[0] & 1 == 1
+    }
+
+    /// Returns the square of the element
+    pub fn square(&self) -> Self {
+        *self * *self
+    }
+
+    /// Returns the multiplicative inverse, computed as self^(p-2)
+    /// The inverse of zero is zero
+    pub fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+
+    /// Returns a square root of the element, computed as self^((p+1)/4)
+    /// # Returns
+    /// * `Option<Fe256>` - The root, or None if the element is not a square
+    pub fn sqrt(&self) -> Option<Self> {
+        let root = self.pow(&SQRT_EXPONENT);
+        (root.square() == *self).then_some(root)
+    }
+
+    /// Returns `b` if the mask is all ones and `a` if it is zero, without branching
+    pub(crate) fn select(mask: u64, a: &Self, b: &Self) -> Self {
+        Fe256(select_limbs(mask, &a.0, &b.0))
+    }
+
+    /// Raises the element to a public exponent given as little endian limbs
+    fn pow(&self, exponent: &[u64; 4]) -> Self {
+        let mut result = Fe256::ONE;
+        for i in (0..256).rev() {
+            result = result.square();
+            if (exponent[i / 64] >> (i % 64)) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+        result
+    }
+}
+
+// Formats the Fe256 as 64 hex digits
+impl Display for Fe256 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", hex::encode(&self.to_bytes()))
+    }
+}
+
+// Fe256 addition
+impl Add for Fe256 {
+    type Output = Fe256;
+
+    fn add(self, other: Fe256) -> Fe256 {
+        let (sum, carry) = add_limbs(&self.0, &other.0);
+        Fe256(reduce_once(&sum, carry, &P))
+    }
+}
+
+// Fe256 subtraction
+impl Sub for Fe256 {
+    type Output = Fe256;
+
+    fn sub(self, other: Fe256) -> Fe256 {
+        let (difference, borrow) = sub_limbs(&self.0, &other.0);
+        Fe256(add_limbs(&difference, &P.map(|limb| limb & mask(borrow))).0)
+    }
+}
+
+// Fe256 negation
+impl Neg for Fe256 {
+    type Output = Fe256;
+
+    fn neg(self) -> Fe256 {
+        Fe256::ZERO - self
+    }
+}
+
+// Fe256 multiplication
+impl Mul for Fe256 {
+    type Output = Fe256;
+
+    fn mul(self, other: Fe256) -> Fe256 {
+        Fe256(reduce_wide(&mul_wide(&self.0, &other.0)))
+    }
+}
+
+/// Reduces a 512-bit product modulo p, using 2^256 = 2^32 + 977 (mod p)
+fn reduce_wide(wide: &[u64; 8]) -> [u64; 4] {
+    // Fold the high half: the result fits in 4 limbs plus a carry of at most 34 bits
+    let mut low = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let t = wide[i] as u128 + wide[i + 4] as u128 * R as u128 + carry;
+        low[i] = t as u64;
+        carry = t >> 64;
+    }
+
+    // Fold the carry, which may wrap past 2^256 once more
+    let mut result = [0u64; 4];
+    let mut t = low[0] as u128 + carry * R as u128;
+    result[0] = t as u64;
+    for i in 1..4 {
+        t = low[i] as u128 + (t >> 64);
+        result[i] = t as u64;
+    }
+    // The wrapped value is tiny, so adding 2^256 mod p again cannot overflow
+    let wrapped = (t >> 64) as u64;
+    result = add_limbs(&result, &[R * wrapped, 0, 0, 0]).0;
+
+    reduce_once(&result, false, &P)
+}
+
+/// Multiplies two 256-bit numbers into a 512-bit product
+pub(crate) fn mul_wide(a: &[u64; 4], b: &[u64; 4]) -> [u64; 8] {
+    let mut result = [0u64; 8];
+    for i in 0..4 {
+        let mut carry = 0u128;
+        for j in 0..4 {
+            let t = result[i + j] as u128 + a[i] as u128 * b[j] as u128 + carry;
+            result[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        result[i + 4] = carry as u64;
+    }
+    result
+}
+
+/// Adds two 256-bit numbers, returning the sum modulo 2^256 and whether it overflowed
+pub(crate) fn add_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut result = [0u64; 4];
+    let mut carry = false;
+    for i in 0..4 {
+        let (sum, c1) = a[i].overflowing_add(b[i]);
+        let (sum, c2) = sum.overflowing_add(carry as u64);
+        result[i] = sum;
+        carry = c1 | c2;
+    }
+    (result, carry)
+}
+
+/// Subtracts two 256-bit numbers, returning the difference modulo 2^256 and whether it underflowed
+pub(crate) fn sub_limbs(a: &[u64; 4], b: &[u64; 4]) -> ([u64; 4], bool) {
+    let mut result = [0u64; 4];
+    let mut borrow = false;
+    for i in 0..4 {
+        let (difference, b1) = a[i].overflowing_sub(b[i]);
+        let (difference, b2) = difference.overflowing_sub(borrow as u64);
+        result[i] = difference;
+        borrow = b1 | b2;
+    }
+    (result, borrow)
+}
+
+/// Subtracts the modulus from a value below twice the modulus if the value overflowed
+/// 2^256 or is not below the modulus, without branching
+pub(crate) fn reduce_once(value: &[u64; 4], overflow: bool, modulus: &[u64; 4]) -> [u64; 4] {
+    let (difference, borrow) = sub_limbs(value, modulus);
+    select_limbs(mask(overflow | !borrow), value, &difference)
+}
+
+/// Returns an all ones mask if the flag is set and zero otherwise
+pub(crate) fn mask(flag: bool) -> u64 {
+    // Hide the flag from the optimizer so that selections stay branch free
+    std::hint::black_box(flag as u64).wrapping_neg()
+}
+
+/// Returns `b` if the mask is all ones and `a` if it is zero, without branching
+pub(crate) fn select_limbs(mask: u64, a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut result = [0u64; 4];
+    for i in 0..4 {
+        result[i] = a[i] ^ ((a[i] ^ b[i]) & mask);
+    }
+    result
+}
+
+/// Returns whether a >= b
+pub(crate) fn geq(a: &[u64; 4], b: &[u64; 4]) -> bool {
+    !sub_limbs(a, b).1
+}
+
+/// Reads 32 big endian bytes as little endian limbs
+pub(crate) fn limbs_from_bytes(bytes: &[u8; 32]) -> [u64; 4] {
+    let mut limbs = [0u64; 4];
+    for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+        limbs[3 - i] = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    limbs
+}
+
+/// Writes little endian limbs as 32 big endian bytes
+pub(crate) fn limbs_to_bytes(limbs: &[u64; 4]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (i, chunk) in bytes.chunks_exact_mut(8).enumerate() {
+        chunk.copy_from_slice(&limbs[3 - i].to_be_bytes());
+    }
+    bytes
+}
+
+/// Encodes a number as 32 big endian bytes, or None if it does not fit
+pub(crate) fn biguint_to_bytes(number: &BigUint) -> Option<[u8; 32]> {
+    let bytes = number.to_bytes_be();
+    if bytes.len() > 32 {
+        return None;
+    }
+    let mut result = [0u8; 32];
+    result[32 - bytes.len()..].copy_from_slice(&bytes);
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prime() -> BigUint {
+        BigUint::from_bytes_be(&limbs_to_bytes(&P))
+    }
+
+    fn fe(number: &BigUint) -> Fe256 {
+        Fe256::from_biguint(number).unwrap()
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(Fe256::from_bytes(&limbs_to_bytes(&P)), None);
+        let max = Fe256::from_biguint(&(prime() - 1u32)).unwrap();
+        assert_eq!(max.to_biguint(), prime() - 1u32);
+        assert_eq!(Fe256::from_biguint(&(BigUint::from(1u32) << 256)), None);
+    }
+
+    #[test]
+    fn test_arithmetic_matches_biguint() {
+        let p = prime();
+        let values = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            BigUint::from(977u32),
+            &p - 1u32,
+            &p - 2u32,
+            BigUint::parse_bytes(
+                b"79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                16,
+            )
+            .unwrap(),
+            BigUint::parse_bytes(
+                b"fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffff000",
+                16,
+            )
+            .unwrap(),
+        ];
+        for a in &values {
+            for b in &values {
+                assert_eq!((fe(a) + fe(b)).to_biguint(), (a + b) % &p);
+                assert_eq!((fe(a) - fe(b)).to_biguint(), (a + &p - b) % &p);
+                assert_eq!((fe(a) * fe(b)).to_biguint(), (a * b) % &p);
+            }
+            assert_eq!((-fe(a) + fe(a)), Fe256::ZERO);
+        }
+    }
+
+    #[test]
+    fn test_invert_and_sqrt() {
+        let x = Fe256::from_u64(0xdeadbeef);
+        assert_eq!(x * x.invert(), Fe256::ONE);
+        assert_eq!(Fe256::ZERO.invert(), Fe256::ZERO);
+
+        let root = Fe256::from_u64(4).sqrt().unwrap();
+        assert_eq!(root.square(), Fe256::from_u64(4));
+        // 3 is not a square modulo p
+        assert_eq!(Fe256::from_u64(3).sqrt(), None);
+    }
+}
@@ -4,6 +4,8 @@ use std::{
     ops::{Add, Div, Mul, Sub},
 };
 
+use crate::error::{parse_hex, Error};
+
 /// FieldElement represents a number in a finite field defined by a prime number.
 /// The number is always less than the prime number.
 /// The operations are defined in the finite field.
@@ -24,18 +26,17 @@ impl FieldElement {
     /// # Returns
     /// * `FieldElement` - The FieldElement created from the number
     pub fn from_int(number: u32, prime: u32) -> FieldElement {
-        if number >= prime {
-            panic!(
-                "Num {} not in field range 0 to {}",
-                number,
-                prime - BigUint::from(1u32)
-            );
-        }
+        Self::try_from_int(number, prime).unwrap_or_else(|err| panic!("{}", err))
+    }
 
-        FieldElement {
-            number: BigUint::from(number),
-            prime: BigUint::from(prime),
-        }
+    /// Creates a new FieldElement from a number and a prime number
+    /// # Arguments
+    /// * `number` - A number in the finite field
+    /// * `prime` - The prime number that defines the finite field
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The FieldElement, or `Error::NumberOutOfRange`
+    pub fn try_from_int(number: u32, prime: u32) -> Result<FieldElement, Error> {
+        Self::try_from_biguint(BigUint::from(number), BigUint::from(prime))
     }
 
     /// Creates a new FieldElement from a byte array and a prime number
@@ -44,21 +45,48 @@ impl FieldElement {
     /// * `number` - A number in the finite field
     /// * `prime` - The prime number that defines the finite field
     /// # Panics
-    /// If the number is greater than or equal to the prime number
+    /// If the number is greater than or equal to the prime number, or either is not valid hex
     /// # Returns
     /// * `FieldElement` - The FieldElement created from the byte array
     pub fn from_bytes(number: &[u8], prime: &[u8]) -> FieldElement {
-        let number = BigUint::parse_bytes(number, 16).unwrap();
-        let prime = BigUint::parse_bytes(prime, 16).unwrap();
+        Self::try_from_bytes(number, prime).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new FieldElement from a byte array and a prime number, both hex encoded
+    /// # Arguments
+    /// * `number` - A number in the finite field
+    /// * `prime` - The prime number that defines the finite field
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The FieldElement, or why it could not be created
+    pub fn try_from_bytes(number: &[u8], prime: &[u8]) -> Result<FieldElement, Error> {
+        Self::try_from_biguint(parse_hex(number)?, parse_hex(prime)?)
+    }
+
+    /// Creates a new FieldElement from a BigUint number and a BigUint prime number
+    /// The number must be less than the prime number
+    /// # Arguments
+    /// * `number` - A number in the finite field
+    /// * `prime` - The prime number that defines the finite field
+    /// # Panics
+    /// If the number is greater than or equal to the prime number
+    /// # Returns
+    /// * `FieldElement` - The FieldElement created from the number
+    pub fn from_biguint(number: BigUint, prime: BigUint) -> FieldElement {
+        Self::try_from_biguint(number, prime).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new FieldElement from a BigUint number and a BigUint prime number
+    /// # Arguments
+    /// * `number` - A number in the finite field
+    /// * `prime` - The prime number that defines the finite field
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The FieldElement, or `Error::NumberOutOfRange`
+    pub fn try_from_biguint(number: BigUint, prime: BigUint) -> Result<FieldElement, Error> {
         if number >= prime {
-            panic!(
-                "Num {} not in field range 0 to {}",
-                number,
-                prime - BigUint::from(1u32)
-            );
+            return Err(Error::NumberOutOfRange { number, prime });
         }
 
-        FieldElement { number, prime }
+        Ok(FieldElement { number, prime })
     }
 
     /// Returns the number of the FieldElement
@@ -87,6 +115,80 @@ impl FieldElement {
             prime: self.prime.clone(),
         }
     }
+
+    /// Returns the square root of the FieldElement
+    /// Only valid when the prime satisfies p % 4 == 3, as secp256k1's does,
+    /// so that sqrt(w) = w^((p + 1) / 4)
+    /// # Returns
+    /// * `FieldElement` - A square root of the FieldElement, if one exists
+    pub fn sqrt(&self) -> Self {
+        self.pow((&self.prime + BigUint::from(1u32)) / BigUint::from(4u32))
+    }
+
+    /// Adds two FieldElement values
+    /// # Arguments
+    /// * `other` - The FieldElement to add
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The sum, or `Error::DifferentFields`
+    pub fn try_add(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_field(other)?;
+        Ok(FieldElement {
+            number: (&self.number + &other.number) % &self.prime,
+            prime: self.prime.clone(),
+        })
+    }
+
+    /// Subtracts two FieldElement values
+    /// # Arguments
+    /// * `other` - The FieldElement to subtract
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The difference, or `Error::DifferentFields`
+    pub fn try_sub(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_field(other)?;
+        Ok(FieldElement {
+            // Ensuring positive result
+            number: (&self.number + &self.prime - &other.number) % &self.prime,
+            prime: self.prime.clone(),
+        })
+    }
+
+    /// Multiplies two FieldElement values
+    /// # Arguments
+    /// * `other` - The FieldElement to multiply by
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The product, or `Error::DifferentFields`
+    pub fn try_mul(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_field(other)?;
+        Ok(FieldElement {
+            number: (&self.number * &other.number) % &self.prime,
+            prime: self.prime.clone(),
+        })
+    }
+
+    /// Divides one FieldElement by another using Fermat's Little Theorem
+    /// # Arguments
+    /// * `other` - The FieldElement to divide by
+    /// # Returns
+    /// * `Result<FieldElement, Error>` - The quotient, or `Error::DifferentFields`
+    pub fn try_div(&self, other: &Self) -> Result<Self, Error> {
+        self.check_same_field(other)?;
+        // Calculate other's multiplicative inverse using Fermat's Little Theorem
+        let inv = &other
+            .number
+            .modpow(&(&self.prime - BigUint::from(2u32)), &self.prime);
+        Ok(FieldElement {
+            number: (&self.number * inv) % &self.prime,
+            prime: self.prime.clone(),
+        })
+    }
+
+    /// Returns `Error::DifferentFields` if the FieldElement values are in different fields
+    fn check_same_field(&self, other: &Self) -> Result<(), Error> {
+        if self.prime != other.prime {
+            return Err(Error::DifferentFields);
+        }
+        Ok(())
+    }
 }
 
 // Formats the FieldElement
@@ -108,14 +210,7 @@ impl Add for FieldElement {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        if self.prime != other.prime {
-            panic!("Cannot operate with two numbers in different Fields");
-        }
-
-        FieldElement {
-            number: (&self.number + &other.number) % &self.prime,
-            prime: self.prime.clone(),
-        }
+        self.try_add(&other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -124,15 +219,7 @@ impl Sub for FieldElement {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
-        if self.prime != other.prime {
-            panic!("Cannot operate with two numbers in different Fields");
-        }
-
-        FieldElement {
-            // Ensuring positive result
-            number: (&self.number + &self.prime - &other.number) % &self.prime,
-            prime: self.prime.clone(),
-        }
+        self.try_sub(&other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -141,14 +228,7 @@ impl Mul<Self> for FieldElement {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self {
-        if self.prime != other.prime {
-            panic!("Cannot operate with two numbers in different Fields");
-        }
-
-        FieldElement {
-            number: (&self.number * &other.number) % &self.prime,
-            prime: self.prime.clone(),
-        }
+        self.try_mul(&other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -178,18 +258,7 @@ impl Div for FieldElement {
     type Output = Self;
 
     fn div(self, other: Self) -> Self {
-        if self.prime != other.prime {
-            panic!("Cannot operate with two numbers in different Fields");
-        }
-        // Calculate other's multiplicative inverse using Fermat's Little Theorem
-        let inv = &other
-            .number
-            .modpow(&(&self.prime - BigUint::from(2u32)), &self.prime);
-        let num = (&self.number * inv) % &self.prime;
-        FieldElement {
-            number: num,
-            prime: self.prime.clone(),
-        }
+        self.try_div(&other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -243,6 +312,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_try_from() {
+        assert_eq!(
+            FieldElement::try_from_int(32, 31),
+            Err(Error::NumberOutOfRange {
+                number: BigUint::from(32u32),
+                prime: BigUint::from(31u32)
+            })
+        );
+        assert_eq!(
+            FieldElement::try_from_bytes(b"1g", b"1f"),
+            Err(Error::InvalidHex(String::from("1g")))
+        );
+        assert_eq!(
+            FieldElement::try_from_bytes(b"1e", b"1f"),
+            Ok(FieldElement::from_int(30, 31))
+        );
+    }
+
+    #[test]
+    fn test_try_ops_different_fields() {
+        let a = FieldElement::from_int(2, 31);
+        let b = FieldElement::from_int(2, 37);
+        assert_eq!(a.try_add(&b), Err(Error::DifferentFields));
+        assert_eq!(a.try_sub(&b), Err(Error::DifferentFields));
+        assert_eq!(a.try_mul(&b), Err(Error::DifferentFields));
+        assert_eq!(a.try_div(&b), Err(Error::DifferentFields));
+    }
+
+    #[test]
+    #[should_panic(expected = "different Fields")]
+    fn test_add_different_fields_panic() {
+        let _ = FieldElement::from_int(2, 31) + FieldElement::from_int(2, 37);
+    }
+
     #[test]
     fn test_eq() {
         let a = FieldElement::from_int(2, 31);
@@ -316,6 +420,19 @@ mod tests {
         assert_eq!(a.pow(5u32) * b, FieldElement::from_int(16, 31));
     }
 
+    #[test]
+    fn test_from_biguint() {
+        let a = FieldElement::from_biguint(BigUint::from(2u32), BigUint::from(31u32));
+        assert_eq!(a, FieldElement::from_int(2, 31));
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let a = FieldElement::from_int(5, 31);
+        let root = a.pow(2u32).sqrt();
+        assert!(root == a || root == FieldElement::from_int(26, 31));
+    }
+
     #[test]
     fn test_div() {
         let a = FieldElement::from_int(3, 31);
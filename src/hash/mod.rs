@@ -0,0 +1,270 @@
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use ripemd::Ripemd160;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::fmt::{Display, Formatter, Result};
+
+use crate::hex;
+
+/// Hash256 is a 32 byte digest, as produced by SHA-256 based hash functions
+/// The bytes are kept in the order the hash function outputs them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hash256([u8; 32]);
+
+/// Hash160 is a 20 byte digest, as produced by `hash160`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hash160([u8; 20]);
+
+impl Hash256 {
+    /// Creates a Hash256 from its bytes
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Hash256(bytes)
+    }
+
+    /// Creates a Hash256 from a slice, if it is exactly 32 bytes long
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Some(Hash256(bytes.try_into().ok()?))
+    }
+
+    /// Returns the bytes of the digest
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Returns the digest with its bytes reversed
+    /// Transaction and block ids are displayed in this little endian order
+    pub fn reversed(&self) -> Self {
+        let mut bytes = self.0;
+        bytes.reverse();
+        Hash256(bytes)
+    }
+
+    /// Returns the digest read as a big endian number, as used for the `z` of a signature
+    pub fn to_biguint(&self) -> BigUint {
+        BigUint::from_bytes_be(&self.0)
+    }
+}
+
+impl Hash160 {
+    /// Creates a Hash160 from its bytes
+    pub fn from_bytes(bytes: [u8; 20]) -> Self {
+        Hash160(bytes)
+    }
+
+    /// Creates a Hash160 from a slice, if it is exactly 20 bytes long
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Some(Hash160(bytes.try_into().ok()?))
+    }
+
+    /// Returns the bytes of the digest
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    /// Returns the digest with its bytes reversed
+    pub fn reversed(&self) -> Self {
+        let mut bytes = self.0;
+        bytes.reverse();
+        Hash160(bytes)
+    }
+}
+
+// Formats the Hash256 as hex
+impl Display for Hash256 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", hex::encode(&self.0))
+    }
+}
+
+// Formats the Hash160 as hex
+impl Display for Hash160 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", hex::encode(&self.0))
+    }
+}
+
+/// Computes the SHA-256 of the data
+/// # Arguments
+/// * `data` - The data to hash
+/// # Returns
+/// * `Hash256` - The digest
+pub fn sha256(data: &[u8]) -> Hash256 {
+    Hash256(Sha256::digest(data).into())
+}
+
+/// Computes the double SHA-256 of the data, used for transaction ids and checksums
+/// # Arguments
+/// * `data` - The data to hash
+/// # Returns
+/// * `Hash256` - The digest
+pub fn hash256(data: &[u8]) -> Hash256 {
+    Hash256(Sha256::digest(Sha256::digest(data)).into())
+}
+
+/// Computes the RIPEMD-160 of the data
+/// # Arguments
+/// * `data` - The data to hash
+/// # Returns
+/// * `Hash160` - The digest
+pub fn ripemd160(data: &[u8]) -> Hash160 {
+    Hash160(Ripemd160::digest(data).into())
+}
+
+/// Computes the SHA-1 of the data, only needed by OP_SHA1
+/// # Arguments
+/// * `data` - The data to hash
+/// # Returns
+/// * `Hash160` - The digest
+pub fn sha1(data: &[u8]) -> Hash160 {
+    Hash160(Sha1::digest(data).into())
+}
+
+/// Computes the RIPEMD-160 of the SHA-256 of the data, used for addresses
+/// # Arguments
+/// * `data` - The data to hash
+/// # Returns
+/// * `Hash160` - The digest
+pub fn hash160(data: &[u8]) -> Hash160 {
+    Hash160(Ripemd160::digest(Sha256::digest(data)).into())
+}
+
+/// Computes the BIP340 tagged hash SHA-256(SHA-256(tag) || SHA-256(tag) || data)
+/// # Arguments
+/// * `tag` - The tag that separates this use of the hash from others
+/// * `data` - The data to hash
+/// # Returns
+/// * `Hash256` - The digest
+pub fn tagged_hash(tag: &str, data: &[u8]) -> Hash256 {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    hasher.update(data);
+    Hash256(hasher.finalize().into())
+}
+
+/// Computes the HMAC-SHA512 of the data, used to derive BIP32 keys
+/// # Arguments
+/// * `key` - The HMAC key
+/// * `data` - The data to authenticate
+/// # Returns
+/// * `[u8; 64]` - The authentication code
+pub fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Derives a 64 byte key from a password with PBKDF2-HMAC-SHA512, used for BIP39 seeds
+/// # Arguments
+/// * `password` - The password
+/// * `salt` - The salt
+/// * `iterations` - The number of HMAC iterations, at least 1
+/// # Returns
+/// * `[u8; 64]` - The derived key, a single block of output
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 64] {
+    let keyed = Hmac::<Sha512>::new_from_slice(password).expect("HMAC accepts keys of any length");
+    // U1 = HMAC(password, salt || INT(1)), then Ui = HMAC(password, Ui-1), all XORed
+    let mut mac = keyed.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut block: [u8; 64] = mac.finalize().into_bytes().into();
+    let mut result = block;
+    for _ in 1..iterations {
+        let mut mac = keyed.clone();
+        mac.update(&block);
+        block = mac.finalize().into_bytes().into();
+        result.iter_mut().zip(block).for_each(|(r, b)| *r ^= b);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s256point::S256Point;
+
+    #[test]
+    fn test_hash256() {
+        assert_eq!(
+            hash256(b"").to_string(),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+        assert_eq!(
+            hash256(b"hello").to_string(),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+    }
+
+    #[test]
+    fn test_hash160() {
+        assert_eq!(
+            hash160(&S256Point::generator().sec(true)).to_string(),
+            "751e76e8199196d454941c45d1b3a323f1433bd6"
+        );
+        assert_eq!(
+            hash160(b"hello").to_string(),
+            "b6a9c8c230722b7c748331a8b450f05566dc7d0f"
+        );
+    }
+
+    #[test]
+    fn test_sha1() {
+        assert_eq!(
+            sha1(b"abc").to_string(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        assert_eq!(
+            tagged_hash("BIP0340/challenge", b"hello").to_string(),
+            "a97ff4dc59e2e158c00a7d9cf1e7d60fb090ecf5f728b6d17be7cbbb0fc572dd"
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha512() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex::encode(&hmac_sha512(b"Jefe", b"what do ya want for nothing?")),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+             9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_hmac_sha512() {
+        assert_eq!(
+            hex::encode(&pbkdf2_hmac_sha512(b"password", b"salt", 2)),
+            "e1d9c16aa681708a45f5c7c4e215ceb66e011a2e9f0040713f18aefdb866d53c\
+             f76cab2868a39b9f7840edce4fef5a82be67335c77a6068e04112754f27ccf4e"
+        );
+    }
+
+    #[test]
+    fn test_byte_order() {
+        let hash = hash256(b"hello");
+        assert_eq!(
+            hash.reversed().to_string(),
+            "503d8319a48348cdc610a582f7bf754b5833df65038606eb48510790dfc99595"
+        );
+        assert_eq!(hash.reversed().reversed(), hash);
+        assert_eq!(
+            hash.to_biguint(),
+            BigUint::parse_bytes(
+                b"9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50",
+                16
+            )
+            .unwrap()
+        );
+        assert_eq!(Hash256::from_slice(hash.as_bytes()), Some(hash));
+        assert_eq!(Hash256::from_slice(&[0u8; 31]), None);
+        assert_eq!(
+            Hash160::from_slice(&[0u8; 20]),
+            Some(Hash160::from_bytes([0u8; 20]))
+        );
+    }
+}
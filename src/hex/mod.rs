@@ -0,0 +1,48 @@
+use crate::error::Error;
+
+/// Encodes bytes as a lowercase hexadecimal string
+/// # Arguments
+/// * `data` - The bytes to encode
+/// # Returns
+/// * `String` - Two hexadecimal digits per byte
+pub fn encode(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes a hexadecimal string into bytes, keeping leading zero bytes
+/// # Arguments
+/// * `s` - The hexadecimal string, with an even number of digits
+/// # Returns
+/// * `Result<Vec<u8>, Error>` - The decoded bytes, or `Error::InvalidHex`
+pub fn decode(s: &str) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::InvalidHex(s.to_string());
+    if !s.len().is_multiple_of(2) || !s.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data = vec![0x00, 0x01, 0xab, 0xff];
+        assert_eq!(encode(&data), "0001abff");
+        assert_eq!(decode("0001abff").unwrap(), data);
+        assert_eq!(decode("0001ABFF").unwrap(), data);
+        assert_eq!(decode("").unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("abc"), Err(Error::InvalidHex(String::from("abc"))));
+        assert_eq!(decode("zz"), Err(Error::InvalidHex(String::from("zz"))));
+        assert_eq!(decode("+1"), Err(Error::InvalidHex(String::from("+1"))));
+    }
+}
@@ -0,0 +1,345 @@
+use std::sync::OnceLock;
+
+use crate::fe256::Fe256;
+use crate::s256point::S256Point;
+use crate::scalar::Scalar;
+
+/// The wNAF width used for points without a precomputed table
+const WNAF_WIDTH: usize = 5;
+/// The wNAF width used for the generator, whose 64 odd multiples are computed once
+const GENERATOR_WNAF_WIDTH: usize = 8;
+
+/// JacobianPoint is a secp256k1 point in Jacobian coordinates (X, Y, Z), standing for the
+/// affine point (X / Z^2, Y / Z^3), so that additions and doublings need no modular inversion
+/// Only the final conversion back to affine coordinates pays for one inversion
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct JacobianPoint {
+    x: Fe256,
+    y: Fe256,
+    z: Fe256,
+}
+
+impl JacobianPoint {
+    /// Returns the point at infinity, the only point with Z = 0
+    pub(crate) fn infinity() -> Self {
+        JacobianPoint {
+            x: Fe256::ONE,
+            y: Fe256::ONE,
+            z: Fe256::ZERO,
+        }
+    }
+
+    /// Converts an affine point, with Z = 1
+    pub(crate) fn from_affine(point: &S256Point) -> Self {
+        match (point.get_x(), point.get_y()) {
+            (Some(x), Some(y)) => JacobianPoint {
+                x: *x,
+                y: *y,
+                z: Fe256::ONE,
+            },
+            _ => Self::infinity(),
+        }
+    }
+
+    /// Returns whether the point is the point at infinity
+    pub(crate) fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    /// Converts the point to affine coordinates with one modular inversion
+    pub(crate) fn to_affine(self) -> S256Point {
+        if self.is_infinity() {
+            return S256Point::infinity();
+        }
+        let z_inv = self.z.invert();
+        let z_inv2 = z_inv.square();
+        S256Point::from_coordinates(self.x * z_inv2, self.y * z_inv2 * z_inv)
+    }
+
+    /// Doubles the point, using the a = 0 formulas (dbl-2009-l)
+    pub(crate) fn double(&self) -> Self {
+        if self.is_infinity() || self.y.is_zero() {
+            return Self::infinity();
+        }
+        let a = self.x.square();
+        let b = self.y.square();
+        let c = b.square();
+        let x_plus_b = self.x + b;
+        let d = x_plus_b.square() - a - c;
+        let d = d + d;
+        let e = a + a + a;
+        let f = e.square();
+        let x3 = f - d - d;
+        let c2 = c + c;
+        let c4 = c2 + c2;
+        let y3 = e * (d - x3) - (c4 + c4);
+        let yz = self.y * self.z;
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: yz + yz,
+        }
+    }
+
+    /// Adds two points (add-2007-bl), falling back to doubling when they are equal
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        if self.is_infinity() {
+            return *other;
+        }
+        if other.is_infinity() {
+            return *self;
+        }
+        let z1z1 = self.z.square();
+        let z2z2 = other.z.square();
+        let u1 = self.x * z2z2;
+        let u2 = other.x * z1z1;
+        let s1 = self.y * other.z * z2z2;
+        let s2 = other.y * self.z * z1z1;
+        let h = u2 - u1;
+        let r = s2 - s1;
+        if h.is_zero() {
+            // Same x: either the same point or its negation
+            return if r.is_zero() {
+                self.double()
+            } else {
+                Self::infinity()
+            };
+        }
+        let hh = h.square();
+        let hhh = h * hh;
+        let v = u1 * hh;
+        let x3 = r.square() - hhh - v - v;
+        let y3 = r * (v - x3) - s1 * hhh;
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: self.z * other.z * h,
+        }
+    }
+
+    /// Returns whether the affine x coordinate of the point equals x, without an inversion,
+    /// by checking X = x * Z^2
+    pub(crate) fn has_x(&self, x: &Fe256) -> bool {
+        !self.is_infinity() && self.x == *x * self.z.square()
+    }
+
+    /// Returns the negation of the point, (X, -Y, Z)
+    pub(crate) fn neg(&self) -> Self {
+        JacobianPoint {
+            x: self.x,
+            y: -self.y,
+            z: self.z,
+        }
+    }
+
+    /// Returns the odd multiples P, 3P, 5P, ... of the point, as used by a wNAF of the
+    /// given width
+    pub(crate) fn odd_multiples(&self, width: usize) -> Vec<Self> {
+        let double = self.double();
+        let mut multiples = vec![*self];
+        for i in 1..1 << (width - 2) {
+            multiples.push(multiples[i - 1].add(&double));
+        }
+        multiples
+    }
+
+    /// Returns lambda * P, computed with the endomorphism (X, Y, Z) -> (beta * X, Y, Z)
+    pub(crate) fn endomorphism(&self) -> Self {
+        JacobianPoint {
+            x: self.x * Fe256::BETA,
+            y: self.y,
+            z: self.z,
+        }
+    }
+
+    /// Computes u * G + the sum of several scalar multiplications, in variable time
+    /// Every scalar is split with the GLV endomorphism into two halves of about 128 bits,
+    /// which halves the shared chain of doublings
+    /// The generator uses a wider wNAF with process-wide tables of the odd multiples of G
+    /// and lambda * G, so it needs fewer additions than the other terms
+    /// # Arguments
+    /// * `u` - The scalar to multiply the generator by
+    /// * `terms` - The pairs of scalar and point to multiply
+    /// # Returns
+    /// * `JacobianPoint` - The sum of the products
+    pub(crate) fn multi_mul_with_generator(u: &Scalar, terms: &[(Scalar, JacobianPoint)]) -> Self {
+        static GENERATOR_MULTIPLES: OnceLock<[Vec<JacobianPoint>; 2]> = OnceLock::new();
+        let [generator_multiples, lambda_multiples] = GENERATOR_MULTIPLES.get_or_init(|| {
+            let multiples = JacobianPoint::from_affine(&S256Point::generator())
+                .odd_multiples(GENERATOR_WNAF_WIDTH);
+            let lambda_multiples = multiples.iter().map(Self::endomorphism).collect();
+            [multiples, lambda_multiples]
+        });
+
+        let multiples: Vec<(Vec<JacobianPoint>, Vec<JacobianPoint>)> = terms
+            .iter()
+            .map(|(_, point)| {
+                let multiples = point.odd_multiples(WNAF_WIDTH);
+                let lambda_multiples = multiples.iter().map(Self::endomorphism).collect();
+                (multiples, lambda_multiples)
+            })
+            .collect();
+
+        let (u1, u2) = u.split_lambda();
+        let mut strauss_terms = vec![
+            (
+                signed_wnaf(&u1, GENERATOR_WNAF_WIDTH),
+                generator_multiples.as_slice(),
+            ),
+            (
+                signed_wnaf(&u2, GENERATOR_WNAF_WIDTH),
+                lambda_multiples.as_slice(),
+            ),
+        ];
+        for ((scalar, _), (multiples, lambda_multiples)) in terms.iter().zip(&multiples) {
+            let (k1, k2) = scalar.split_lambda();
+            strauss_terms.push((signed_wnaf(&k1, WNAF_WIDTH), multiples.as_slice()));
+            strauss_terms.push((signed_wnaf(&k2, WNAF_WIDTH), lambda_multiples.as_slice()));
+        }
+        Self::strauss(&strauss_terms)
+    }
+
+    /// Runs the Strauss loop over wNAF digits and the matching odd multiples
+    fn strauss(terms: &[(Vec<i8>, &[JacobianPoint])]) -> Self {
+        let len = terms.iter().map(|(digits, _)| digits.len()).max();
+        let mut result = Self::infinity();
+        for i in (0..len.unwrap_or(0)).rev() {
+            result = result.double();
+            for (digits, multiples) in terms {
+                match digits.get(i) {
+                    Some(&digit) if digit > 0 => {
+                        result = result.add(&multiples[digit as usize / 2]);
+                    }
+                    Some(&digit) if digit < 0 => {
+                        result = result.add(&multiples[-digit as usize / 2].neg());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Returns the wNAF of a half from the GLV split, which stands for a negative number when
+/// it is high, as the wNAF of its absolute value with the signs flipped
+fn signed_wnaf(scalar: &Scalar, width: usize) -> Vec<i8> {
+    if scalar.is_high() {
+        (-*scalar).wnaf(width).iter().map(|digit| -digit).collect()
+    } else {
+        scalar.wnaf(width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::projective::ProjectivePoint;
+    use num_bigint::BigUint;
+
+    /// Multiplies a point by a scalar, without the generator term
+    fn mul(point: &JacobianPoint, scalar: Scalar) -> JacobianPoint {
+        JacobianPoint::multi_mul_with_generator(&Scalar::ZERO, &[(scalar, *point)])
+    }
+
+    #[test]
+    fn test_mul_matches_affine() {
+        let g = S256Point::generator();
+        for k in [1u32, 2, 3, 7, 1000, 0xdeadbeef] {
+            let expected = g.get_point() * BigUint::from(k);
+            let scalar = Scalar::from_u64(k as u64);
+            let jacobian = mul(&JacobianPoint::from_affine(&g), scalar);
+            assert_eq!(jacobian.to_affine().get_point(), expected);
+            let jacobian = JacobianPoint::multi_mul_with_generator(&scalar, &[]);
+            assert_eq!(jacobian.to_affine().get_point(), expected);
+        }
+    }
+
+    #[test]
+    fn test_multi_mul_with_generator() {
+        let g = S256Point::generator();
+        let p = g * Scalar::from_u64(0xabcdef);
+        let q = g * Scalar::from_u64(0x123456789);
+        let (u, v, w) = (
+            Scalar::from_bytes_reduced(&[0x3c; 32]),
+            -Scalar::from_u64(5),
+            Scalar::from_bytes_reduced(&[0xe1; 32]),
+        );
+        let expected = g.get_point() * u.to_biguint()
+            + p.get_point() * v.to_biguint()
+            + q.get_point() * w.to_biguint();
+        let sum = JacobianPoint::multi_mul_with_generator(
+            &u,
+            &[
+                (v, JacobianPoint::from_affine(&p)),
+                (w, JacobianPoint::from_affine(&q)),
+            ],
+        );
+        assert_eq!(sum.to_affine().get_point(), expected);
+        assert!(JacobianPoint::multi_mul_with_generator(&Scalar::ZERO, &[]).is_infinity());
+
+        let x = sum.to_affine().get_x().copied().unwrap();
+        assert!(sum.has_x(&x));
+        assert!(!sum.has_x(&(x + Fe256::ONE)));
+    }
+
+    #[test]
+    fn test_endomorphism() {
+        let g = S256Point::generator();
+        let lambda = Scalar::from_bytes(&[
+            0x53, 0x63, 0xad, 0x4c, 0xc0, 0x5c, 0x30, 0xe0, 0xa5, 0x26, 0x1c, 0x02, 0x88, 0x12,
+            0x64, 0x5a, 0x12, 0x2e, 0x22, 0xea, 0x20, 0x81, 0x66, 0x78, 0xdf, 0x02, 0x96, 0x7c,
+            0x1b, 0x23, 0xbd, 0x72,
+        ])
+        .unwrap();
+        let expected = ProjectivePoint::from_affine(&g).mul(&lambda).to_affine();
+        assert_eq!(
+            JacobianPoint::from_affine(&g).endomorphism().to_affine(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_glv_matches_plain() {
+        let g = S256Point::generator();
+        let p = g * Scalar::from_bytes_reduced(&[0x42; 32]);
+        let mut bytes = [0u8; 32];
+        bytes[15] = 1;
+        let scalars = [
+            Scalar::ONE,
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&bytes),
+            -Scalar::from_bytes_reduced(&bytes),
+            Scalar::from_bytes_reduced(&[0xff; 32]),
+            Scalar::from_bytes_reduced(&[0x7f; 32]),
+            Scalar::from_bytes_reduced(&[0xc3; 32]),
+        ];
+        for (u, v) in scalars.iter().zip(scalars.iter().rev()) {
+            let plain = ProjectivePoint::from_affine(&g)
+                .mul(u)
+                .add(&ProjectivePoint::from_affine(&p).mul(v))
+                .to_affine();
+            let glv =
+                JacobianPoint::multi_mul_with_generator(u, &[(*v, JacobianPoint::from_affine(&p))]);
+            assert_eq!(glv.to_affine(), plain);
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let g = JacobianPoint::from_affine(&S256Point::generator());
+        let two_g = g.double();
+        let three_g = mul(&g, Scalar::from_u64(3));
+        assert_eq!(
+            two_g.add(&g).to_affine(),
+            three_g.to_affine(),
+            "mixed Z coordinates"
+        );
+        assert_eq!(g.add(&g).to_affine(), two_g.to_affine());
+
+        assert!(g.add(&g.neg()).is_infinity());
+        assert_eq!(mul(&g, -Scalar::ONE).to_affine(), g.neg().to_affine());
+        assert!(JacobianPoint::infinity().double().is_infinity());
+        assert_eq!(JacobianPoint::infinity().add(&g).to_affine(), g.to_affine());
+    }
+}
@@ -1,11 +1,47 @@
+mod address;
+pub mod base58;
+pub mod base64;
+mod batch;
+pub mod bech32;
+mod bip32;
+mod error;
+mod fe256;
 mod field_element;
+pub mod hash;
+pub mod hex;
+mod jacobian;
+pub mod message;
+mod mnemonic;
+mod network;
+pub mod op;
 mod point;
+mod precomputed;
 mod private_key;
+mod projective;
+mod recoverable;
 mod s256point;
+mod scalar;
+mod schnorr;
+mod script;
 mod signature;
+mod tx;
+pub mod varint;
 
+pub use crate::address::{Address, AddressError, Payload, WitnessProgram};
+pub use crate::batch::BatchVerifier;
+pub use crate::bip32::{Bip32Error, DerivationPath, ExtendedPrivKey, ExtendedPubKey, HARDENED};
+pub use crate::error::Error;
+pub use crate::fe256::Fe256;
 pub use crate::field_element::FieldElement;
+pub use crate::mnemonic::{Mnemonic, MnemonicError};
+pub use crate::network::Network;
 pub use crate::point::Point;
-pub use crate::private_key::PrivateKey;
-pub use crate::s256point::S256Point;
-pub use crate::signature::Signature;
+pub use crate::precomputed::PrecomputedPoint;
+pub use crate::private_key::{PrivateKey, WifError};
+pub use crate::recoverable::{RecoverableSignature, RecoveryError};
+pub use crate::s256point::{S256Point, SecError};
+pub use crate::scalar::Scalar;
+pub use crate::schnorr::{SchnorrError, SchnorrSignature, XOnlyPublicKey};
+pub use crate::script::{Cmd, Script};
+pub use crate::signature::{DerError, Signature};
+pub use crate::tx::{Tx, TxIn, TxOut};
@@ -1,3 +1,4 @@
+use bitcoin::hex;
 use bitcoin::FieldElement;
 use bitcoin::Point;
 use bitcoin::PrivateKey;
@@ -68,10 +69,10 @@ fn main() {
     // S256Point
     let s256point: S256Point = S256Point::generator();
     println!("{}", s256point);
-    println!("{}", s256point.get_point().clone() * BigUint::from(2u32));
+    println!("{}", s256point.get_point() * BigUint::from(2u32));
 
     let n = BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap();
-    println!("{}", s256point.get_point().clone() * n);
+    println!("{}", s256point.get_point() * n);
 
     // Verifying signature
     let z = b"bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423";
@@ -80,6 +81,17 @@ fn main() {
     let px = b"04519fac3d910ca7e7138f7013706f619fa8f033e6ec6e09370ea38cee6a7574";
     let py = b"82b51eab8c27c66e26c858a079bcdf4f1ada34cec420cafc7eac1a42216fb6c4";
     let point = S256Point::new(Some(px), Some(py));
+    println!(
+        "Signature valid: {}",
+        point.verify(
+            BigUint::parse_bytes(z, 16).unwrap(),
+            Signature::from_bytes(r, s)
+        )
+    );
+
+    // SEC format
+    println!("SEC compressed: {}", hex::encode(&point.sec(true)));
+    println!("SEC uncompressed: {}", hex::encode(&point.sec(false)));
 
     // Private key
     let private_key =
@@ -0,0 +1,383 @@
+use crate::address::{Address, Payload};
+use crate::base64;
+use crate::error::Error;
+use crate::hash::{hash256, tagged_hash, Hash256};
+use crate::network::Network;
+use crate::op::{OP_0, OP_RETURN};
+use crate::private_key::PrivateKey;
+use crate::recoverable::{RecoverableSignature, RecoveryError};
+use crate::s256point::S256Point;
+use crate::script::{Cmd, Script};
+use crate::tx::{Tx, TxIn, TxOut};
+use crate::varint::{encode_varint, read_varint};
+
+/// The prefix Bitcoin Core hashes before every signed message
+const MESSAGE_MAGIC: &str = "Bitcoin Signed Message:\n";
+
+/// The tag of the BIP340 tagged hash of a BIP322 message
+const BIP322_TAG: &str = "BIP0322-signed-message";
+
+/// The type of address a BIP137 signature header claims to sign for
+enum HeaderType {
+    /// P2PKH with the compressed or uncompressed key, headers 27 to 34
+    P2pkh,
+    /// P2SH-P2WPKH, headers 35 to 38
+    P2shP2wpkh,
+    /// Native P2WPKH, headers 39 to 42
+    P2wpkh,
+}
+
+/// Returns the hash a Bitcoin Signed Message signs: the hash256 of the varint
+/// prefixed magic string followed by the varint prefixed message
+/// # Arguments
+/// * `message` - The message
+/// # Returns
+/// * `Hash256` - The hash to sign
+pub fn signed_message_hash(message: &str) -> Hash256 {
+    let mut data = encode_varint(MESSAGE_MAGIC.len() as u64);
+    data.extend_from_slice(MESSAGE_MAGIC.as_bytes());
+    data.extend(encode_varint(message.len() as u64));
+    data.extend_from_slice(message.as_bytes());
+    hash256(&data)
+}
+
+/// Signs a message the way `bitcoin-cli signmessage` does, proving ownership of the
+/// P2PKH address of the key
+/// # Arguments
+/// * `private_key` - The key of the address
+/// * `message` - The message to sign
+/// * `compressed` - Whether the address uses the compressed public key
+/// # Returns
+/// * `String` - The Base64 encoded 65 byte compact signature
+pub fn sign_message(private_key: &PrivateKey, message: &str, compressed: bool) -> String {
+    let z = signed_message_hash(message).to_biguint();
+    let signature = private_key.sign_recoverable_biguint(z, None);
+    base64::encode(&signature.serialize_compact(compressed))
+}
+
+/// Returns the hash a BIP322 signature commits to: the tagged hash of the message
+/// # Arguments
+/// * `message` - The message
+/// # Returns
+/// * `Hash256` - The hash pushed by the virtual `to_spend` transaction
+pub fn bip322_message_hash(message: &str) -> Hash256 {
+    tagged_hash(BIP322_TAG, message.as_bytes())
+}
+
+/// Signs a message in the BIP322 "simple" format for the P2WPKH address of a key
+/// The signature is the witness of the virtual `to_sign` transaction, which spends the
+/// output of the address in a virtual `to_spend` transaction committing to the message
+/// # Arguments
+/// * `private_key` - The key of the address
+/// * `message` - The message to sign
+/// # Returns
+/// * `String` - The Base64 encoded witness
+pub fn sign_message_bip322(private_key: &PrivateKey, message: &str) -> String {
+    // The script_pubkey, and so the signature, is the same on every network
+    let address = Address::p2wpkh(private_key.get_public_key(), Network::Mainnet);
+    let mut to_sign = to_sign(&address, message);
+    to_sign
+        .sign_segwit_input(0, private_key)
+        .expect("The to_sign input spends the P2WPKH output of the key");
+    base64::encode(&encode_witness(to_sign.get_tx_ins()[0].get_witness()))
+}
+
+/// Verifies a signed message against an address, like `bitcoin-cli verifymessage`
+/// A 65 byte signature is a compact signature: the public key is recovered from it,
+/// and the header byte tells which address type of that key it signs for, following
+/// BIP137: P2PKH with an uncompressed (27 to 30) or compressed (31 to 34) key,
+/// P2SH-P2WPKH (35 to 38) or P2WPKH (39 to 42)
+/// For P2WPKH and P2WSH addresses, other signatures are checked as a BIP322 "simple"
+/// signature, the witness spending the address in the virtual `to_sign` transaction
+/// # Arguments
+/// * `address` - The address the message is claimed to be signed for
+/// * `signature` - The Base64 encoded 65 byte compact signature or BIP322 witness
+/// * `message` - The signed message
+/// # Returns
+/// * `Result<bool, Error>` - Whether the signature is valid for the address, or
+///   `Error::InvalidBase64`, `Error::Recovery` or `Error::Io` if the signature is
+///   malformed, or `Error::UnsupportedScript` for a BIP322 signature of an address
+///   that is not P2WPKH or P2WSH
+pub fn verify_message(address: &Address, signature: &str, message: &str) -> Result<bool, Error> {
+    let mut bytes = base64::decode(signature)?;
+    if bytes.len() != 65 {
+        if let Payload::WitnessProgram(_) = address.get_payload() {
+            return verify_bip322(address, &bytes, message);
+        }
+    }
+    let header_type = match bytes.first() {
+        Some(35..=38) => HeaderType::P2shP2wpkh,
+        Some(39..=42) => HeaderType::P2wpkh,
+        // Other headers outside 27 to 34 are left for parse_compact to reject
+        _ => HeaderType::P2pkh,
+    };
+    // Segwit headers use compressed keys, so map them onto the compressed P2PKH range
+    if let HeaderType::P2shP2wpkh | HeaderType::P2wpkh = header_type {
+        bytes[0] = 31 + (bytes[0] - 35) % 4;
+    }
+    let (recoverable, compressed) = RecoverableSignature::parse_compact(&bytes)?;
+
+    let z = signed_message_hash(message).to_biguint();
+    let public_key = match S256Point::recover(z, &recoverable) {
+        Ok(public_key) => public_key,
+        Err(RecoveryError::NoPublicKey) => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+
+    let network = address.get_network();
+    let expected = match header_type {
+        HeaderType::P2pkh => Address::p2pkh(&public_key, compressed, network),
+        HeaderType::P2shP2wpkh => Address::p2sh_p2wpkh(&public_key, network),
+        HeaderType::P2wpkh => Address::p2wpkh(&public_key, network),
+    };
+    Ok(expected == *address)
+}
+
+/// Verifies a BIP322 "simple" signature, a serialized witness
+/// Only version 0 witness programs can be checked, as taproot spends are not supported
+fn verify_bip322(address: &Address, witness: &[u8], message: &str) -> Result<bool, Error> {
+    let script_pubkey = address.script_pubkey();
+    if !script_pubkey.is_p2wpkh() && !script_pubkey.is_p2wsh() {
+        return Err(Error::UnsupportedScript);
+    }
+    let mut reader = witness;
+    let num_items = read_varint(&mut reader)?;
+    let mut items = Vec::new();
+    for _ in 0..num_items {
+        let len = read_varint(&mut reader)? as usize;
+        if reader.len() < len {
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof));
+        }
+        let (item, rest) = reader.split_at(len);
+        items.push(item.to_vec());
+        reader = rest;
+    }
+    if !reader.is_empty() {
+        return Ok(false);
+    }
+
+    let mut to_sign = to_sign(address, message);
+    to_sign.get_tx_ins_mut()[0].set_witness(items);
+    to_sign.verify_input(0)
+}
+
+/// Creates the unsigned BIP322 `to_sign` transaction of a message, whose only input
+/// spends the output of the address in the `to_spend` transaction
+fn to_sign(address: &Address, message: &str) -> Tx {
+    let script_sig = Script::new(vec![
+        Cmd::Op(OP_0),
+        Cmd::Data(bip322_message_hash(message).as_bytes().to_vec()),
+    ]);
+    let to_spend = Tx::new(
+        0,
+        vec![TxIn::new(
+            Hash256::from_bytes([0; 32]),
+            0xffffffff,
+            script_sig,
+            0,
+        )],
+        vec![TxOut::new(0, address.script_pubkey())],
+        0,
+    );
+
+    let mut tx_in = TxIn::new(to_spend.hash(), 0, Script::default(), 0);
+    tx_in.set_prev_output(to_spend.get_tx_outs()[0].clone());
+    let tx_out = TxOut::new(0, Script::new(vec![Cmd::Op(OP_RETURN)]));
+    Tx::new(0, vec![tx_in], vec![tx_out], 0)
+}
+
+/// Serializes witness items, each prefixed with its length, after the number of items
+fn encode_witness(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut result = encode_varint(items.len() as u64);
+    for item in items {
+        result.extend(encode_varint(item.len() as u64));
+        result.extend_from_slice(item);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+    use crate::schnorr::XOnlyPublicKey;
+
+    fn private_key() -> PrivateKey {
+        PrivateKey::new(b"8b387de39861728c92ec9f589c303b1038ff60eb3963b12cd212263a1d1e0f00")
+    }
+
+    #[test]
+    fn test_signed_message_hash() {
+        assert_eq!(
+            hex::encode(signed_message_hash("Hello World").as_bytes()),
+            "a7af0baad5ae99b97fc69b3a0d1abcf3ef17f131cc4776e1bc11933ec8550f49"
+        );
+    }
+
+    #[test]
+    fn test_sign_message() {
+        // Signatures made by Bitcoin Core's message signing, through rust-bitcoin
+        let private_key = private_key();
+        let signature = sign_message(&private_key, "Hello World", true);
+        assert_eq!(
+            signature,
+            "H41Npq2y5Beo+D5oMi4rcvPxl4yDL0DIwyUHjwvM5v2HYKwuMHl7QwTitmRhB/LUY3mZR+bvHXJelG9FenPp6uw="
+        );
+        let address: Address = "1K4JrXYa2ss5saiEfyjdgDosjCExY8tzbb".parse().unwrap();
+        assert_eq!(
+            verify_message(&address, &signature, "Hello World"),
+            Ok(true)
+        );
+        assert_eq!(
+            verify_message(&address, &signature, "Hello World!"),
+            Ok(false)
+        );
+
+        // A message longer than 252 bytes has a three byte length prefix
+        let message = "a".repeat(300);
+        let signature = sign_message(&private_key, &message, false);
+        assert_eq!(
+            signature,
+            "GxFo/4WsbW21WCJFXXet+F1TlnwYhnZynac08/cH99/qIAauxDev0VmXeMUEu5bsPg5sM7CUDdXbmIGGFMpgshQ="
+        );
+        let uncompressed: Address = "1JuPxtpSQ4J5uR8jkorhkXMfp6HKG3RoJ".parse().unwrap();
+        assert_eq!(
+            verify_message(&uncompressed, &signature, &message),
+            Ok(true)
+        );
+        // The header says the key is uncompressed, so it does not match the compressed address
+        assert_eq!(verify_message(&address, &signature, &message), Ok(false));
+    }
+
+    #[test]
+    fn test_verify_p2sh_p2wpkh() {
+        let private_key = private_key();
+        let address = Address::p2sh_p2wpkh(private_key.get_public_key(), Network::Mainnet);
+        assert_eq!(address.to_string(), "3EvqpfSsTHqeR1G3D5heTPrdZWdK31Stwc");
+
+        let mut bytes = base64::decode(&sign_message(&private_key, "Hello World", true)).unwrap();
+        assert!(!verify_message(&address, &base64::encode(&bytes), "Hello World").unwrap());
+        // Move the header from the compressed P2PKH range to the P2SH-P2WPKH range
+        bytes[0] += 4;
+        let signature = base64::encode(&bytes);
+        assert_eq!(
+            verify_message(&address, &signature, "Hello World"),
+            Ok(true)
+        );
+        let p2pkh = private_key.get_public_key().address(true, Network::Mainnet);
+        assert_eq!(verify_message(&p2pkh, &signature, "Hello World"), Ok(false));
+    }
+
+    #[test]
+    fn test_verify_p2wpkh() {
+        let private_key = private_key();
+        let address = Address::p2wpkh(private_key.get_public_key(), Network::Mainnet);
+        let mut bytes = base64::decode(&sign_message(&private_key, "Hello World", true)).unwrap();
+        // Move the header from the compressed P2PKH range to the P2WPKH range
+        bytes[0] += 8;
+        let signature = base64::encode(&bytes);
+        assert_eq!(
+            verify_message(&address, &signature, "Hello World"),
+            Ok(true)
+        );
+        let p2sh_p2wpkh = Address::p2sh_p2wpkh(private_key.get_public_key(), Network::Mainnet);
+        assert_eq!(
+            verify_message(&p2sh_p2wpkh, &signature, "Hello World"),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn test_bip322_message_hash() {
+        // The test vectors from BIP322
+        assert_eq!(
+            hex::encode(bip322_message_hash("").as_bytes()),
+            "c90c269c4f8fcbe6880f72a721ddfbf1914268a794cbb21cfafee13770ae19f1"
+        );
+        assert_eq!(
+            hex::encode(bip322_message_hash("Hello World").as_bytes()),
+            "f0eb03b1a75ac6d9847f55c624a99169b5dccba2a31f5b23bea77ba270de0a7a"
+        );
+    }
+
+    #[test]
+    fn test_bip322() {
+        // The test vectors from BIP322
+        let key =
+            PrivateKey::from_wif("L3VFeEujGtevx9w18HD1fhRbCH67Az2dpCymeRE1SoPK6XQtaN2k").unwrap();
+        let address: Address = "bc1q9vza2e8x573nczrlzms0wvx3gsqjx7vavgkx0l"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Address::p2wpkh(key.get_public_key(), Network::Mainnet),
+            address
+        );
+        for (message, signature) in [
+            (
+                "",
+                "AkcwRAIgM2gBAQqvZX15ZiysmKmQpDrG83avLIT492QBzLnQIxYCIBaTpOaD20qRlEylyxFSeEA2ba9YOixpX8z46TSDtS40ASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=",
+            ),
+            (
+                "Hello World",
+                "AkcwRAIgZRfIY3p7/DoVTty6YZbWS71bc5Vct9p9Fia83eRmw2QCICK/ENGfwLtptFluMGs2KsqoNSk89pO7F29zJLUx9a/sASECx/EgAxlkQpQ9hYjgGu6EBCPMVPwVIVJqO4XCsMvViHI=",
+            ),
+        ] {
+            assert_eq!(verify_message(&address, signature, message), Ok(true));
+            assert_eq!(
+                verify_message(&address, signature, "Hello World!"),
+                Ok(false)
+            );
+        }
+
+        let signature = sign_message_bip322(&key, "Hello World");
+        assert_eq!(
+            verify_message(&address, &signature, "Hello World"),
+            Ok(true)
+        );
+        let other = Address::p2wpkh(private_key().get_public_key(), Network::Mainnet);
+        assert_eq!(verify_message(&other, &signature, "Hello World"), Ok(false));
+    }
+
+    #[test]
+    fn test_bip322_errors() {
+        let signature = sign_message_bip322(&private_key(), "Hello World");
+        let (output_key, _) = XOnlyPublicKey::from_point(private_key().get_public_key());
+        let p2tr = Address::p2tr(&output_key, Network::Mainnet);
+        assert_eq!(
+            verify_message(&p2tr, &signature, "Hello World"),
+            Err(Error::UnsupportedScript)
+        );
+
+        let address = Address::p2wpkh(private_key().get_public_key(), Network::Mainnet);
+        let mut bytes = base64::decode(&signature).unwrap();
+        bytes.push(0);
+        assert_eq!(
+            verify_message(&address, &base64::encode(&bytes), "Hello World"),
+            Ok(false)
+        );
+        bytes.truncate(bytes.len() - 2);
+        assert_eq!(
+            verify_message(&address, &base64::encode(&bytes), "Hello World"),
+            Err(Error::Io(std::io::ErrorKind::UnexpectedEof))
+        );
+    }
+
+    #[test]
+    fn test_verify_errors() {
+        let address: Address = "1K4JrXYa2ss5saiEfyjdgDosjCExY8tzbb".parse().unwrap();
+        assert_eq!(
+            verify_message(&address, "not base64", "Hello World"),
+            Err(Error::InvalidBase64(String::from("not base64")))
+        );
+        assert_eq!(
+            verify_message(&address, &base64::encode(&[31; 64]), "Hello World"),
+            Err(Error::Recovery(RecoveryError::InvalidLength(64)))
+        );
+        let mut bytes = [1u8; 65];
+        bytes[0] = 43;
+        assert_eq!(
+            verify_message(&address, &base64::encode(&bytes), "Hello World"),
+            Err(Error::Recovery(RecoveryError::InvalidHeader(43)))
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt::{Display, Formatter, Result};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::bip32::{Bip32Error, ExtendedPrivKey};
+use crate::hash::{pbkdf2_hmac_sha512, sha256};
+use crate::network::Network;
+
+/// The BIP39 English wordlist, one word per line in alphabetical order
+const ENGLISH: &str = include_str!("english.txt");
+
+/// The number of PBKDF2 iterations used to stretch a mnemonic into a seed
+const SEED_ITERATIONS: u32 = 2048;
+
+/// Errors that can occur when creating or parsing a mnemonic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MnemonicError {
+    /// The entropy is not 16, 20, 24, 28 or 32 bytes long
+    InvalidEntropyLength(usize),
+    /// The phrase does not have 12, 15, 18, 21 or 24 words
+    InvalidWordCount(usize),
+    /// A word is not in the wordlist
+    UnknownWord(String),
+    /// The checksum bits at the end of the phrase do not match the entropy
+    InvalidChecksum,
+}
+
+// Formats the MnemonicError
+impl Display for MnemonicError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            MnemonicError::InvalidEntropyLength(len) => {
+                write!(f, "Invalid mnemonic entropy length {}", len)
+            }
+            MnemonicError::InvalidWordCount(count) => {
+                write!(f, "Invalid mnemonic word count {}", count)
+            }
+            MnemonicError::UnknownWord(word) => write!(f, "Unknown mnemonic word {:?}", word),
+            MnemonicError::InvalidChecksum => write!(f, "Invalid mnemonic checksum"),
+        }
+    }
+}
+
+impl std::error::Error for MnemonicError {}
+
+/// Mnemonic is a BIP39 backup phrase, which encodes 128 to 256 bits of entropy
+/// followed by a checksum of entropy bits / 32 bits, 11 bits per word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    /// Creates the mnemonic of some entropy
+    /// # Arguments
+    /// * `entropy` - The entropy, 16, 20, 24, 28 or 32 bytes from a secure random source
+    /// # Returns
+    /// * `Result<Mnemonic, MnemonicError>` - The mnemonic, or
+    ///   `MnemonicError::InvalidEntropyLength`
+    pub fn from_entropy(entropy: &[u8]) -> std::result::Result<Mnemonic, MnemonicError> {
+        if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+            return Err(MnemonicError::InvalidEntropyLength(entropy.len()));
+        }
+        Ok(Mnemonic {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    pub fn get_entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Returns the words of the phrase
+    pub fn words(&self) -> Vec<&'static str> {
+        let checksum = sha256(&self.entropy);
+        let checksum_bits = self.entropy.len() / 4;
+        let bit = |i: usize| {
+            let byte = if i < self.entropy.len() * 8 {
+                self.entropy[i / 8]
+            } else {
+                checksum.as_bytes()[(i - self.entropy.len() * 8) / 8]
+            };
+            (byte >> (7 - i % 8)) & 1
+        };
+
+        let words = (self.entropy.len() * 8 + checksum_bits) / 11;
+        (0..words)
+            .map(|w| {
+                let index = (0..11).fold(0, |index, i| index << 1 | bit(w * 11 + i) as usize);
+                wordlist()[index]
+            })
+            .collect()
+    }
+
+    /// Derives the 64 byte seed of the mnemonic, which feeds `ExtendedPrivKey::new_master`
+    /// The seed is PBKDF2-HMAC-SHA512 of the phrase, salted with "mnemonic" followed by
+    /// the passphrase, over 2048 iterations
+    /// The passphrase is used as given, so it must already be in the Unicode NFKD form
+    /// BIP39 asks for
+    /// # Arguments
+    /// * `passphrase` - The optional passphrase, empty if there is none
+    /// # Returns
+    /// * `[u8; 64]` - The seed
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        let salt = format!("mnemonic{}", passphrase);
+        pbkdf2_hmac_sha512(
+            self.to_string().as_bytes(),
+            salt.as_bytes(),
+            SEED_ITERATIONS,
+        )
+    }
+
+    /// Creates the BIP32 master key of the seed of the mnemonic
+    /// # Arguments
+    /// * `passphrase` - The optional passphrase, empty if there is none
+    /// * `network` - The network the keys are for
+    /// # Returns
+    /// * `Result<ExtendedPrivKey, Bip32Error>` - The master key, or
+    ///   `Bip32Error::InvalidSeed` in the very unlikely case the seed gives no valid key
+    pub fn to_master_key(
+        &self,
+        passphrase: &str,
+        network: Network,
+    ) -> std::result::Result<ExtendedPrivKey, Bip32Error> {
+        ExtendedPrivKey::new_master(&self.to_seed(passphrase), network)
+    }
+}
+
+// Formats the Mnemonic as its words separated by single spaces
+impl Display for Mnemonic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.words().join(" "))
+    }
+}
+
+// Parses a Mnemonic from its phrase, checking the words and the checksum
+impl FromStr for Mnemonic {
+    type Err = MnemonicError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        if !(12..=24).contains(&words.len()) || !words.len().is_multiple_of(3) {
+            return Err(MnemonicError::InvalidWordCount(words.len()));
+        }
+
+        let mut bits = Vec::with_capacity(words.len() * 11);
+        for word in &words {
+            let index = wordlist()
+                .binary_search(word)
+                .map_err(|_| MnemonicError::UnknownWord(word.to_string()))?;
+            bits.extend((0..11).rev().map(|i| (index >> i) & 1 == 1));
+        }
+
+        let entropy_bits = bits.len() * 32 / 33;
+        let entropy: Vec<u8> = bits[..entropy_bits]
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+            .collect();
+        let mnemonic = Mnemonic { entropy };
+        if mnemonic.words() != words {
+            return Err(MnemonicError::InvalidChecksum);
+        }
+        Ok(mnemonic)
+    }
+}
+
+/// Returns the English wordlist, split on first use
+fn wordlist() -> &'static [&'static str] {
+    static WORDLIST: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDLIST.get_or_init(|| ENGLISH.lines().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    /// The test vectors from python-mnemonic, vectors.json, all with the passphrase "TREZOR"
+    const TEST_VECTORS: &str = include_str!("test-vectors.csv");
+
+    #[test]
+    fn test_vectors() {
+        for line in TEST_VECTORS.lines().skip(1) {
+            let fields: Vec<&str> = line.split(',').collect();
+            let (entropy, phrase, seed, xprv) = (fields[0], fields[1], fields[2], fields[3]);
+
+            let mnemonic = Mnemonic::from_entropy(&hex::decode(entropy).unwrap()).unwrap();
+            assert_eq!(mnemonic.to_string(), phrase);
+            assert_eq!(phrase.parse::<Mnemonic>(), Ok(mnemonic.clone()));
+            let derived = mnemonic.to_seed("TREZOR");
+            assert_eq!(hex::encode(&derived), seed, "{}", phrase);
+            assert_eq!(
+                ExtendedPrivKey::new_master(&derived, Network::Mainnet)
+                    .unwrap()
+                    .to_string(),
+                xprv
+            );
+        }
+    }
+
+    #[test]
+    fn test_wordlist() {
+        let words = wordlist();
+        assert_eq!(words.len(), 2048);
+        assert_eq!((words[0], words[2047]), ("abandon", "zoo"));
+        assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Mnemonic::from_entropy(&[0; 15]),
+            Err(MnemonicError::InvalidEntropyLength(15))
+        );
+        assert_eq!(
+            Mnemonic::from_entropy(&[0; 36]),
+            Err(MnemonicError::InvalidEntropyLength(36))
+        );
+        assert_eq!(
+            "abandon abandon abandon".parse::<Mnemonic>(),
+            Err(MnemonicError::InvalidWordCount(3))
+        );
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon bitcoin"
+                .parse::<Mnemonic>(),
+            Err(MnemonicError::UnknownWord(String::from("bitcoin")))
+        );
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+             abandon abandon"
+                .parse::<Mnemonic>(),
+            Err(MnemonicError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn test_to_master_key() {
+        // Extra whitespace between words is accepted
+        let mnemonic: Mnemonic = "  abandon abandon abandon abandon abandon abandon abandon \
+                                  abandon abandon abandon\tabandon about\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            mnemonic
+                .to_master_key("TREZOR", Network::Mainnet)
+                .unwrap()
+                .to_string(),
+            "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF"
+        );
+    }
+}
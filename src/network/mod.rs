@@ -0,0 +1,68 @@
+use std::fmt::{Display, Formatter, Result};
+
+/// Network selects the version bytes used to encode addresses and keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// Returns the version byte of P2PKH addresses
+    pub fn p2pkh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            Network::Testnet => 0x6f,
+        }
+    }
+
+    /// Returns the version byte of P2SH addresses
+    pub fn p2sh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            Network::Testnet => 0xc4,
+        }
+    }
+
+    /// Returns the human readable part of segwit addresses
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Testnet => "tb",
+        }
+    }
+
+    /// Returns the version byte of WIF private keys
+    pub fn wif_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x80,
+            Network::Testnet => 0xef,
+        }
+    }
+
+    /// Returns the version bytes of BIP32 extended private keys (xprv or tprv)
+    pub fn xprv_version(&self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0x04, 0x88, 0xad, 0xe4],
+            Network::Testnet => [0x04, 0x35, 0x83, 0x94],
+        }
+    }
+
+    /// Returns the version bytes of BIP32 extended public keys (xpub or tpub)
+    pub fn xpub_version(&self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0x04, 0x88, 0xb2, 0x1e],
+            Network::Testnet => [0x04, 0x35, 0x87, 0xcf],
+        }
+    }
+}
+
+// Formats the Network
+impl Display for Network {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Network::Mainnet => write!(f, "mainnet"),
+            Network::Testnet => write!(f, "testnet"),
+        }
+    }
+}
@@ -0,0 +1,873 @@
+use num_bigint::BigUint;
+use std::collections::VecDeque;
+
+use crate::hash::{hash160, hash256, ripemd160, sha1, sha256};
+use crate::s256point::S256Point;
+use crate::script::Cmd;
+use crate::signature::Signature;
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1NEGATE: u8 = 0x4f;
+pub const OP_1: u8 = 0x51;
+pub const OP_2: u8 = 0x52;
+pub const OP_3: u8 = 0x53;
+pub const OP_4: u8 = 0x54;
+pub const OP_5: u8 = 0x55;
+pub const OP_6: u8 = 0x56;
+pub const OP_7: u8 = 0x57;
+pub const OP_8: u8 = 0x58;
+pub const OP_9: u8 = 0x59;
+pub const OP_10: u8 = 0x5a;
+pub const OP_11: u8 = 0x5b;
+pub const OP_12: u8 = 0x5c;
+pub const OP_13: u8 = 0x5d;
+pub const OP_14: u8 = 0x5e;
+pub const OP_15: u8 = 0x5f;
+pub const OP_16: u8 = 0x60;
+pub const OP_NOP: u8 = 0x61;
+pub const OP_IF: u8 = 0x63;
+pub const OP_NOTIF: u8 = 0x64;
+pub const OP_ELSE: u8 = 0x67;
+pub const OP_ENDIF: u8 = 0x68;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_TOALTSTACK: u8 = 0x6b;
+pub const OP_FROMALTSTACK: u8 = 0x6c;
+pub const OP_2DROP: u8 = 0x6d;
+pub const OP_2DUP: u8 = 0x6e;
+pub const OP_3DUP: u8 = 0x6f;
+pub const OP_2OVER: u8 = 0x70;
+pub const OP_2ROT: u8 = 0x71;
+pub const OP_2SWAP: u8 = 0x72;
+pub const OP_IFDUP: u8 = 0x73;
+pub const OP_DEPTH: u8 = 0x74;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_NIP: u8 = 0x77;
+pub const OP_OVER: u8 = 0x78;
+pub const OP_PICK: u8 = 0x79;
+pub const OP_ROLL: u8 = 0x7a;
+pub const OP_ROT: u8 = 0x7b;
+pub const OP_SWAP: u8 = 0x7c;
+pub const OP_TUCK: u8 = 0x7d;
+pub const OP_SIZE: u8 = 0x82;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_1ADD: u8 = 0x8b;
+pub const OP_1SUB: u8 = 0x8c;
+pub const OP_NEGATE: u8 = 0x8f;
+pub const OP_ABS: u8 = 0x90;
+pub const OP_NOT: u8 = 0x91;
+pub const OP_0NOTEQUAL: u8 = 0x92;
+pub const OP_ADD: u8 = 0x93;
+pub const OP_SUB: u8 = 0x94;
+pub const OP_BOOLAND: u8 = 0x9a;
+pub const OP_BOOLOR: u8 = 0x9b;
+pub const OP_NUMEQUAL: u8 = 0x9c;
+pub const OP_NUMEQUALVERIFY: u8 = 0x9d;
+pub const OP_NUMNOTEQUAL: u8 = 0x9e;
+pub const OP_LESSTHAN: u8 = 0x9f;
+pub const OP_GREATERTHAN: u8 = 0xa0;
+pub const OP_LESSTHANOREQUAL: u8 = 0xa1;
+pub const OP_GREATERTHANOREQUAL: u8 = 0xa2;
+pub const OP_MIN: u8 = 0xa3;
+pub const OP_MAX: u8 = 0xa4;
+pub const OP_WITHIN: u8 = 0xa5;
+pub const OP_RIPEMD160: u8 = 0xa6;
+pub const OP_SHA1: u8 = 0xa7;
+pub const OP_SHA256: u8 = 0xa8;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_HASH256: u8 = 0xaa;
+pub const OP_CODESEPARATOR: u8 = 0xab;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+pub const OP_CHECKMULTISIGVERIFY: u8 = 0xaf;
+pub const OP_NOP1: u8 = 0xb0;
+pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+pub const OP_NOP10: u8 = 0xb9;
+
+/// The stack of the script interpreter, with the top at the end
+pub type Stack = Vec<Vec<u8>>;
+
+/// Returns the name of an opcode, as used when displaying scripts
+/// # Arguments
+/// * `op` - The opcode
+/// # Returns
+/// * `Option<&'static str>` - The name, or None for opcodes without one
+pub fn op_name(op: u8) -> Option<&'static str> {
+    let name = match op {
+        OP_0 => "OP_0",
+        OP_PUSHDATA1 => "OP_PUSHDATA1",
+        OP_PUSHDATA2 => "OP_PUSHDATA2",
+        OP_PUSHDATA4 => "OP_PUSHDATA4",
+        OP_1NEGATE => "OP_1NEGATE",
+        OP_1 => "OP_1",
+        OP_2 => "OP_2",
+        OP_3 => "OP_3",
+        OP_4 => "OP_4",
+        OP_5 => "OP_5",
+        OP_6 => "OP_6",
+        OP_7 => "OP_7",
+        OP_8 => "OP_8",
+        OP_9 => "OP_9",
+        OP_10 => "OP_10",
+        OP_11 => "OP_11",
+        OP_12 => "OP_12",
+        OP_13 => "OP_13",
+        OP_14 => "OP_14",
+        OP_15 => "OP_15",
+        OP_16 => "OP_16",
+        OP_NOP => "OP_NOP",
+        OP_IF => "OP_IF",
+        OP_NOTIF => "OP_NOTIF",
+        OP_ELSE => "OP_ELSE",
+        OP_ENDIF => "OP_ENDIF",
+        OP_VERIFY => "OP_VERIFY",
+        OP_RETURN => "OP_RETURN",
+        OP_TOALTSTACK => "OP_TOALTSTACK",
+        OP_FROMALTSTACK => "OP_FROMALTSTACK",
+        OP_2DROP => "OP_2DROP",
+        OP_2DUP => "OP_2DUP",
+        OP_3DUP => "OP_3DUP",
+        OP_2OVER => "OP_2OVER",
+        OP_2ROT => "OP_2ROT",
+        OP_2SWAP => "OP_2SWAP",
+        OP_IFDUP => "OP_IFDUP",
+        OP_DEPTH => "OP_DEPTH",
+        OP_DROP => "OP_DROP",
+        OP_DUP => "OP_DUP",
+        OP_NIP => "OP_NIP",
+        OP_OVER => "OP_OVER",
+        OP_PICK => "OP_PICK",
+        OP_ROLL => "OP_ROLL",
+        OP_ROT => "OP_ROT",
+        OP_SWAP => "OP_SWAP",
+        OP_TUCK => "OP_TUCK",
+        OP_SIZE => "OP_SIZE",
+        OP_EQUAL => "OP_EQUAL",
+        OP_EQUALVERIFY => "OP_EQUALVERIFY",
+        OP_1ADD => "OP_1ADD",
+        OP_1SUB => "OP_1SUB",
+        OP_NEGATE => "OP_NEGATE",
+        OP_ABS => "OP_ABS",
+        OP_NOT => "OP_NOT",
+        OP_0NOTEQUAL => "OP_0NOTEQUAL",
+        OP_ADD => "OP_ADD",
+        OP_SUB => "OP_SUB",
+        OP_BOOLAND => "OP_BOOLAND",
+        OP_BOOLOR => "OP_BOOLOR",
+        OP_NUMEQUAL => "OP_NUMEQUAL",
+        OP_NUMEQUALVERIFY => "OP_NUMEQUALVERIFY",
+        OP_NUMNOTEQUAL => "OP_NUMNOTEQUAL",
+        OP_LESSTHAN => "OP_LESSTHAN",
+        OP_GREATERTHAN => "OP_GREATERTHAN",
+        OP_LESSTHANOREQUAL => "OP_LESSTHANOREQUAL",
+        OP_GREATERTHANOREQUAL => "OP_GREATERTHANOREQUAL",
+        OP_MIN => "OP_MIN",
+        OP_MAX => "OP_MAX",
+        OP_WITHIN => "OP_WITHIN",
+        OP_RIPEMD160 => "OP_RIPEMD160",
+        OP_SHA1 => "OP_SHA1",
+        OP_SHA256 => "OP_SHA256",
+        OP_HASH160 => "OP_HASH160",
+        OP_HASH256 => "OP_HASH256",
+        OP_CODESEPARATOR => "OP_CODESEPARATOR",
+        OP_CHECKSIG => "OP_CHECKSIG",
+        OP_CHECKSIGVERIFY => "OP_CHECKSIGVERIFY",
+        OP_CHECKMULTISIG => "OP_CHECKMULTISIG",
+        OP_CHECKMULTISIGVERIFY => "OP_CHECKMULTISIGVERIFY",
+        OP_NOP1 => "OP_NOP1",
+        OP_CHECKLOCKTIMEVERIFY => "OP_CHECKLOCKTIMEVERIFY",
+        OP_CHECKSEQUENCEVERIFY => "OP_CHECKSEQUENCEVERIFY",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Encodes a number in the minimal little endian sign-magnitude format of the stack
+/// # Arguments
+/// * `num` - The number to encode
+/// # Returns
+/// * `Vec<u8>` - The encoded number, empty for zero
+pub fn encode_num(num: i64) -> Vec<u8> {
+    if num == 0 {
+        return Vec::new();
+    }
+    let negative = num < 0;
+    let mut abs = num.unsigned_abs();
+    let mut result = Vec::new();
+    while abs > 0 {
+        result.push((abs & 0xff) as u8);
+        abs >>= 8;
+    }
+    // The top bit of the last byte holds the sign, add a byte if it is taken
+    if result[result.len() - 1] & 0x80 != 0 {
+        result.push(if negative { 0x80 } else { 0x00 });
+    } else if negative {
+        let last = result.len() - 1;
+        result[last] |= 0x80;
+    }
+    result
+}
+
+/// The maximum length of a number operand, as enforced by consensus
+const MAX_NUM_LENGTH: usize = 4;
+
+/// Decodes a number from the little endian sign-magnitude format of the stack
+/// Results of arithmetic may be longer, but like in consensus they cannot be used as
+/// operands again
+/// # Arguments
+/// * `element` - The encoded number, at most 4 bytes long
+/// # Returns
+/// * `Option<i64>` - The number, or None if it is too long
+pub fn decode_num(element: &[u8]) -> Option<i64> {
+    if element.len() > MAX_NUM_LENGTH {
+        return None;
+    }
+    let Some((&last, rest)) = element.split_last() else {
+        return Some(0);
+    };
+    let negative = last & 0x80 != 0;
+    let mut result = (last & 0x7f) as i64;
+    for &byte in rest.iter().rev() {
+        result = (result << 8) + byte as i64;
+    }
+    Some(if negative { -result } else { result })
+}
+
+/// Interprets a stack element as a boolean, where any encoding of zero is false
+/// # Arguments
+/// * `element` - The stack element
+/// # Returns
+/// * `bool` - False for empty, all zero or negative zero elements, true otherwise
+pub fn is_true(element: &[u8]) -> bool {
+    match element.split_last() {
+        Some((&last, rest)) => last & 0x7f != 0 || rest.iter().any(|&byte| byte != 0),
+        None => false,
+    }
+}
+
+/// Executes one opcode
+/// OP_CHECKLOCKTIMEVERIFY and OP_CHECKSEQUENCEVERIFY do nothing, like the OP_NOP2 and OP_NOP3
+/// they were before BIP65 and BIP112, since the interpreter cannot see the locktime and
+/// sequences of the transaction. OP_CODESEPARATOR does nothing here either; the caller
+/// signs the commands after it instead of the whole script
+/// OP_ELSE and OP_ENDIF assume that the conditionals of the script are balanced
+/// # Arguments
+/// * `op` - The opcode to execute
+/// * `stack` - The main stack
+/// * `altstack` - The alternate stack
+/// * `cmds` - The commands left to execute, used by the flow control opcodes
+/// * `sighash` - Computes the signature hash checked by the signature opcodes from the
+///   sighash type of each signature
+/// # Returns
+/// * `bool` - False if the opcode failed or is not supported, which fails the script
+pub fn execute(
+    op: u8,
+    stack: &mut Stack,
+    altstack: &mut Stack,
+    cmds: &mut VecDeque<Cmd>,
+    sighash: &dyn Fn(u32) -> BigUint,
+) -> bool {
+    match op {
+        OP_0 => push_num(stack, 0),
+        OP_1NEGATE => push_num(stack, -1),
+        OP_1..=OP_16 => push_num(stack, (op - OP_1 + 1) as i64),
+        OP_NOP | OP_NOP1..=OP_NOP10 => true,
+        OP_IF => op_if(stack, cmds, false),
+        OP_NOTIF => op_if(stack, cmds, true),
+        OP_ELSE => skip_branch(cmds, false),
+        OP_ENDIF => true,
+        OP_VERIFY => op_verify(stack),
+        OP_RETURN => false,
+        OP_TOALTSTACK => move_top(stack, altstack),
+        OP_FROMALTSTACK => move_top(altstack, stack),
+        OP_2DROP => op_2drop(stack),
+        OP_2DUP => op_2dup(stack),
+        OP_3DUP => op_3dup(stack),
+        OP_2OVER => op_2over(stack),
+        OP_2ROT => op_2rot(stack),
+        OP_2SWAP => op_2swap(stack),
+        OP_IFDUP => op_ifdup(stack),
+        OP_DEPTH => push_num(stack, stack.len() as i64),
+        OP_DROP => stack.pop().is_some(),
+        OP_DUP => op_dup(stack),
+        OP_NIP => op_nip(stack),
+        OP_OVER => op_over(stack),
+        OP_PICK => op_pick(stack, false),
+        OP_ROLL => op_pick(stack, true),
+        OP_ROT => op_rot(stack),
+        OP_SWAP => op_swap(stack),
+        OP_TUCK => op_tuck(stack),
+        OP_SIZE => op_size(stack),
+        OP_EQUAL => op_equal(stack),
+        OP_EQUALVERIFY => op_equal(stack) && op_verify(stack),
+        OP_1ADD => unary_num(stack, |a| a.checked_add(1)),
+        OP_1SUB => unary_num(stack, |a| a.checked_sub(1)),
+        OP_NEGATE => unary_num(stack, |a| Some(-a)),
+        OP_ABS => unary_num(stack, |a| Some(a.abs())),
+        OP_NOT => unary_num(stack, |a| Some((a == 0) as i64)),
+        OP_0NOTEQUAL => unary_num(stack, |a| Some((a != 0) as i64)),
+        OP_ADD => binary_num(stack, |a, b| a.checked_add(b)),
+        OP_SUB => binary_num(stack, |a, b| a.checked_sub(b)),
+        OP_BOOLAND => binary_num(stack, |a, b| Some((a != 0 && b != 0) as i64)),
+        OP_BOOLOR => binary_num(stack, |a, b| Some((a != 0 || b != 0) as i64)),
+        OP_NUMEQUAL => binary_num(stack, |a, b| Some((a == b) as i64)),
+        OP_NUMEQUALVERIFY => binary_num(stack, |a, b| Some((a == b) as i64)) && op_verify(stack),
+        OP_NUMNOTEQUAL => binary_num(stack, |a, b| Some((a != b) as i64)),
+        OP_LESSTHAN => binary_num(stack, |a, b| Some((a < b) as i64)),
+        OP_GREATERTHAN => binary_num(stack, |a, b| Some((a > b) as i64)),
+        OP_LESSTHANOREQUAL => binary_num(stack, |a, b| Some((a <= b) as i64)),
+        OP_GREATERTHANOREQUAL => binary_num(stack, |a, b| Some((a >= b) as i64)),
+        OP_MIN => binary_num(stack, |a, b| Some(a.min(b))),
+        OP_MAX => binary_num(stack, |a, b| Some(a.max(b))),
+        OP_WITHIN => op_within(stack),
+        OP_RIPEMD160 => hash_top(stack, |data| ripemd160(data).as_bytes().to_vec()),
+        OP_SHA1 => hash_top(stack, |data| sha1(data).as_bytes().to_vec()),
+        OP_SHA256 => hash_top(stack, |data| sha256(data).as_bytes().to_vec()),
+        OP_HASH160 => hash_top(stack, |data| hash160(data).as_bytes().to_vec()),
+        OP_HASH256 => hash_top(stack, |data| hash256(data).as_bytes().to_vec()),
+        OP_CODESEPARATOR => true,
+        OP_CHECKSIG => op_checksig(stack, sighash),
+        OP_CHECKSIGVERIFY => op_checksig(stack, sighash) && op_verify(stack),
+        OP_CHECKMULTISIG => op_checkmultisig(stack, sighash),
+        OP_CHECKMULTISIGVERIFY => op_checkmultisig(stack, sighash) && op_verify(stack),
+        _ => false,
+    }
+}
+
+/// Pushes an encoded number onto the stack
+fn push_num(stack: &mut Stack, num: i64) -> bool {
+    stack.push(encode_num(num));
+    true
+}
+
+/// Pops an encoded number from the stack
+fn pop_num(stack: &mut Stack) -> Option<i64> {
+    decode_num(&stack.pop()?)
+}
+
+/// Starts the conditional block of an OP_IF or OP_NOTIF on the top of the stack
+/// If the condition holds, the true branch runs next and the OP_ELSE ending it skips the
+/// rest of the block. Otherwise the true branch is skipped. The rest of the block stays
+/// in `cmds`, so the commands after an OP_CODESEPARATOR are still those of the script
+fn op_if(stack: &mut Stack, cmds: &mut VecDeque<Cmd>, negate: bool) -> bool {
+    if branch_end(cmds, false).is_none() {
+        return false;
+    }
+    let Some(element) = stack.pop() else {
+        return false;
+    };
+    is_true(&element) != negate || skip_branch(cmds, true)
+}
+
+/// Removes the commands up to and including the OP_ENDIF of the current block, or its
+/// OP_ELSE if `to_else` is set and it has one
+fn skip_branch(cmds: &mut VecDeque<Cmd>, to_else: bool) -> bool {
+    match branch_end(cmds, to_else) {
+        Some(end) => {
+            cmds.drain(..=end);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns the index of the OP_ENDIF of the current block, or of its OP_ELSE if `to_else`
+/// is set and it has one, skipping nested blocks
+fn branch_end(cmds: &VecDeque<Cmd>, to_else: bool) -> Option<usize> {
+    let mut depth = 0;
+    for (i, cmd) in cmds.iter().enumerate() {
+        match cmd {
+            Cmd::Op(OP_IF) | Cmd::Op(OP_NOTIF) => depth += 1,
+            Cmd::Op(OP_ENDIF) if depth == 0 => return Some(i),
+            Cmd::Op(OP_ENDIF) => depth -= 1,
+            Cmd::Op(OP_ELSE) if depth == 0 && to_else => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Fails unless the top of the stack is true, removing it
+fn op_verify(stack: &mut Stack) -> bool {
+    stack.pop().is_some_and(|top| is_true(&top))
+}
+
+/// Moves the top element of one stack to another
+fn move_top(from: &mut Stack, to: &mut Stack) -> bool {
+    match from.pop() {
+        Some(element) => {
+            to.push(element);
+            true
+        }
+        None => false,
+    }
+}
+
+fn op_2drop(stack: &mut Stack) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    stack.truncate(stack.len() - 2);
+    true
+}
+
+fn op_2dup(stack: &mut Stack) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    stack.extend_from_within(stack.len() - 2..);
+    true
+}
+
+fn op_3dup(stack: &mut Stack) -> bool {
+    if stack.len() < 3 {
+        return false;
+    }
+    stack.extend_from_within(stack.len() - 3..);
+    true
+}
+
+fn op_2over(stack: &mut Stack) -> bool {
+    if stack.len() < 4 {
+        return false;
+    }
+    stack.extend_from_within(stack.len() - 4..stack.len() - 2);
+    true
+}
+
+fn op_2rot(stack: &mut Stack) -> bool {
+    if stack.len() < 6 {
+        return false;
+    }
+    let start = stack.len() - 6;
+    let moved: Vec<_> = stack.drain(start..start + 2).collect();
+    stack.extend(moved);
+    true
+}
+
+fn op_2swap(stack: &mut Stack) -> bool {
+    if stack.len() < 4 {
+        return false;
+    }
+    let start = stack.len() - 4;
+    stack[start..].rotate_left(2);
+    true
+}
+
+fn op_ifdup(stack: &mut Stack) -> bool {
+    let Some(top) = stack.last() else {
+        return false;
+    };
+    if is_true(top) {
+        stack.push(top.clone());
+    }
+    true
+}
+
+fn op_dup(stack: &mut Stack) -> bool {
+    match stack.last() {
+        Some(top) => {
+            stack.push(top.clone());
+            true
+        }
+        None => false,
+    }
+}
+
+fn op_nip(stack: &mut Stack) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    stack.remove(stack.len() - 2);
+    true
+}
+
+fn op_over(stack: &mut Stack) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    stack.push(stack[stack.len() - 2].clone());
+    true
+}
+
+/// Copies (OP_PICK) or moves (OP_ROLL) the element n deep to the top of the stack
+fn op_pick(stack: &mut Stack, remove: bool) -> bool {
+    let Some(n) = pop_num(stack) else {
+        return false;
+    };
+    if n < 0 || n as usize >= stack.len() {
+        return false;
+    }
+    let index = stack.len() - 1 - n as usize;
+    let element = if remove {
+        stack.remove(index)
+    } else {
+        stack[index].clone()
+    };
+    stack.push(element);
+    true
+}
+
+fn op_rot(stack: &mut Stack) -> bool {
+    if stack.len() < 3 {
+        return false;
+    }
+    let element = stack.remove(stack.len() - 3);
+    stack.push(element);
+    true
+}
+
+fn op_swap(stack: &mut Stack) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    let len = stack.len();
+    stack.swap(len - 1, len - 2);
+    true
+}
+
+fn op_tuck(stack: &mut Stack) -> bool {
+    if stack.len() < 2 {
+        return false;
+    }
+    stack.insert(stack.len() - 2, stack[stack.len() - 1].clone());
+    true
+}
+
+fn op_size(stack: &mut Stack) -> bool {
+    match stack.last() {
+        Some(top) => push_num(stack, top.len() as i64),
+        None => false,
+    }
+}
+
+fn op_equal(stack: &mut Stack) -> bool {
+    match (stack.pop(), stack.pop()) {
+        (Some(a), Some(b)) => push_num(stack, (a == b) as i64),
+        _ => false,
+    }
+}
+
+/// Replaces the number on top of the stack with f of it
+fn unary_num(stack: &mut Stack, f: impl Fn(i64) -> Option<i64>) -> bool {
+    match pop_num(stack).and_then(f) {
+        Some(result) => push_num(stack, result),
+        None => false,
+    }
+}
+
+/// Replaces the two numbers on top of the stack with f of them, the deeper one first
+fn binary_num(stack: &mut Stack, f: impl Fn(i64, i64) -> Option<i64>) -> bool {
+    let (Some(b), Some(a)) = (pop_num(stack), pop_num(stack)) else {
+        return false;
+    };
+    match f(a, b) {
+        Some(result) => push_num(stack, result),
+        None => false,
+    }
+}
+
+fn op_within(stack: &mut Stack) -> bool {
+    let (Some(max), Some(min), Some(x)) = (pop_num(stack), pop_num(stack), pop_num(stack)) else {
+        return false;
+    };
+    push_num(stack, (min <= x && x < max) as i64)
+}
+
+/// Replaces the element on top of the stack with its hash
+fn hash_top(stack: &mut Stack, hash: impl Fn(&[u8]) -> Vec<u8>) -> bool {
+    match stack.pop() {
+        Some(element) => {
+            stack.push(hash(&element));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Checks a DER signature followed by a sighash byte against a SEC public key
+fn check_signature(sec: &[u8], sig: &[u8], sighash: &dyn Fn(u32) -> BigUint) -> bool {
+    let Some((&hash_type, der)) = sig.split_last() else {
+        return false;
+    };
+    match (S256Point::parse_sec(sec), Signature::parse_der(der)) {
+        (Ok(point), Ok(signature)) => point.verify(sighash(hash_type as u32), signature),
+        _ => false,
+    }
+}
+
+fn op_checksig(stack: &mut Stack, sighash: &dyn Fn(u32) -> BigUint) -> bool {
+    let (Some(sec), Some(sig)) = (stack.pop(), stack.pop()) else {
+        return false;
+    };
+    push_num(stack, check_signature(&sec, &sig, sighash) as i64)
+}
+
+/// Checks m of n signatures against n public keys, in order
+/// Like Bitcoin Core, it consumes one extra element below the signatures
+fn op_checkmultisig(stack: &mut Stack, sighash: &dyn Fn(u32) -> BigUint) -> bool {
+    let Some(n) = pop_num(stack) else {
+        return false;
+    };
+    if n < 0 || n as usize > stack.len() {
+        return false;
+    }
+    let secs = stack.split_off(stack.len() - n as usize);
+    let Some(m) = pop_num(stack) else {
+        return false;
+    };
+    if m < 0 || m > n || m as usize >= stack.len() {
+        return false;
+    }
+    let sigs = stack.split_off(stack.len() - m as usize);
+    if stack.pop().is_none() {
+        return false;
+    }
+
+    // Each signature must match a public key after the one matching the previous signature
+    let mut secs = secs.iter();
+    let valid = sigs
+        .iter()
+        .all(|sig| secs.any(|sec| check_signature(sec, sig, sighash)));
+    push_num(stack, valid as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(ops: &[u8], stack: &mut Stack) -> bool {
+        let mut altstack = Vec::new();
+        let mut cmds = VecDeque::new();
+        ops.iter()
+            .all(|&op| execute(op, stack, &mut altstack, &mut cmds, &|_| BigUint::ZERO))
+    }
+
+    #[test]
+    fn test_num() {
+        for num in [0, 1, -1, 127, 128, -128, 255, 256, -32768, i32::MAX as i64] {
+            assert_eq!(decode_num(&encode_num(num)), Some(num));
+        }
+        assert_eq!(encode_num(-1), vec![0x81]);
+        assert_eq!(encode_num(128), vec![0x80, 0x00]);
+        assert_eq!(encode_num(-128), vec![0x80, 0x80]);
+        assert_eq!(decode_num(&encode_num(1 << 40)), None);
+        assert_eq!(decode_num(&[0u8; 5]), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut stack = vec![encode_num(2), encode_num(3)];
+        assert!(run(&[OP_ADD, OP_5, OP_NUMEQUAL], &mut stack));
+        assert_eq!(stack, vec![encode_num(1)]);
+
+        let mut stack = vec![encode_num(2), encode_num(3)];
+        assert!(run(&[OP_SUB, OP_NEGATE, OP_1ADD, OP_ABS], &mut stack));
+        assert_eq!(stack, vec![encode_num(2)]);
+
+        let mut stack = vec![encode_num(5), encode_num(2), encode_num(6)];
+        assert!(run(&[OP_WITHIN], &mut stack));
+        assert_eq!(stack, vec![encode_num(1)]);
+
+        let mut stack = vec![encode_num(5)];
+        assert!(!run(&[OP_ADD], &mut stack));
+    }
+
+    #[test]
+    fn test_arithmetic_overflow() {
+        // Operands longer than 4 bytes fail the script instead of overflowing
+        for op in [OP_1ADD, OP_1SUB, OP_NEGATE, OP_ABS] {
+            let mut stack = vec![vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]];
+            assert!(!run(&[op], &mut stack));
+            let mut stack = vec![vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]];
+            assert!(!run(&[op], &mut stack));
+        }
+
+        // The result of arithmetic on 4 byte operands may be longer, but not reused
+        let mut stack = vec![vec![0xff, 0xff, 0xff, 0x7f]];
+        assert!(run(&[OP_1ADD], &mut stack));
+        assert_eq!(stack, vec![encode_num(1 << 31)]);
+        assert!(!run(&[OP_1ADD], &mut stack));
+        let mut stack = vec![vec![0xff, 0xff, 0xff, 0xff], vec![0xff, 0xff, 0xff, 0xff]];
+        assert!(run(&[OP_ADD], &mut stack));
+        assert_eq!(stack, vec![encode_num(-(1 << 32) + 2)]);
+    }
+
+    #[test]
+    fn test_stack_ops() {
+        let mut stack = vec![vec![1], vec![2], vec![3]];
+        assert!(run(&[OP_ROT], &mut stack));
+        assert_eq!(stack, vec![vec![2], vec![3], vec![1]]);
+        assert!(run(&[OP_SWAP, OP_OVER], &mut stack));
+        assert_eq!(stack, vec![vec![2], vec![1], vec![3], vec![1]]);
+        assert!(run(&[OP_2SWAP], &mut stack));
+        assert_eq!(stack, vec![vec![3], vec![1], vec![2], vec![1]]);
+        assert!(run(&[OP_2, OP_PICK], &mut stack));
+        assert_eq!(stack, vec![vec![3], vec![1], vec![2], vec![1], vec![1]]);
+        assert!(run(&[OP_3, OP_ROLL, OP_NIP, OP_DEPTH], &mut stack));
+        assert_eq!(
+            stack,
+            vec![vec![3], vec![2], vec![1], vec![1], encode_num(4)]
+        );
+        assert!(run(&[OP_2DROP, OP_TUCK, OP_SIZE], &mut stack));
+        assert_eq!(
+            stack,
+            vec![vec![3], vec![1], vec![2], vec![1], encode_num(1)]
+        );
+        assert!(!run(&[OP_9, OP_PICK], &mut stack));
+    }
+
+    #[test]
+    fn test_altstack() {
+        let mut stack = vec![vec![1], vec![2]];
+        let mut altstack = Vec::new();
+        let mut cmds = VecDeque::new();
+        let z = |_| BigUint::ZERO;
+        assert!(execute(
+            OP_TOALTSTACK,
+            &mut stack,
+            &mut altstack,
+            &mut cmds,
+            &z
+        ));
+        assert_eq!(altstack, vec![vec![2]]);
+        assert!(execute(
+            OP_FROMALTSTACK,
+            &mut stack,
+            &mut altstack,
+            &mut cmds,
+            &z
+        ));
+        assert_eq!(stack, vec![vec![1], vec![2]]);
+        assert!(!execute(
+            OP_FROMALTSTACK,
+            &mut stack,
+            &mut altstack,
+            &mut cmds,
+            &z
+        ));
+    }
+
+    #[test]
+    fn test_hash_ops() {
+        let mut stack = vec![b"hello".to_vec()];
+        assert!(run(&[OP_HASH160], &mut stack));
+        assert_eq!(stack, vec![hash160(b"hello").as_bytes().to_vec()]);
+
+        let mut stack = vec![b"hello".to_vec()];
+        assert!(run(&[OP_SHA1], &mut stack));
+        assert_eq!(stack, vec![sha1(b"hello").as_bytes().to_vec()]);
+    }
+
+    #[test]
+    fn test_nops() {
+        // The locktime opcodes are the NOPs they replaced, and OP_CODESEPARATOR has no
+        // effect on the stacks
+        let mut stack = vec![encode_num(1)];
+        assert!(run(
+            &[
+                OP_NOP,
+                OP_CHECKLOCKTIMEVERIFY,
+                OP_CHECKSEQUENCEVERIFY,
+                OP_NOP10,
+                OP_CODESEPARATOR
+            ],
+            &mut stack
+        ));
+        assert_eq!(stack, vec![encode_num(1)]);
+    }
+
+    #[test]
+    fn test_if() {
+        let z = |_| BigUint::ZERO;
+        let block = [
+            Cmd::Op(OP_2),
+            Cmd::Op(OP_ELSE),
+            Cmd::Op(OP_3),
+            Cmd::Op(OP_ENDIF),
+        ];
+        // The rest of the block stays, and the OP_ELSE after the true branch skips it
+        for (condition, expected) in [(1, &block[..]), (0, &block[2..])] {
+            let mut stack = vec![encode_num(condition)];
+            let mut altstack = Vec::new();
+            let mut cmds: VecDeque<Cmd> = block.iter().cloned().collect();
+            assert!(execute(OP_IF, &mut stack, &mut altstack, &mut cmds, &z));
+            assert_eq!(cmds, expected);
+            assert!(stack.is_empty());
+        }
+        let mut cmds: VecDeque<Cmd> = block[2..].iter().cloned().collect();
+        cmds.push_back(Cmd::Op(OP_4));
+        assert!(execute(
+            OP_ELSE,
+            &mut Vec::new(),
+            &mut Vec::new(),
+            &mut cmds,
+            &z
+        ));
+        assert_eq!(cmds, [Cmd::Op(OP_4)]);
+
+        let mut cmds: VecDeque<Cmd> = VecDeque::from(vec![Cmd::Op(OP_2)]);
+        assert!(!execute(
+            OP_IF,
+            &mut vec![encode_num(1)],
+            &mut Vec::new(),
+            &mut cmds,
+            &z
+        ));
+    }
+
+    #[test]
+    fn test_checksig() {
+        let z = BigUint::parse_bytes(
+            b"7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+            16,
+        )
+        .unwrap();
+        let sec = crate::hex::decode("04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34").unwrap();
+        let sig = crate::hex::decode("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601").unwrap();
+        let mut stack = vec![sig.clone(), sec.clone()];
+        assert!(op_checksig(&mut stack, &|_| z.clone()));
+        assert_eq!(decode_num(&stack[0]), Some(1));
+
+        let mut stack = vec![sig, sec];
+        assert!(op_checksig(&mut stack, &|_| &z + 1u32));
+        assert_eq!(decode_num(&stack[0]), Some(0));
+    }
+
+    #[test]
+    fn test_checkmultisig() {
+        let z = BigUint::parse_bytes(
+            b"e71bfa115715d6fd33796948126f40a8cdd39f187e4afb03896795189fe1423c",
+            16,
+        )
+        .unwrap();
+        let sig1 = crate::hex::decode("3045022100dc92655fe37036f47756db8102e0d7d5e28b3beb83a8fef4f5dc0559bddfb94e02205a36d4e4e6c7fcd16658c50783e00c341609977aed3ad00937bf4ee942a8993701").unwrap();
+        let sig2 = crate::hex::decode("3045022100da6bee3c93766232079a01639d07fa869598749729ae323eab8eef53577d611b02207bef15429dcadce2121ea07f233115c6f09034c0be68db99980b9a6c5e75402201").unwrap();
+        let sec1 = crate::hex::decode(
+            "022626e955ea6ea6d98850c994f9107b036b1334f18ca8830bfff1295d21cfdb70",
+        )
+        .unwrap();
+        let sec2 = crate::hex::decode(
+            "03b287eaf122eea69030a0e9feed096bed8045c8b98bec453e1ffac7fbdbd4bb71",
+        )
+        .unwrap();
+        let mut stack = vec![
+            Vec::new(),
+            sig1,
+            sig2,
+            encode_num(2),
+            sec1,
+            sec2,
+            encode_num(2),
+        ];
+        assert!(op_checkmultisig(&mut stack, &|_| z.clone()));
+        assert_eq!(stack, vec![encode_num(1)]);
+    }
+}
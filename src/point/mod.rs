@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::field_element::FieldElement;
 use num_bigint::BigUint;
 use std::{
@@ -59,17 +60,169 @@ impl Point {
         a: FieldElement,
         b: FieldElement,
     ) -> Self {
+        Self::try_new(x, y, a, b).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a new Point from x and y coordinates and the curve parameters a and b
+    /// # Arguments
+    /// * `x` - The x coordinate of the point
+    /// * `y` - The y coordinate of the point
+    /// * `a` - The a parameter of the curve
+    /// * `b` - The b parameter of the curve
+    /// # Returns
+    /// * `Result<Point, Error>` - The Point, or why the coordinates do not describe one
+    pub fn try_new(
+        x: Option<FieldElement>,
+        y: Option<FieldElement>,
+        a: FieldElement,
+        b: FieldElement,
+    ) -> std::result::Result<Self, Error> {
         match (&x, &y) {
             (Some(x_val), Some(y_val)) => {
-                if y_val.pow(2u32) != x_val.pow(3u32) + a.clone() * x_val.clone() + b.clone() {
-                    panic!("({}, {}) is not on the curve", x_val, y_val);
+                let rhs = x_val.pow(3u32).try_add(&a.try_mul(x_val)?)?.try_add(&b)?;
+                if y_val.pow(2u32) != rhs {
+                    return Err(Error::NotOnCurve {
+                        x: x_val.clone(),
+                        y: y_val.clone(),
+                    });
+                }
+            }
+            (None, None) => return Ok(Point { x, y, a, b }),
+            _ => return Err(Error::IncompletePoint),
+        }
+        Ok(Point { x, y, a, b })
+    }
+
+    /// Adds two points on the same curve
+    /// # Arguments
+    /// * `other` - The point to add
+    /// # Returns
+    /// * `Result<Point, Error>` - The sum of the points, or `Error::DifferentCurves`
+    pub fn try_add(&self, other: &Self) -> std::result::Result<Self, Error> {
+        if self.a != other.a || self.b != other.b {
+            return Err(Error::DifferentCurves);
+        }
+
+        match (&self.x, &self.y, &other.x, &other.y) {
+            (None, _, _, _) => Ok(other.clone()),
+            (_, _, None, _) => Ok(self.clone()),
+            (Some(x1), Some(y1), Some(x2), Some(y2)) if x1 == x2 => {
+                // Vertical line, either P + (-P) or doubling a point with y = 0
+                if y1 != y2 || y1.get_number() == &BigUint::ZERO {
+                    return Point::try_new(None, None, self.a.clone(), self.b.clone());
+                }
+                // Handling the doubling case
+                let num = (3 * x1.pow(2u32)).try_add(&self.a)?;
+                let denom = 2 * y1.clone();
+                let s = num.try_div(&denom)?;
+                let x3 = s.pow(2u32).try_sub(&(2 * x1.clone()))?;
+                let y3 = s.try_mul(&x1.try_sub(&x3)?)?.try_sub(y1)?;
+                Point::try_new(Some(x3), Some(y3), self.a.clone(), self.b.clone())
+            }
+            (Some(x1), Some(y1), Some(x2), Some(y2)) => {
+                let s = y2.try_sub(y1)?.try_div(&x2.try_sub(x1)?)?;
+                let x3 = s.pow(2u32).try_sub(x1)?.try_sub(x2)?;
+                let y3 = s.try_mul(&x1.try_sub(&x3)?)?.try_sub(y1)?;
+                Point::try_new(Some(x3), Some(y3), self.a.clone(), self.b.clone())
+            }
+            _ => Err(Error::IncompletePoint),
+        }
+    }
+
+    /// Computes the sum of several scalar multiplications with Strauss' method
+    /// All the terms share one chain of doublings, and each coefficient is written in width-4
+    /// non-adjacent form, so a term only adds an odd multiple of its point for about one
+    /// bit in five
+    /// This works on affine points of any curve; secp256k1 signature verification does not
+    /// go through it, but through a Jacobian version of the same loop
+    /// # Arguments
+    /// * `terms` - The pairs of coefficient and point, all on the same curve
+    /// # Panics
+    /// If there are no terms, or the points are on different curves
+    /// # Returns
+    /// * `Point` - The sum of the products
+    pub fn multi_mul(terms: &[(BigUint, Point)]) -> Self {
+        Self::try_multi_mul(terms).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Computes the sum of several scalar multiplications with Strauss' method
+    /// # Arguments
+    /// * `terms` - The pairs of coefficient and point, all on the same curve
+    /// # Returns
+    /// * `Result<Point, Error>` - The sum of the products, `Error::EmptyMultiMul` if there are
+    ///   no terms, since the curve is then unknown, or `Error::DifferentCurves`
+    pub fn try_multi_mul(terms: &[(BigUint, Point)]) -> std::result::Result<Self, Error> {
+        let (_, first) = terms.first().ok_or(Error::EmptyMultiMul)?;
+        let infinity = Point::try_new(None, None, first.a.clone(), first.b.clone())?;
+
+        let mut tables = Vec::with_capacity(terms.len());
+        for (coefficient, point) in terms {
+            // The odd multiples P, 3P, 5P and 7P used by the width-4 digits
+            let double = point.try_add(point)?;
+            let mut multiples = vec![infinity.try_add(point)?];
+            for i in 1..4 {
+                multiples.push(multiples[i - 1].try_add(&double)?);
+            }
+            tables.push((wnaf(coefficient, 4), multiples));
+        }
+
+        let len = tables.iter().map(|(digits, _)| digits.len()).max();
+        let mut result = infinity;
+        for i in (0..len.unwrap_or(0)).rev() {
+            result = result.try_add(&result)?;
+            for (digits, multiples) in &tables {
+                match digits.get(i) {
+                    Some(&digit) if digit > 0 => {
+                        result = result.try_add(&multiples[digit as usize / 2])?;
+                    }
+                    Some(&digit) if digit < 0 => {
+                        result = result.try_add(&multiples[-digit as usize / 2].neg())?;
+                    }
+                    _ => {}
                 }
             }
-            (None, None) => return Point { x, y, a, b },
-            _ => panic!("Incomplete point coordinates"),
         }
-        Point { x, y, a, b }
+        Ok(result)
+    }
+
+    /// Returns the negation of the point, (x, -y)
+    fn neg(&self) -> Self {
+        let y = self.y.as_ref().map(|y| {
+            let prime = y.get_prime();
+            FieldElement::from_biguint((prime - y.get_number()) % prime, prime.clone())
+        });
+        Point {
+            x: self.x.clone(),
+            y,
+            a: self.a.clone(),
+            b: self.b.clone(),
+        }
+    }
+}
+
+/// Returns the width-w non-adjacent form of a number, least significant digit first
+/// Every digit is zero or odd with absolute value below 2^(w-1)
+fn wnaf(number: &BigUint, width: u32) -> Vec<i8> {
+    let modulus = 1u32 << width;
+    let mut k = number.clone();
+    let mut digits = vec![];
+    while k > BigUint::ZERO {
+        let mut digit = 0i32;
+        if k.bit(0) {
+            digit = (k.iter_u32_digits().next().unwrap_or(0) & (modulus - 1)) as i32;
+            if digit >= (modulus / 2) as i32 {
+                digit -= modulus as i32;
+            }
+            if digit > 0 {
+                k -= digit as u32;
+            } else {
+                k += (-digit) as u32;
+            }
+        }
+        digits.push(digit as i8);
+        k >>= 1;
     }
+    digits
 }
 
 // Formats the Point
@@ -102,35 +255,7 @@ impl Add for Point {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        if self.a != other.a || self.b != other.b {
-            panic!("Points are not on the same curve");
-        }
-
-        match (self.x.clone(), other.x.clone()) {
-            (None, _) => return other,
-            (_, None) => return self,
-            (Some(x1), Some(x2)) if x1 == x2 => {
-                if self.y != other.y {
-                    return Point::new(None, None, self.a, self.b); // Point at infinity
-                }
-                // Handling the doubling case
-                if self == other {
-                    let num = 3 * x1.clone().pow(2u32) + self.a.clone();
-                    let denom = 2 * self.y.as_ref().unwrap().clone();
-                    let s = num / denom;
-                    let x3 = s.pow(2u32) - 2 * x1.clone();
-                    let y3 = s * (x1.clone() - x3.clone()) - self.y.as_ref().unwrap().clone();
-                    return Point::new(Some(x3), Some(y3), self.a, self.b);
-                }
-            }
-            (Some(x1), Some(x2)) => {
-                let s = (other.y.unwrap() - self.y.clone().unwrap()) / (x2.clone() - x1.clone());
-                let x3 = s.pow(2u32) - x1.clone() - x2.clone();
-                let y3 = s * (x1.clone() - x3.clone()) - self.y.unwrap();
-                return Point::new(Some(x3), Some(y3), self.a, self.b);
-            }
-        }
-        panic!("Unhandled point addition case");
+        self.try_add(&other).unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
@@ -213,6 +338,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_try_new() {
+        let prime = 223;
+        let a = FieldElement::from_int(0, prime);
+        let b = FieldElement::from_int(7, prime);
+        let x = FieldElement::from_int(200, prime);
+        let y = FieldElement::from_int(119, prime);
+        assert_eq!(
+            Point::try_new(Some(x.clone()), Some(y.clone()), a.clone(), b.clone()),
+            Err(Error::NotOnCurve { x: x.clone(), y })
+        );
+        assert_eq!(
+            Point::try_new(Some(x), None, a.clone(), b.clone()),
+            Err(Error::IncompletePoint)
+        );
+        assert!(Point::try_new(None, None, a, b).is_ok());
+    }
+
+    #[test]
+    fn test_try_add_different_curves() {
+        let prime = 223;
+        let p1 = Point::new(
+            Some(FieldElement::from_int(192, prime)),
+            Some(FieldElement::from_int(105, prime)),
+            FieldElement::from_int(0, prime),
+            FieldElement::from_int(7, prime),
+        );
+        let p2 = Point::new(
+            None,
+            None,
+            FieldElement::from_int(0, prime),
+            FieldElement::from_int(5, prime),
+        );
+        assert_eq!(p1.try_add(&p2), Err(Error::DifferentCurves));
+    }
+
     #[test]
     fn test_display() {
         let prime = 223;
@@ -283,6 +444,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_multi_mul() {
+        let prime = 223;
+        let a = FieldElement::from_int(0, prime);
+        let b = FieldElement::from_int(7, prime);
+        let point = |x, y| {
+            Point::new(
+                Some(FieldElement::from_int(x, prime)),
+                Some(FieldElement::from_int(y, prime)),
+                a.clone(),
+                b.clone(),
+            )
+        };
+        let (p1, p2, p3) = (point(47, 71), point(192, 105), point(143, 98));
+
+        for (c1, c2, c3) in [(0u32, 0u32, 0u32), (1, 2, 3), (20, 7, 0), (255, 1000, 77)] {
+            let expected = p1.clone() * BigUint::from(c1)
+                + p2.clone() * BigUint::from(c2)
+                + p3.clone() * BigUint::from(c3);
+            let terms = [
+                (BigUint::from(c1), p1.clone()),
+                (BigUint::from(c2), p2.clone()),
+                (BigUint::from(c3), p3.clone()),
+            ];
+            assert_eq!(Point::multi_mul(&terms), expected);
+        }
+
+        let other_curve = Point::new(
+            Some(FieldElement::from_int(1, prime)),
+            Some(FieldElement::from_int(0, prime)),
+            FieldElement::from_int(0, prime),
+            FieldElement::from_int(222, prime),
+        );
+        assert_eq!(
+            Point::try_multi_mul(&[
+                (BigUint::from(1u32), p1),
+                (BigUint::from(1u32), other_curve)
+            ]),
+            Err(Error::DifferentCurves)
+        );
+        assert_eq!(Point::try_multi_mul(&[]), Err(Error::EmptyMultiMul));
+    }
+
+    #[test]
+    #[should_panic(expected = "no terms")]
+    fn test_multi_mul_empty() {
+        Point::multi_mul(&[]);
+    }
+
+    #[test]
+    fn test_wnaf() {
+        for number in [0u32, 1, 7, 8, 255, 1000, 0xdeadbeef] {
+            let digits = wnaf(&BigUint::from(number), 4);
+            let value = digits
+                .iter()
+                .rev()
+                .fold(0i64, |value, &digit| 2 * value + digit as i64);
+            assert_eq!(value, number as i64);
+            assert!(digits
+                .iter()
+                .all(|&d| d == 0 || (d % 2 != 0 && d.abs() < 8)));
+        }
+    }
+
     #[test]
     fn test_rmul() {
         let prime = 223;
@@ -0,0 +1,150 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::OnceLock;
+
+use num_bigint::BigUint;
+
+use crate::projective::ProjectivePoint;
+use crate::s256point::S256Point;
+use crate::scalar::Scalar;
+use crate::signature::Signature;
+
+/// The number of 4-bit windows in a 256-bit scalar
+const WINDOWS: usize = 64;
+
+/// PrecomputedPoint holds a table of multiples of a point, so that multiplying it by a
+/// scalar needs 64 point additions and no doublings
+/// Row i of the table holds j * 16^i * P for j from 0 to 15, about 96 KiB in total
+/// Building the table costs about as much as four multiplications, so it pays off for
+/// points used many times, like the generator or a public key that is verified against
+/// repeatedly
+#[derive(Clone)]
+pub struct PrecomputedPoint {
+    point: S256Point,
+    table: Vec<[ProjectivePoint; 16]>,
+}
+
+impl PrecomputedPoint {
+    /// Builds the table of multiples of a point
+    /// # Arguments
+    /// * `point` - The point to precompute
+    /// # Returns
+    /// * `PrecomputedPoint` - The point with its table
+    pub fn new(point: &S256Point) -> PrecomputedPoint {
+        let mut table = Vec::with_capacity(WINDOWS);
+        let mut base = ProjectivePoint::from_affine(point);
+        for _ in 0..WINDOWS {
+            let mut row = [ProjectivePoint::identity(); 16];
+            for j in 1..16 {
+                row[j] = row[j - 1].add(&base);
+            }
+            table.push(row);
+            // The next row starts from 16 times this base
+            for _ in 0..4 {
+                base = base.double();
+            }
+        }
+        PrecomputedPoint {
+            point: *point,
+            table,
+        }
+    }
+
+    /// Returns the process-wide table of the generator point, built on first use
+    pub fn generator() -> &'static PrecomputedPoint {
+        static GENERATOR: OnceLock<PrecomputedPoint> = OnceLock::new();
+        GENERATOR.get_or_init(|| PrecomputedPoint::new(&S256Point::generator()))
+    }
+
+    pub fn get_point(&self) -> &S256Point {
+        &self.point
+    }
+
+    /// Multiplies the point by a scalar in constant time
+    /// Every row is read entirely, so neither timing nor memory access depends on the scalar
+    /// # Arguments
+    /// * `scalar` - The scalar, which may be a secret
+    /// # Returns
+    /// * `S256Point` - The product
+    pub fn mul(&self, scalar: &Scalar) -> S256Point {
+        let mut result = ProjectivePoint::identity();
+        for (i, row) in self.table.iter().enumerate() {
+            result = result.add(&ProjectivePoint::lookup(row, scalar.window(i)));
+        }
+        result.to_affine()
+    }
+
+    /// Multiplies the point by a public scalar, skipping the zero windows
+    pub(crate) fn mul_vartime(&self, scalar: &Scalar) -> ProjectivePoint {
+        let mut result = ProjectivePoint::identity();
+        for (i, row) in self.table.iter().enumerate() {
+            let window = scalar.window(i);
+            if window != 0 {
+                result = result.add(&row[window]);
+            }
+        }
+        result
+    }
+
+    /// Verifies a signature against the precomputed point, which is faster than
+    /// `S256Point::verify` once the table is built
+    /// # Arguments
+    /// * `z` - The hash of the message
+    /// * `signature` - The signature to verify
+    /// # Returns
+    /// * `bool` - True if the signature is valid, false otherwise
+    pub fn verify(&self, z: BigUint, signature: Signature) -> bool {
+        S256Point::verify_with(&z, &signature, |u, v| {
+            PrecomputedPoint::generator()
+                .mul_vartime(u)
+                .add(&self.mul_vartime(v))
+                .to_affine()
+        })
+    }
+}
+
+// Formats the PrecomputedPoint, leaving out the table
+impl Debug for PrecomputedPoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrecomputedPoint")
+            .field("point", &self.point)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private_key::PrivateKey;
+
+    #[test]
+    fn test_mul() {
+        let g = S256Point::generator();
+        let table = PrecomputedPoint::generator();
+        let scalars = [
+            Scalar::ZERO,
+            Scalar::ONE,
+            Scalar::from_u64(16),
+            Scalar::from_u64(0xdeadbeef),
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&[0x5a; 32]),
+        ];
+        for k in scalars {
+            let expected = ProjectivePoint::from_affine(&g).mul(&k).to_affine();
+            assert_eq!(table.mul(&k), expected);
+            assert_eq!(table.mul_vartime(&k).to_affine(), expected);
+        }
+        assert_eq!(table.get_point(), &g);
+    }
+
+    #[test]
+    fn test_verify() {
+        let private_key = PrivateKey::new(b"12345");
+        let point = PrecomputedPoint::new(private_key.get_public_key());
+        let z = BigUint::from(0xcafeu32);
+        let signature = private_key.sign_biguint(z.clone(), None);
+
+        assert!(point.verify(z.clone(), signature.clone()));
+        assert!(!point.verify(z + 1u32, signature.clone()));
+        assert!(!PrecomputedPoint::generator().verify(BigUint::from(0xcafeu32), signature));
+    }
+}
@@ -1,58 +1,322 @@
+use hmac::{Hmac, Mac};
 use num_bigint::BigUint;
-use rand::prelude::*;
-use std::fmt::Display;
+use sha2::Sha256;
+use std::fmt::{Display, Formatter};
 
+use crate::base58::{self, Base58Error};
+use crate::error::{parse_hex, Error};
+use crate::hash::{hash256, tagged_hash};
+use crate::network::Network;
 use crate::point::Point;
+use crate::recoverable::RecoverableSignature;
 use crate::s256point::S256Point;
+use crate::scalar::Scalar;
+use crate::schnorr::{challenge, SchnorrSignature, XOnlyPublicKey};
 use crate::signature::Signature;
 
+/// Errors that can occur when parsing a private key in WIF format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WifError {
+    /// The string is not valid Base58Check
+    Base58(Base58Error),
+    /// The decoded data is neither 33 nor 34 bytes long
+    InvalidLength(usize),
+    /// The version byte does not belong to any known network
+    UnknownPrefix(u8),
+    /// The byte after the secret is not the 0x01 compression flag
+    InvalidCompressionFlag(u8),
+    /// The secret is not in the range 1 to n - 1
+    InvalidSecret,
+}
+
+// Formats the WifError
+impl Display for WifError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WifError::Base58(err) => write!(f, "{}", err),
+            WifError::InvalidLength(len) => write!(f, "Invalid WIF length {}", len),
+            WifError::UnknownPrefix(prefix) => write!(f, "Unknown WIF prefix 0x{:02x}", prefix),
+            WifError::InvalidCompressionFlag(flag) => {
+                write!(f, "Invalid WIF compression flag 0x{:02x}", flag)
+            }
+            WifError::InvalidSecret => write!(f, "WIF secret not in range 1 to n - 1"),
+        }
+    }
+}
+
+impl std::error::Error for WifError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WifError::Base58(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<Base58Error> for WifError {
+    fn from(err: Base58Error) -> Self {
+        WifError::Base58(err)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PrivateKey {
-    secret: BigUint,
-    point: Point,
+    secret: Scalar,
+    point: S256Point,
 }
 
 impl PrivateKey {
+    /// Creates a private key from a hex encoded secret
+    /// # Panics
+    /// If the secret is not valid hex or not in the range 1 to n - 1
     pub fn new(secret: &[u8]) -> PrivateKey {
-        let secret = BigUint::parse_bytes(secret, 16).unwrap();
-        PrivateKey {
-            secret: secret.clone(),
-            point: S256Point::generator().get_point().clone() * secret,
+        Self::try_new(secret).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a private key from a hex encoded secret
+    /// # Arguments
+    /// * `secret` - The secret, hex encoded
+    /// # Returns
+    /// * `Result<PrivateKey, Error>` - The private key, or why the secret is not valid
+    pub fn try_new(secret: &[u8]) -> Result<PrivateKey, Error> {
+        Self::from_secret(parse_hex(secret)?)
+    }
+
+    /// Creates a private key from its secret number
+    fn from_secret(secret: BigUint) -> Result<PrivateKey, Error> {
+        Self::from_scalar(Scalar::from_biguint(&secret).ok_or(Error::InvalidSecret)?)
+    }
+
+    /// Creates a private key from its secret scalar, which must not be zero
+    pub(crate) fn from_scalar(secret: Scalar) -> Result<PrivateKey, Error> {
+        if secret.is_zero() {
+            return Err(Error::InvalidSecret);
         }
+        Ok(PrivateKey {
+            secret,
+            point: S256Point::generator() * secret,
+        })
     }
 
-    pub fn get_secret(&self) -> &BigUint {
+    /// Serializes the private key in Wallet Import Format
+    /// # Arguments
+    /// * `compressed` - Whether the key is used with its compressed SEC public key
+    /// * `network` - The network the key is for
+    /// # Returns
+    /// * `String` - The Base58Check encoded key
+    pub fn to_wif(&self, compressed: bool, network: Network) -> String {
+        let mut data = vec![network.wif_prefix()];
+        data.extend_from_slice(&self.secret.to_bytes());
+        if compressed {
+            data.push(0x01);
+        }
+        base58::encode_check(&data)
+    }
+
+    /// Parses a private key from Wallet Import Format
+    /// # Arguments
+    /// * `wif` - The Base58Check encoded key
+    /// # Returns
+    /// * `Result<PrivateKey, WifError>` - The private key, or why it could not be parsed
+    pub fn from_wif(wif: &str) -> Result<PrivateKey, WifError> {
+        Self::parse_wif(wif).map(|(private_key, _, _)| private_key)
+    }
+
+    /// Parses a private key from Wallet Import Format, keeping its compression flag and network
+    /// # Arguments
+    /// * `wif` - The Base58Check encoded key
+    /// # Returns
+    /// * `Result<(PrivateKey, bool, Network), WifError>` - The private key, whether it is
+    ///   compressed and its network, or why it could not be parsed
+    pub fn parse_wif(wif: &str) -> Result<(PrivateKey, bool, Network), WifError> {
+        let data = base58::decode_check(wif)?;
+        let compressed = match data.len() {
+            33 => false,
+            34 if data[33] == 0x01 => true,
+            34 => return Err(WifError::InvalidCompressionFlag(data[33])),
+            len => return Err(WifError::InvalidLength(len)),
+        };
+        let network = [Network::Mainnet, Network::Testnet]
+            .into_iter()
+            .find(|network| network.wif_prefix() == data[0])
+            .ok_or(WifError::UnknownPrefix(data[0]))?;
+        let private_key = Self::from_secret(BigUint::from_bytes_be(&data[1..33]))
+            .map_err(|_| WifError::InvalidSecret)?;
+
+        Ok((private_key, compressed, network))
+    }
+
+    pub fn get_secret(&self) -> &Scalar {
         &self.secret
     }
 
-    pub fn get_point(&self) -> &Point {
+    pub fn get_point(&self) -> Point {
+        self.point.get_point()
+    }
+
+    /// Returns the public key of the private key
+    pub fn get_public_key(&self) -> &S256Point {
         &self.point
     }
 
+    /// Signs the hash of a message with a deterministic nonce (RFC 6979)
+    /// # Arguments
+    /// * `z` - The hash of the message, hex encoded
+    /// # Panics
+    /// If the hash is not valid hex
+    /// # Returns
+    /// * `Signature` - The low-s signature of the hash
     pub fn sign(&self, z: &[u8]) -> Signature {
-        let mut rng = rand::thread_rng();
-        let k = generate_random_number(S256Point::BASE_ORDER);
-        let r = (S256Point::generator().get_point().clone() * k.clone())
+        self.sign_with_entropy(z, None)
+    }
+
+    /// Signs the hash of a message with a deterministic nonce (RFC 6979)
+    /// # Arguments
+    /// * `z` - The hash of the message, hex encoded
+    /// # Returns
+    /// * `Result<Signature, Error>` - The low-s signature of the hash, or `Error::InvalidHex`
+    pub fn try_sign(&self, z: &[u8]) -> Result<Signature, Error> {
+        Ok(self.sign_biguint(parse_hex(z)?, None))
+    }
+
+    /// Signs a message, using its hash256 as the hash to sign
+    /// # Arguments
+    /// * `message` - The message to sign
+    /// # Returns
+    /// * `Signature` - The low-s signature of the message hash
+    pub fn sign_message(&self, message: &[u8]) -> Signature {
+        self.sign_biguint(hash256(message).to_biguint(), None)
+    }
+
+    /// Signs the hash of a message with a deterministic nonce (RFC 6979),
+    /// mixing optional extra entropy into the nonce derivation
+    /// # Arguments
+    /// * `z` - The hash of the message, hex encoded
+    /// * `extra_entropy` - Additional data appended to the nonce derivation, as in RFC 6979 section 3.6
+    /// # Panics
+    /// If the hash is not valid hex
+    /// # Returns
+    /// * `Signature` - The low-s signature of the hash
+    pub fn sign_with_entropy(&self, z: &[u8], extra_entropy: Option<&[u8; 32]>) -> Signature {
+        let z = parse_hex(z).unwrap_or_else(|err| panic!("{}", err));
+        self.sign_biguint(z, extra_entropy)
+    }
+
+    /// Signs the hash of a message with a deterministic nonce (RFC 6979), keeping the
+    /// recovery id that lets a verifier recover the public key from the signature
+    /// # Arguments
+    /// * `z` - The hash of the message, hex encoded
+    /// # Panics
+    /// If the hash is not valid hex
+    /// # Returns
+    /// * `RecoverableSignature` - The low-s signature of the hash with its recovery id
+    pub fn sign_recoverable(&self, z: &[u8]) -> RecoverableSignature {
+        let z = parse_hex(z).unwrap_or_else(|err| panic!("{}", err));
+        self.sign_recoverable_biguint(z, None)
+    }
+
+    /// Signs the hash of a message given as a number
+    pub(crate) fn sign_biguint(&self, z: BigUint, extra_entropy: Option<&[u8; 32]>) -> Signature {
+        self.sign_recoverable_biguint(z, extra_entropy)
+            .get_signature()
+            .clone()
+    }
+
+    /// Signs the hash of a message given as a number, keeping the recovery id
+    pub(crate) fn sign_recoverable_biguint(
+        &self,
+        z: BigUint,
+        extra_entropy: Option<&[u8; 32]>,
+    ) -> RecoverableSignature {
+        let z = Scalar::from_biguint_reduced(&z);
+        let k = self.deterministic_k(&z, extra_entropy);
+        let nonce_point = S256Point::generator() * k;
+        let x = nonce_point
             .get_x()
-            .unwrap()
-            .get_number()
-            .clone();
-        let base_order = BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap();
-        let k_inv = k.modpow(&(&base_order - 2u32), &base_order);
-        let mut s = ((BigUint::parse_bytes(z, 16).unwrap() + (r.clone() * self.secret.clone()))
-            * k_inv)
-            % base_order.clone();
-        if s > base_order.clone() / 2u32 {
-            s = base_order - s;
+            .expect("k is in the range 1 to n - 1")
+            .to_bytes();
+        let r = Scalar::from_bytes_reduced(&x);
+        // Bit 0 of the recovery id is the parity of R's y, bit 1 whether R's x overflowed n
+        let mut recovery_id = nonce_point.get_y().unwrap().is_odd() as u8;
+        if r.to_bytes() != x {
+            recovery_id |= 2;
+        }
+        let mut s = (z + r * self.secret) * k.invert();
+        if s.is_high() {
+            // -s is the signature for -R, whose y has the other parity
+            s = -s;
+            recovery_id ^= 1;
         }
 
-        Signature::new(r, s)
+        RecoverableSignature::new(Signature::new(r, s), recovery_id)
+    }
+
+    /// Signs a message with a BIP340 Schnorr signature
+    /// # Arguments
+    /// * `message` - The message to sign, of any length, usually a 32 byte hash
+    /// * `aux_rand` - Fresh auxiliary randomness mixed into the nonce, or zeros
+    /// # Returns
+    /// * `SchnorrSignature` - The signature, valid for the x-only public key
+    pub fn sign_schnorr(&self, message: &[u8], aux_rand: &[u8; 32]) -> SchnorrSignature {
+        // The x-only key stands for the point with even y, so sign with the matching secret
+        let (public_key, odd) = XOnlyPublicKey::from_point(&self.point);
+        let d = if odd { -self.secret } else { self.secret };
+        let public_key = public_key.serialize();
+
+        let aux_hash = tagged_hash("BIP0340/aux", aux_rand);
+        let mut data: Vec<u8> = d
+            .to_bytes()
+            .iter()
+            .zip(aux_hash.as_bytes())
+            .map(|(d, a)| d ^ a)
+            .collect();
+        data.extend_from_slice(&public_key);
+        data.extend_from_slice(message);
+        let k = Scalar::from_bytes_reduced(tagged_hash("BIP0340/nonce", &data).as_bytes());
+        assert!(!k.is_zero(), "BIP340 nonce is zero");
+
+        let nonce_point = S256Point::generator() * k;
+        let (r, odd) = XOnlyPublicKey::from_point(&nonce_point);
+        let k = if odd { -k } else { k };
+        let r = *r.to_point().get_x().unwrap();
+
+        let e = challenge(&r, &public_key, message);
+        SchnorrSignature::new(r, k + e * d)
+    }
+
+    /// Generates the nonce k from the secret and the message hash following RFC 6979
+    /// with HMAC-SHA256
+    /// # Arguments
+    /// * `z` - The hash of the message, reduced modulo n
+    /// * `extra_entropy` - Optional additional data for the nonce derivation
+    /// # Returns
+    /// * `Scalar` - The nonce, in the range 1 to n - 1
+    fn deterministic_k(&self, z: &Scalar, extra_entropy: Option<&[u8; 32]>) -> Scalar {
+        let mut k = [0u8; 32];
+        let mut v = [1u8; 32];
+        let secret_bytes = self.secret.to_bytes();
+        let z_bytes = z.to_bytes();
+        let extra_entropy: &[u8] = extra_entropy.map_or(&[], |e| e);
+
+        k = hmac_sha256(&k, &[&v, &[0x00], &secret_bytes, &z_bytes, extra_entropy]);
+        v = hmac_sha256(&k, &[&v]);
+        k = hmac_sha256(&k, &[&v, &[0x01], &secret_bytes, &z_bytes, extra_entropy]);
+        v = hmac_sha256(&k, &[&v]);
+
+        loop {
+            v = hmac_sha256(&k, &[&v]);
+            if let Some(candidate) = Scalar::from_bytes(&v).filter(|k| !k.is_zero()) {
+                return candidate;
+            }
+            k = hmac_sha256(&k, &[&v, &[0x00]]);
+            v = hmac_sha256(&k, &[&v]);
+        }
     }
 }
 
 impl Display for PrivateKey {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "PrivateKey({:0>64x})", self.secret)
+        write!(f, "PrivateKey({})", self.secret)
     }
 }
 
@@ -62,19 +326,201 @@ impl PartialEq for PrivateKey {
     }
 }
 
-fn generate_random_number(max: &[u8]) -> BigUint {
-    let mut rng = rand::thread_rng();
+/// Computes the HMAC-SHA256 of the concatenation of the given data
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    for chunk in data {
+        mac.update(chunk);
+    }
+    mac.finalize().into_bytes().into()
+}
 
-    let mut secret: [u8; 32] = [0u8; 32];
-    rng.fill_bytes(&mut secret);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let mut s = BigUint::from_bytes_be(&secret);
+    // Vectors cross-checked against libsecp256k1's RFC 6979 nonce function
+    #[test]
+    fn test_sign_deterministic() {
+        let cases = [
+            (
+                "1",
+                "a0dc65ffca799873cbea0ac274015b9526505daaaed385155425f7337704883e",
+                "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8",
+                "2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5",
+            ),
+            (
+                "1",
+                "7d1833f54854ac51659521afcd0ec6dca2ce2351429614bfa28a756b1b3c637f",
+                "8600dbd41e348fe5c9465ab92d23e3db8b98b873beecd930736488696438cb6b",
+                "547fe64427496db33bf66019dacbf0039c04199abb0122918601db38a72cfc21",
+            ),
+            (
+                "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+                "a0dc65ffca799873cbea0ac274015b9526505daaaed385155425f7337704883e",
+                "fd567d121db66e382991534ada77a6bd3106f0a1098c231e47993447cd6af2d0",
+                "6b39cd0eb1bc8603e159ef5c20a5c8ad685a45b06ce9bebed3f153d10d93bed5",
+            ),
+            (
+                "5a3028a13c7c5b0b455c155198de1a4b3a75a9009b972cd17577c0bd6a3a0949",
+                "7d1833f54854ac51659521afcd0ec6dca2ce2351429614bfa28a756b1b3c637f",
+                "b489489d93b389d26d4b2a22bd3b9945ead9b4df097cf830056e6471d9cbdb82",
+                "321fcb71559686b447d61a9f440929fe366fafb93f0d82630fd263a31976861f",
+            ),
+        ];
 
-    let max = BigUint::parse_bytes(max, 16).unwrap();
-    while s >= max {
-        rng.fill_bytes(&mut secret);
-        s = BigUint::from_bytes_be(&secret);
+        for (secret, z, r, s) in cases {
+            let private_key = PrivateKey::new(secret.as_bytes());
+            let signature = private_key.sign(z.as_bytes());
+            assert_eq!(signature, Signature::from_bytes(r.as_bytes(), s.as_bytes()));
+            assert_eq!(private_key.sign(z.as_bytes()), signature);
+        }
     }
 
-    s
+    #[test]
+    fn test_sign_message() {
+        let private_key = PrivateKey::new(b"12345");
+        let message = b"Programming Bitcoin!";
+        let signature = private_key.sign_message(message);
+        assert_eq!(
+            signature,
+            private_key.sign(hash256(message).to_string().as_bytes())
+        );
+        let point = S256Point::generator() * *private_key.get_secret();
+        assert!(point.verify_message(message, &signature));
+        assert!(!point.verify_message(b"Programming Bitcoin?", &signature));
+    }
+
+    #[test]
+    fn test_wif() {
+        let cases = [
+            (
+                BigUint::from(2u32).pow(256) - BigUint::from(2u32).pow(199),
+                true,
+                Network::Mainnet,
+                "L5oLkpV3aqBJ4BgssVAsax1iRa77G5CVYnv9adQ6Z87te7TyUdSC",
+            ),
+            (
+                BigUint::from(2u32).pow(256) - BigUint::from(2u32).pow(201),
+                false,
+                Network::Testnet,
+                "93XfLeifX7Jx7n7ELGMAf1SUR6f9kgQs8Xke8WStMwUtrDucMzn",
+            ),
+            (
+                BigUint::parse_bytes(
+                    b"0dba685b4511dbd3d368e5c4358a1277de9486447af7b3604a69b8d9d8b7889d",
+                    16,
+                )
+                .unwrap(),
+                false,
+                Network::Mainnet,
+                "5HvLFPDVgFZRK9cd4C5jcWki5Skz6fmKqi1GQJf5ZoMofid2Dty",
+            ),
+            (
+                BigUint::parse_bytes(
+                    b"1cca23de92fd1862fb5b76e5f4f50eb082165e5191e116c18ed1a6b24be6a53f",
+                    16,
+                )
+                .unwrap(),
+                true,
+                Network::Testnet,
+                "cNYfWuhDpbNM1JWc3c6JTrtrFVxU4AGhUKgw5f93NP2QaBqmxKkg",
+            ),
+        ];
+
+        for (secret, compressed, network, wif) in cases {
+            let private_key = PrivateKey::from_secret(secret).unwrap();
+            assert_eq!(private_key.to_wif(compressed, network), wif);
+            assert_eq!(
+                PrivateKey::parse_wif(wif),
+                Ok((private_key.clone(), compressed, network))
+            );
+            assert_eq!(PrivateKey::from_wif(wif), Ok(private_key));
+        }
+    }
+
+    #[test]
+    fn test_wif_errors() {
+        let mut data = vec![0x80];
+        data.extend_from_slice(&[0x11; 32]);
+        assert_eq!(
+            PrivateKey::from_wif(&base58::encode_check(&data[..32])),
+            Err(WifError::InvalidLength(32))
+        );
+
+        let mut bad_flag = data.clone();
+        bad_flag.push(0x02);
+        assert_eq!(
+            PrivateKey::from_wif(&base58::encode_check(&bad_flag)),
+            Err(WifError::InvalidCompressionFlag(0x02))
+        );
+
+        let mut bad_prefix = data.clone();
+        bad_prefix[0] = 0x81;
+        assert_eq!(
+            PrivateKey::from_wif(&base58::encode_check(&bad_prefix)),
+            Err(WifError::UnknownPrefix(0x81))
+        );
+
+        let mut zero = vec![0x80];
+        zero.extend_from_slice(&[0x00; 32]);
+        assert_eq!(
+            PrivateKey::from_wif(&base58::encode_check(&zero)),
+            Err(WifError::InvalidSecret)
+        );
+
+        assert_eq!(
+            PrivateKey::from_wif("5HvLFPDVgFZRK9cd4C5jcWki5Skz6fmKqi1GQJf5ZoMofid2Dtz"),
+            Err(WifError::Base58(Base58Error::InvalidChecksum))
+        );
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(
+            PrivateKey::try_new(b"not hex"),
+            Err(Error::InvalidHex(String::from("not hex")))
+        );
+        assert_eq!(PrivateKey::try_new(b"0"), Err(Error::InvalidSecret));
+        assert_eq!(
+            PrivateKey::try_new(S256Point::BASE_ORDER),
+            Err(Error::InvalidSecret)
+        );
+        assert_eq!(PrivateKey::try_new(b"1"), Ok(PrivateKey::new(b"1")));
+    }
+
+    #[test]
+    fn test_try_sign() {
+        let private_key = PrivateKey::new(b"1");
+        assert_eq!(
+            private_key.try_sign(b"xx"),
+            Err(Error::InvalidHex(String::from("xx")))
+        );
+        assert_eq!(private_key.try_sign(b"55"), Ok(private_key.sign(b"55")));
+    }
+
+    #[test]
+    fn test_sign_with_entropy() {
+        let private_key = PrivateKey::new(b"1");
+        let z = b"a0dc65ffca799873cbea0ac274015b9526505daaaed385155425f7337704883e";
+        let signature = private_key.sign_with_entropy(z, Some(&[0x42; 32]));
+        assert_eq!(
+            signature,
+            Signature::from_bytes(
+                b"400b8a5f10d27b17ff5bd77a7baf0a8b0fd758057508eee7cfa230a43971b984",
+                b"4235c775e2fd7a9dd34c228336a4baff02b4b76c9c9d707cb6dc8957b951a2cc",
+            )
+        );
+        assert_ne!(signature, private_key.sign(z));
+        assert_eq!(private_key.sign_with_entropy(z, None), private_key.sign(z));
+    }
+
+    #[test]
+    fn test_sign_verifies() {
+        let private_key =
+            PrivateKey::new(b"5a3028a13c7c5b0b455c155198de1a4b3a75a9009b972cd17577c0bd6a3a0949");
+        let z = b"bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423";
+        let point = S256Point::generator() * *private_key.get_secret();
+        assert!(point.verify(BigUint::parse_bytes(z, 16).unwrap(), private_key.sign(z)));
+    }
 }
@@ -0,0 +1,184 @@
+use crate::fe256::{mask, Fe256};
+use crate::s256point::S256Point;
+use crate::scalar::Scalar;
+
+/// 3 * b, the curve constant used by the complete formulas for y^2 = x^3 + 7
+const B3: u64 = 21;
+
+/// ProjectivePoint is a secp256k1 point in homogeneous projective coordinates (X : Y : Z),
+/// standing for the affine point (X / Z, Y / Z), with the point at infinity as (0 : 1 : 0)
+/// It uses the complete formulas from Renes, Costello and Batina (2015), which have no
+/// special cases, so multiplying by a secret scalar takes the same steps for every scalar
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ProjectivePoint {
+    x: Fe256,
+    y: Fe256,
+    z: Fe256,
+}
+
+impl ProjectivePoint {
+    /// Returns the point at infinity
+    pub(crate) fn identity() -> Self {
+        ProjectivePoint {
+            x: Fe256::ZERO,
+            y: Fe256::ONE,
+            z: Fe256::ZERO,
+        }
+    }
+
+    /// Converts an affine point, with Z = 1
+    pub(crate) fn from_affine(point: &S256Point) -> Self {
+        match (point.get_x(), point.get_y()) {
+            (Some(x), Some(y)) => ProjectivePoint {
+                x: *x,
+                y: *y,
+                z: Fe256::ONE,
+            },
+            _ => Self::identity(),
+        }
+    }
+
+    /// Converts the point to affine coordinates with one modular inversion
+    pub(crate) fn to_affine(self) -> S256Point {
+        if self.z.is_zero() {
+            return S256Point::infinity();
+        }
+        let z_inv = self.z.invert();
+        S256Point::from_coordinates(self.x * z_inv, self.y * z_inv)
+    }
+
+    /// Adds two points with the complete formula for a = 0 (Algorithm 7)
+    /// It is also correct for equal points, opposite points and the point at infinity
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        let b3 = Fe256::from_u64(B3);
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (other.x, other.y, other.z);
+
+        let t0 = x1 * x2;
+        let t1 = y1 * y2;
+        let t2 = z1 * z2;
+        let t3 = (x1 + y1) * (x2 + y2) - (t0 + t1);
+        let t4 = (y1 + z1) * (y2 + z2) - (t1 + t2);
+        let y3 = (x1 + z1) * (x2 + z2) - (t0 + t2);
+        let t0 = t0 + t0 + t0;
+        let t2 = b3 * t2;
+        let z3 = t1 + t2;
+        let t1 = t1 - t2;
+        let y3 = b3 * y3;
+        let x3 = t3 * t1 - t4 * y3;
+        let y3 = t1 * z3 + y3 * t0;
+        let z3 = z3 * t4 + t0 * t3;
+        ProjectivePoint {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    /// Doubles the point with the complete formula for a = 0 (Algorithm 9)
+    pub(crate) fn double(&self) -> Self {
+        let b3 = Fe256::from_u64(B3);
+        let (x, y, z) = (self.x, self.y, self.z);
+
+        let t0 = y.square();
+        let z3 = t0 + t0;
+        let z3 = z3 + z3;
+        let z3 = z3 + z3;
+        let t1 = y * z;
+        let t2 = b3 * z.square();
+        let x3 = t2 * z3;
+        let y3 = t0 + t2;
+        let z3 = t1 * z3;
+        let t2 = t2 + t2 + t2;
+        let t0 = t0 - t2;
+        let y3 = x3 + t0 * y3;
+        let x3 = t0 * (x * y);
+        ProjectivePoint {
+            x: x3 + x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    /// Multiplies the point by a scalar in constant time, with a fixed 4-bit window
+    /// Every window costs four doublings and one addition, and the table entry is
+    /// selected by reading all of them, so neither timing nor memory access depends on
+    /// the scalar
+    pub(crate) fn mul(&self, scalar: &Scalar) -> Self {
+        // table[i] = i * self
+        let mut table = [Self::identity(); 16];
+        for i in 1..16 {
+            table[i] = table[i - 1].add(self);
+        }
+
+        let mut result = Self::identity();
+        for i in (0..64).rev() {
+            for _ in 0..4 {
+                result = result.double();
+            }
+            result = result.add(&Self::lookup(&table, scalar.window(i)));
+        }
+        result
+    }
+
+    /// Returns table[index] after reading every entry of the table
+    pub(crate) fn lookup(table: &[Self; 16], index: usize) -> Self {
+        let mut result = Self::identity();
+        for (i, entry) in table.iter().enumerate() {
+            let difference = (i ^ index) as u64;
+            // All ones if the difference is zero, without comparing
+            let equal = mask((difference | difference.wrapping_neg()) >> 63 == 0);
+            result.x = Fe256::select(equal, &result.x, &entry.x);
+            result.y = Fe256::select(equal, &result.y, &entry.y);
+            result.z = Fe256::select(equal, &result.z, &entry.z);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jacobian::JacobianPoint;
+
+    #[test]
+    fn test_mul_matches_variable_time() {
+        let g = S256Point::generator();
+        let scalars = [
+            Scalar::ZERO,
+            Scalar::ONE,
+            Scalar::from_u64(15),
+            Scalar::from_u64(16),
+            Scalar::from_u64(0xdeadbeef),
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&[0xa5; 32]),
+        ];
+        for k in scalars {
+            let expected = JacobianPoint::multi_mul_with_generator(&k, &[]).to_affine();
+            assert_eq!(
+                ProjectivePoint::from_affine(&g).mul(&k).to_affine(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_complete() {
+        let g = ProjectivePoint::from_affine(&S256Point::generator());
+        let identity = ProjectivePoint::identity();
+        let two_g = g.double();
+
+        assert_eq!(g.add(&g).to_affine(), two_g.to_affine());
+        assert_eq!(identity.add(&g).to_affine(), g.to_affine());
+        assert_eq!(g.add(&identity).to_affine(), g.to_affine());
+        assert!(identity.double().to_affine().is_infinity());
+        assert!(identity.add(&identity).to_affine().is_infinity());
+
+        let minus_g = g.mul(&-Scalar::ONE);
+        assert!(g.add(&minus_g).to_affine().is_infinity());
+        assert_eq!(
+            two_g.add(&g).to_affine(),
+            g.mul(&Scalar::from_u64(3)).to_affine()
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::{Display, Formatter};
+
+use crate::hex;
+use crate::scalar::Scalar;
+use crate::signature::Signature;
+
+/// The header byte of a compact signature with recovery id 0 and an uncompressed key
+const COMPACT_HEADER: u8 = 27;
+
+/// Errors that can occur when parsing a recoverable signature or recovering its key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryError {
+    /// The compact signature is not 65 bytes long
+    InvalidLength(usize),
+    /// The header byte of the compact signature is not in the range 27 to 34
+    InvalidHeader(u8),
+    /// The recovery id is not in the range 0 to 3
+    InvalidRecoveryId(u8),
+    /// The r or s value is not smaller than the group order n
+    ValueOutOfRange,
+    /// No public key matches the signature and the recovery id
+    NoPublicKey,
+}
+
+// Formats the RecoveryError
+impl Display for RecoveryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecoveryError::InvalidLength(len) => {
+                write!(f, "Invalid compact signature length {}", len)
+            }
+            RecoveryError::InvalidHeader(header) => {
+                write!(f, "Invalid compact signature header {}", header)
+            }
+            RecoveryError::InvalidRecoveryId(id) => write!(f, "Invalid recovery id {}", id),
+            RecoveryError::ValueOutOfRange => {
+                write!(f, "Compact signature value not below the group order")
+            }
+            RecoveryError::NoPublicKey => write!(f, "No public key matches the signature"),
+        }
+    }
+}
+
+impl std::error::Error for RecoveryError {}
+
+/// RecoverableSignature is an ECDSA signature along with the recovery id, which tells
+/// which of the up to four public keys matching the signature is the signer's
+/// Bit 0 of the id is the parity of the y coordinate of the nonce point R, and bit 1
+/// is set if the x coordinate of R was not smaller than n, which is very unlikely
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoverableSignature {
+    signature: Signature,
+    recovery_id: u8,
+}
+
+impl RecoverableSignature {
+    /// Creates a recoverable signature
+    /// # Panics
+    /// If the recovery id is greater than 3
+    pub fn new(signature: Signature, recovery_id: u8) -> RecoverableSignature {
+        Self::try_new(signature, recovery_id).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a recoverable signature
+    /// # Arguments
+    /// * `signature` - The ECDSA signature
+    /// * `recovery_id` - The recovery id, from 0 to 3
+    /// # Returns
+    /// * `Result<RecoverableSignature, RecoveryError>` - The signature, or
+    ///   `RecoveryError::InvalidRecoveryId`
+    pub fn try_new(
+        signature: Signature,
+        recovery_id: u8,
+    ) -> Result<RecoverableSignature, RecoveryError> {
+        if recovery_id > 3 {
+            return Err(RecoveryError::InvalidRecoveryId(recovery_id));
+        }
+        Ok(RecoverableSignature {
+            signature,
+            recovery_id,
+        })
+    }
+
+    pub fn get_signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn get_recovery_id(&self) -> u8 {
+        self.recovery_id
+    }
+
+    /// Serializes the signature in the 65 byte compact format used by Bitcoin Core's
+    /// message signing: a header byte, then r and s, 32 bytes each
+    /// The header is 27 plus the recovery id, plus 4 if the key is compressed
+    /// # Arguments
+    /// * `compressed` - Whether the signer's address uses the compressed public key
+    /// # Returns
+    /// * `[u8; 65]` - The compact signature
+    pub fn serialize_compact(&self, compressed: bool) -> [u8; 65] {
+        let mut result = [0u8; 65];
+        result[0] = COMPACT_HEADER + self.recovery_id + if compressed { 4 } else { 0 };
+        result[1..33].copy_from_slice(&self.signature.get_r().to_bytes());
+        result[33..].copy_from_slice(&self.signature.get_s().to_bytes());
+        result
+    }
+
+    /// Parses a signature in the 65 byte compact format
+    /// # Arguments
+    /// * `bytes` - The compact signature
+    /// # Returns
+    /// * `Result<(RecoverableSignature, bool), RecoveryError>` - The signature and whether
+    ///   the key is compressed, or why the bytes are not a valid compact signature
+    pub fn parse_compact(bytes: &[u8]) -> Result<(RecoverableSignature, bool), RecoveryError> {
+        if bytes.len() != 65 {
+            return Err(RecoveryError::InvalidLength(bytes.len()));
+        }
+        let header = bytes[0];
+        if !(COMPACT_HEADER..COMPACT_HEADER + 8).contains(&header) {
+            return Err(RecoveryError::InvalidHeader(header));
+        }
+        let id = header - COMPACT_HEADER;
+        let r = Scalar::from_bytes(bytes[1..33].try_into().unwrap())
+            .ok_or(RecoveryError::ValueOutOfRange)?;
+        let s = Scalar::from_bytes(bytes[33..].try_into().unwrap())
+            .ok_or(RecoveryError::ValueOutOfRange)?;
+        let signature = RecoverableSignature::try_new(Signature::new(r, s), id & 3)?;
+        Ok((signature, id & 4 != 0))
+    }
+}
+
+// Formats the RecoverableSignature as the hex of its compact format with a compressed key
+impl Display for RecoverableSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(&self.serialize_compact(true)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private_key::PrivateKey;
+    use crate::s256point::S256Point;
+    use num_bigint::BigUint;
+
+    /// Signatures made by libsecp256k1: secret, hash, recovery id, r and s
+    const VECTORS: [(&str, &str, u8, &str); 4] = [
+        (
+            "8b387de39861728c92ec9f589c303b1038ff60eb3963b12cd212263a1d1e0f00",
+            "bc62d4b80d9e36da29c16c5d4d9f11731f36052c72401a76c23c0fb5a9b74423",
+            0,
+            "68ffc16c8a9abc633ed2b922ca24a17d37f6db4950bccc5285fb3fa60f967ba9\
+             672397ab76f140c4b340e5168b2221d67bf3e53d35e4e2d75e4a7fcbf1a76f02",
+        ),
+        (
+            "1",
+            "1",
+            1,
+            "6673ffad2147741f04772b6f921f0ba6af0c1e77fc439e65c36dedf4092e8898\
+             4c1a971652e0ada880120ef8025e709fff2080c4a39aae068d12eed009b68c89",
+        ),
+        (
+            "deadbeef",
+            "ec208baa0fc1c19f708a9ca96fdeff3ac3f230bb4a7ba4aede4942ad003c0f60",
+            1,
+            "ced4be270d18947e1fd7c0c29fb51e4bf1b4f90e3f31106b48cf87d8e8c43c0d\
+             152525c9ff1c21e178e0c0bd69ad5f4c4a102f9f7812d71e92afd94a9a6fd770",
+        ),
+        (
+            "12345",
+            "7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+            0,
+            "e9ec1e65982d7cfd2fff41b6bf4f4361ee727f81ec75d384cbf1dbbe9224f674\
+             0086f075b3b243037fc8f47203327b69f7427e18403dad57f713e5100dbfe988",
+        ),
+    ];
+
+    #[test]
+    fn test_sign_recoverable() {
+        for (secret, z, recovery_id, compact) in VECTORS {
+            let private_key = PrivateKey::new(secret.as_bytes());
+            let signature = private_key.sign_recoverable(z.as_bytes());
+            assert_eq!(signature.get_recovery_id(), recovery_id, "{}", secret);
+            assert_eq!(
+                hex::encode(&signature.serialize_compact(true)[1..]),
+                compact,
+                "{}",
+                secret
+            );
+            assert_eq!(
+                signature.get_signature(),
+                &private_key.sign(z.as_bytes()),
+                "{}",
+                secret
+            );
+
+            let z = BigUint::parse_bytes(z.as_bytes(), 16).unwrap();
+            assert_eq!(
+                S256Point::recover(z, &signature).unwrap(),
+                *private_key.get_public_key(),
+                "{}",
+                secret
+            );
+        }
+    }
+
+    #[test]
+    fn test_recover_other_ids() {
+        // Recovering with the wrong id gives a different key, or none
+        let private_key = PrivateKey::new(b"deadbeef");
+        let z = BigUint::from(0xcafeu32);
+        let signature = private_key.sign_recoverable_biguint(z.clone(), None);
+        for id in 0..4 {
+            let other = RecoverableSignature::new(signature.get_signature().clone(), id);
+            match S256Point::recover(z.clone(), &other) {
+                Ok(key) => assert_eq!(
+                    key == *private_key.get_public_key(),
+                    id == signature.get_recovery_id()
+                ),
+                Err(err) => assert_eq!(err, RecoveryError::NoPublicKey),
+            }
+        }
+        // r + n is not below p for this r, so ids 2 and 3 have no key
+        let high = RecoverableSignature::new(signature.get_signature().clone(), 2);
+        assert_eq!(
+            S256Point::recover(z.clone(), &high),
+            Err(RecoveryError::NoPublicKey)
+        );
+
+        let zero = RecoverableSignature::new(Signature::new(Scalar::ZERO, Scalar::ONE), 0);
+        assert_eq!(
+            S256Point::recover(z, &zero),
+            Err(RecoveryError::NoPublicKey)
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let private_key = PrivateKey::new(b"12345");
+        let signature = private_key.sign_recoverable(b"abcdef");
+        for compressed in [false, true] {
+            let bytes = signature.serialize_compact(compressed);
+            assert_eq!(
+                bytes[0],
+                27 + signature.get_recovery_id() + 4 * compressed as u8
+            );
+            assert_eq!(
+                RecoverableSignature::parse_compact(&bytes),
+                Ok((signature.clone(), compressed))
+            );
+        }
+
+        let mut bytes = signature.serialize_compact(true);
+        assert_eq!(
+            RecoverableSignature::parse_compact(&bytes[..64]),
+            Err(RecoveryError::InvalidLength(64))
+        );
+        bytes[0] = 35;
+        assert_eq!(
+            RecoverableSignature::parse_compact(&bytes),
+            Err(RecoveryError::InvalidHeader(35))
+        );
+        bytes[0] = 31;
+        bytes[33..].copy_from_slice(&[0xff; 32]);
+        assert_eq!(
+            RecoverableSignature::parse_compact(&bytes),
+            Err(RecoveryError::ValueOutOfRange)
+        );
+        assert_eq!(
+            RecoverableSignature::try_new(signature.get_signature().clone(), 4),
+            Err(RecoveryError::InvalidRecoveryId(4))
+        );
+    }
+}
@@ -1,15 +1,59 @@
 use std::fmt::{Display, Formatter};
 use std::ops::Mul;
+use std::sync::OnceLock;
 
 use num_bigint::BigUint;
 
+use crate::address::Address;
+use crate::bip32::{Bip32Error, HARDENED};
+use crate::error::Error;
+use crate::fe256::Fe256;
 use crate::field_element::FieldElement;
+use crate::hash::{hash160, hash256, hmac_sha512, Hash160};
+use crate::hex;
+use crate::jacobian::JacobianPoint;
+use crate::network::Network;
 use crate::point::Point;
+use crate::precomputed::PrecomputedPoint;
+use crate::projective::ProjectivePoint;
+use crate::recoverable::{RecoverableSignature, RecoveryError};
+use crate::scalar::Scalar;
 use crate::signature::Signature;
 
+/// Errors that can occur when parsing a SEC encoded public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecError {
+    /// The input is empty
+    Empty,
+    /// The prefix byte is not 0x02, 0x03 or 0x04
+    InvalidPrefix(u8),
+    /// The input length does not match the prefix
+    InvalidLength(usize),
+    /// A coordinate is not smaller than the field prime
+    CoordinateOutOfRange,
+    /// The coordinates do not describe a point on the curve
+    NotOnCurve,
+}
+
+// Formats the SecError
+impl Display for SecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            SecError::Empty => write!(f, "Empty SEC public key"),
+            SecError::InvalidPrefix(prefix) => write!(f, "Invalid SEC prefix 0x{:02x}", prefix),
+            SecError::InvalidLength(len) => write!(f, "Invalid SEC public key length {}", len),
+            SecError::CoordinateOutOfRange => write!(f, "SEC coordinate not in field range"),
+            SecError::NotOnCurve => write!(f, "SEC public key is not on the curve"),
+        }
+    }
+}
+
+impl std::error::Error for SecError {}
+
 /// Elliptic curve point on secp256k1
-#[derive(Debug, Clone, PartialEq)]
-pub struct S256Point(Point);
+/// The affine coordinates are stored as `Fe256` values, None being the point at infinity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct S256Point(Option<(Fe256, Fe256)>);
 
 impl S256Point {
     pub const A: &'static [u8; 1] = b"0";
@@ -28,21 +72,34 @@ impl S256Point {
     /// # Arguments
     /// * `x` - The x coordinate of the point
     /// * `y` - The y coordinate of the point
+    /// # Panics
+    /// If the coordinates are not valid hex or do not describe a point on the curve
     /// # Returns
     /// * `S256Point` - The S256Point created from the coordinates
     pub fn new(x: Option<&[u8]>, y: Option<&[u8]>) -> Self {
-        let a = FieldElement::from_bytes(Self::A, Self::PRIME);
-        let b = FieldElement::from_bytes(Self::B, Self::PRIME);
+        Self::try_new(x, y).unwrap_or_else(|err| panic!("{}", err))
+    }
 
+    /// Creates a new S256Point from hex encoded x and y coordinates
+    /// # Arguments
+    /// * `x` - The x coordinate of the point
+    /// * `y` - The y coordinate of the point
+    /// # Returns
+    /// * `Result<S256Point, Error>` - The S256Point, or why the coordinates do not describe one
+    pub fn try_new(x: Option<&[u8]>, y: Option<&[u8]>) -> Result<Self, Error> {
         match (x, y) {
             (Some(x), Some(y)) => {
-                let gx = FieldElement::from_bytes(x, Self::PRIME);
-                let gy = FieldElement::from_bytes(y, Self::PRIME);
-
-                S256Point(Point::new(Some(gx), Some(gy), a, b))
+                let x = FieldElement::try_from_bytes(x, Self::PRIME)?;
+                let y = FieldElement::try_from_bytes(y, Self::PRIME)?;
+                let fx = Fe256::from_biguint(x.get_number()).unwrap();
+                let fy = Fe256::from_biguint(y.get_number()).unwrap();
+                if fy.square() != Self::curve_rhs(&fx) {
+                    return Err(Error::NotOnCurve { x, y });
+                }
+                Ok(Self::from_coordinates(fx, fy))
             }
-            (None, None) => S256Point(Point::new(None, None, a, b)),
-            _ => panic!("Incomplete point coordinates"),
+            (None, None) => Ok(Self::infinity()),
+            _ => Err(Error::IncompletePoint),
         }
     }
 
@@ -50,43 +107,292 @@ impl S256Point {
     /// # Returns
     /// * `S256Point` - The generator point of the curve
     pub fn generator() -> Self {
-        S256Point::new(Some(Self::G_X), Some(Self::G_Y))
+        static GENERATOR: OnceLock<S256Point> = OnceLock::new();
+        *GENERATOR.get_or_init(|| {
+            let coordinate = |hex_bytes: &[u8]| {
+                let bytes = hex::decode(std::str::from_utf8(hex_bytes).unwrap()).unwrap();
+                Fe256::from_bytes(&bytes.try_into().unwrap()).unwrap()
+            };
+            Self::from_coordinates(coordinate(Self::G_X), coordinate(Self::G_Y))
+        })
     }
 
-    /// Returns the point
-    pub fn get_point(&self) -> &Point {
-        &self.0
+    /// Returns the point as a generic `Point` over `FieldElement` coordinates
+    pub fn get_point(&self) -> Point {
+        let prime = BigUint::parse_bytes(Self::PRIME, 16).unwrap();
+        let a = FieldElement::from_bytes(Self::A, Self::PRIME);
+        let b = FieldElement::from_bytes(Self::B, Self::PRIME);
+        match self.0 {
+            Some((x, y)) => Point::new(
+                Some(FieldElement::from_biguint(x.to_biguint(), prime.clone())),
+                Some(FieldElement::from_biguint(y.to_biguint(), prime)),
+                a,
+                b,
+            ),
+            None => Point::new(None, None, a, b),
+        }
+    }
+
+    /// Returns the x coordinate, or None for the point at infinity
+    pub fn get_x(&self) -> Option<&Fe256> {
+        self.0.as_ref().map(|(x, _)| x)
+    }
+
+    /// Returns the y coordinate, or None for the point at infinity
+    pub fn get_y(&self) -> Option<&Fe256> {
+        self.0.as_ref().map(|(_, y)| y)
+    }
+
+    /// Returns whether the point is the point at infinity
+    pub fn is_infinity(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Serializes the point in SEC format
+    /// # Arguments
+    /// * `compressed` - Whether to use the 33 byte compressed format instead of the 65 byte uncompressed one
+    /// # Panics
+    /// If the point is the point at infinity, which has no SEC encoding
+    /// # Returns
+    /// * `Vec<u8>` - The SEC encoded point
+    pub fn sec(&self, compressed: bool) -> Vec<u8> {
+        let (x, y) = self.0.expect("The point at infinity has no SEC encoding");
+
+        if compressed {
+            let prefix = if y.is_odd() { 0x03 } else { 0x02 };
+            let mut result = vec![prefix];
+            result.extend_from_slice(&x.to_bytes());
+            result
+        } else {
+            let mut result = vec![0x04];
+            result.extend_from_slice(&x.to_bytes());
+            result.extend_from_slice(&y.to_bytes());
+            result
+        }
+    }
+
+    /// Returns the hash160 of the SEC encoded point
+    /// # Arguments
+    /// * `compressed` - Whether to hash the compressed SEC format
+    /// # Returns
+    /// * `Hash160` - The hash of the public key
+    pub fn hash160(&self, compressed: bool) -> Hash160 {
+        hash160(&self.sec(compressed))
+    }
+
+    /// Returns the P2PKH address of the point
+    /// # Arguments
+    /// * `compressed` - Whether to hash the compressed SEC format
+    /// * `network` - The network the address is for
+    /// # Returns
+    /// * `Address` - The P2PKH address
+    pub fn address(&self, compressed: bool, network: Network) -> Address {
+        Address::p2pkh(self, compressed, network)
+    }
+
+    /// Parses a point from its SEC format, compressed or uncompressed
+    /// # Arguments
+    /// * `sec` - The SEC encoded point
+    /// # Returns
+    /// * `Result<S256Point, SecError>` - The parsed point, or the reason it could not be parsed
+    pub fn parse_sec(sec: &[u8]) -> Result<S256Point, SecError> {
+        let prefix = *sec.first().ok_or(SecError::Empty)?;
+        let coordinate = |bytes: &[u8]| {
+            Fe256::from_bytes(bytes.try_into().unwrap()).ok_or(SecError::CoordinateOutOfRange)
+        };
+
+        match prefix {
+            0x04 => {
+                if sec.len() != 65 {
+                    return Err(SecError::InvalidLength(sec.len()));
+                }
+                let x = coordinate(&sec[1..33])?;
+                let y = coordinate(&sec[33..65])?;
+                if y.square() != Self::curve_rhs(&x) {
+                    return Err(SecError::NotOnCurve);
+                }
+                Ok(Self::from_coordinates(x, y))
+            }
+            0x02 | 0x03 => {
+                if sec.len() != 33 {
+                    return Err(SecError::InvalidLength(sec.len()));
+                }
+                let x = coordinate(&sec[1..33])?;
+                // Solve y^2 = x^3 + 7 for y and pick the root with the requested parity
+                let beta = Self::curve_rhs(&x).sqrt().ok_or(SecError::NotOnCurve)?;
+                let is_odd = prefix == 0x03;
+                let y = if beta.is_odd() == is_odd { beta } else { -beta };
+                Ok(Self::from_coordinates(x, y))
+            }
+            _ => Err(SecError::InvalidPrefix(prefix)),
+        }
+    }
+
+    /// Returns x^3 + 7, the right hand side of the curve equation
+    fn curve_rhs(x: &Fe256) -> Fe256 {
+        x.square() * *x + Fe256::from_u64(7)
+    }
+
+    /// Creates a S256Point from coordinates already known to be on the curve
+    pub(crate) fn from_coordinates(x: Fe256, y: Fe256) -> Self {
+        S256Point(Some((x, y)))
+    }
+
+    /// Returns the point at infinity
+    pub(crate) fn infinity() -> Self {
+        S256Point(None)
     }
 
     /// Verifies a signature
+    /// Signatures with r or s equal to zero are rejected
+    /// u * G + v * P is computed with Strauss' method in Jacobian coordinates, not with
+    /// `Point::multi_mul`
     /// # Arguments
     /// * `z` - The hash of the message
     /// * `signature` - The signature to verify
     /// # Returns
     /// * `bool` - True if the signature is valid, false otherwise
     pub fn verify(&self, z: BigUint, signature: Signature) -> bool {
-        let base_order = BigUint::parse_bytes(Self::BASE_ORDER, 16).unwrap();
-        let s_inv = signature.get_s().modpow(&(&base_order - 2u32), &base_order);
-        let u = (&z * &s_inv) % &base_order;
-        let v = (signature.get_r() * &s_inv) % &base_order;
-        let total =
-            (u * S256Point::generator().get_point().clone()) + (v * self.get_point().clone());
-        total.get_x().unwrap().get_number() == signature.get_r()
+        Self::verify_with(&z, &signature, |u, v| {
+            JacobianPoint::multi_mul_with_generator(u, &[(*v, JacobianPoint::from_affine(self))])
+                .to_affine()
+        })
+    }
+
+    /// Checks a signature given a way to compute u * G + v * P, for the public key P
+    /// # Arguments
+    /// * `z` - The hash of the message
+    /// * `signature` - The signature to verify
+    /// * `combine` - Computes u * G + v * P from u and v
+    /// # Returns
+    /// * `bool` - True if the signature is valid, false otherwise
+    pub(crate) fn verify_with<F>(z: &BigUint, signature: &Signature, combine: F) -> bool
+    where
+        F: FnOnce(&Scalar, &Scalar) -> S256Point,
+    {
+        let (r, s) = (*signature.get_r(), *signature.get_s());
+        if r.is_zero() || s.is_zero() {
+            return false;
+        }
+        let s_inv = s.invert();
+        let u = Scalar::from_biguint_reduced(z) * s_inv;
+        let v = r * s_inv;
+        match combine(&u, &v).get_x() {
+            None => false,
+            Some(x) => Scalar::from_bytes_reduced(&x.to_bytes()) == r,
+        }
+    }
+
+    /// Verifies a signature over a message, using its hash256 as the hash
+    /// # Arguments
+    /// * `message` - The signed message
+    /// * `signature` - The signature to verify
+    /// # Returns
+    /// * `bool` - True if the signature is valid, false otherwise
+    pub fn verify_message(&self, message: &[u8], signature: &Signature) -> bool {
+        self.verify(hash256(message).to_biguint(), signature.clone())
+    }
+
+    /// Derives a normal BIP32 child public key, without the private key
+    /// The child is parent + IL * G, where IL is the left half of
+    /// HMAC-SHA512(chain code, SEC(parent) || index), and the right half is the child's
+    /// chain code
+    /// # Arguments
+    /// * `chain_code` - The chain code of this key
+    /// * `index` - The child number, below 2^31 since hardened children need the private key
+    /// # Returns
+    /// * `Result<(S256Point, [u8; 32]), Bip32Error>` - The child key and its chain code,
+    ///   `Bip32Error::HardenedFromPublic` for a hardened index, or
+    ///   `Bip32Error::InvalidChild` in the very unlikely case the index has no valid child
+    pub fn derive_child(
+        &self,
+        chain_code: &[u8; 32],
+        index: u32,
+    ) -> Result<(S256Point, [u8; 32]), Bip32Error> {
+        if index >= HARDENED {
+            return Err(Bip32Error::HardenedFromPublic(index));
+        }
+        let mut data = self.sec(true);
+        data.extend_from_slice(&index.to_be_bytes());
+        let i = hmac_sha512(chain_code, &data);
+
+        let tweak = Scalar::from_bytes(i[..32].try_into().unwrap())
+            .ok_or(Bip32Error::InvalidChild(index))?;
+        let child = JacobianPoint::multi_mul_with_generator(
+            &tweak,
+            &[(Scalar::ONE, JacobianPoint::from_affine(self))],
+        )
+        .to_affine();
+        if child.is_infinity() {
+            return Err(Bip32Error::InvalidChild(index));
+        }
+        Ok((child, i[32..].try_into().unwrap()))
+    }
+
+    /// Recovers the public key that made a signature, using its recovery id
+    /// The id gives the nonce point R, and the key is r^-1 * (s * R - z * G)
+    /// # Arguments
+    /// * `z` - The hash of the message
+    /// * `signature` - The recoverable signature
+    /// # Returns
+    /// * `Result<S256Point, RecoveryError>` - The public key, or
+    ///   `RecoveryError::NoPublicKey` if no key matches the signature and the id
+    pub fn recover(
+        z: BigUint,
+        signature: &RecoverableSignature,
+    ) -> Result<S256Point, RecoveryError> {
+        let id = signature.get_recovery_id();
+        let (r, s) = (
+            *signature.get_signature().get_r(),
+            *signature.get_signature().get_s(),
+        );
+        if r.is_zero() || s.is_zero() {
+            return Err(RecoveryError::NoPublicKey);
+        }
+
+        // The x coordinate of R is r, or r + n if it was not smaller than n
+        let mut x = r.to_biguint();
+        if id & 2 != 0 {
+            x += BigUint::parse_bytes(Self::BASE_ORDER, 16).unwrap();
+        }
+        let x = Fe256::from_biguint(&x).ok_or(RecoveryError::NoPublicKey)?;
+        let mut sec = vec![0x02 | (id & 1)];
+        sec.extend_from_slice(&x.to_bytes());
+        let nonce_point = S256Point::parse_sec(&sec).map_err(|_| RecoveryError::NoPublicKey)?;
+
+        let r_inv = r.invert();
+        let z = Scalar::from_biguint_reduced(&z);
+        let point = JacobianPoint::multi_mul_with_generator(
+            &(-(z * r_inv)),
+            &[(s * r_inv, JacobianPoint::from_affine(&nonce_point))],
+        )
+        .to_affine();
+        if point.is_infinity() {
+            return Err(RecoveryError::NoPublicKey);
+        }
+        Ok(point)
     }
 }
 
 // Formats the S256Point
 impl Display for S256Point {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match &self.0.get_x() {
+        match &self.0 {
             None => write!(f, "S256Point(infinity)"),
-            Some(x) => write!(
-                f,
-                "S256Point({},{})",
-                x.get_number(),
-                self.0.get_y().as_ref().unwrap().get_number()
-            ),
+            Some((x, y)) => write!(f, "S256Point({},{})", x.to_biguint(), y.to_biguint()),
+        }
+    }
+}
+
+// S256Point multiplication, in constant time since the scalar may be a secret
+impl Mul<Scalar> for S256Point {
+    type Output = S256Point;
+
+    fn mul(self, scalar: Scalar) -> S256Point {
+        if self == S256Point::generator() {
+            return PrecomputedPoint::generator().mul(&scalar);
         }
+        ProjectivePoint::from_affine(&self).mul(&scalar).to_affine()
     }
 }
 
@@ -95,18 +401,8 @@ impl Mul<BigUint> for S256Point {
     type Output = S256Point;
 
     fn mul(self, coefficient: BigUint) -> S256Point {
-        let n = BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap();
-        let coef = coefficient % n;
-
-        let res = self.0 * coef;
-
-        match res.get_x() {
-            None => S256Point::new(None, None),
-            Some(_) => S256Point::new(
-                Some(&res.get_x().unwrap().get_number().to_bytes_be()),
-                Some(&res.get_y().unwrap().get_number().to_bytes_be()),
-            ),
-        }
+        // Every point has order n, so the coefficient can be reduced modulo n
+        self * Scalar::from_biguint_reduced(&coefficient)
     }
 }
 
@@ -171,10 +467,118 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_try_new() {
+        assert_eq!(
+            S256Point::try_new(Some(S256Point::G_X), None),
+            Err(Error::IncompletePoint)
+        );
+        assert_eq!(
+            S256Point::try_new(Some(b"xyz"), Some(S256Point::G_Y)),
+            Err(Error::InvalidHex(String::from("xyz")))
+        );
+        assert!(matches!(
+            S256Point::try_new(Some(S256Point::G_X), Some(S256Point::G_X)),
+            Err(Error::NotOnCurve { .. })
+        ));
+        assert!(matches!(
+            S256Point::try_new(Some(S256Point::PRIME), Some(S256Point::G_Y)),
+            Err(Error::NumberOutOfRange { .. })
+        ));
+        assert_eq!(
+            S256Point::try_new(Some(S256Point::G_X), Some(S256Point::G_Y)),
+            Ok(S256Point::generator())
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_zero() {
+        let point = S256Point::generator();
+        let z = BigUint::from(1u32);
+        assert!(!point.verify(z.clone(), Signature::new(Scalar::ZERO, Scalar::ONE)));
+        assert!(!point.verify(z, Signature::new(Scalar::ONE, Scalar::ZERO)));
+    }
+
     #[test]
     fn test_mul_base_order_to_generator() {
         let n = BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap();
         let point = S256Point::generator();
         assert_eq!(point * n, S256Point::new(None, None));
     }
+
+    #[test]
+    fn test_sec() {
+        let cases: Vec<(BigUint, &[u8], &[u8])> = vec![
+            (
+                BigUint::from(999u32).pow(3),
+                b"049d5ca49670cbe4c3bfa84c96a8c87df086c6ea6a24ba6b809c9de234496808d56fa15cc7f3d38cda98dee2419f415b7513dde1301f8643cd9245aea7f3f911f9",
+                b"039d5ca49670cbe4c3bfa84c96a8c87df086c6ea6a24ba6b809c9de234496808d5",
+            ),
+            (
+                BigUint::from(123u32),
+                b"04a598a8030da6d86c6bc7f2f5144ea549d28211ea58faa70ebf4c1e665c1fe9b5204b5d6f84822c307e4b4a7140737aec23fc63b65b35f86a10026dbd2d864e6b",
+                b"03a598a8030da6d86c6bc7f2f5144ea549d28211ea58faa70ebf4c1e665c1fe9b5",
+            ),
+            (
+                BigUint::from(42424242u32),
+                b"04aee2e7d843f7430097859e2bc603abcc3274ff8169c1a469fee0f20614066f8e21ec53f40efac47ac1c5211b2123527e0e9b57ede790c4da1e72c91fb7da54a3",
+                b"03aee2e7d843f7430097859e2bc603abcc3274ff8169c1a469fee0f20614066f8e",
+            ),
+        ];
+
+        for (coefficient, uncompressed, compressed) in cases {
+            let point = S256Point::generator() * coefficient;
+            let uncompressed = BigUint::parse_bytes(uncompressed, 16)
+                .unwrap()
+                .to_bytes_be();
+            let compressed = BigUint::parse_bytes(compressed, 16).unwrap().to_bytes_be();
+            assert_eq!(point.sec(false), uncompressed);
+            assert_eq!(point.sec(true), compressed);
+        }
+    }
+
+    #[test]
+    fn test_parse_sec() {
+        for coefficient in [5000u32, 5001, 2019, 0xdeadbeef] {
+            let point = S256Point::generator() * BigUint::from(coefficient);
+            assert_eq!(S256Point::parse_sec(&point.sec(false)).unwrap(), point);
+            assert_eq!(S256Point::parse_sec(&point.sec(true)).unwrap(), point);
+        }
+    }
+
+    #[test]
+    fn test_parse_sec_errors() {
+        let sec = S256Point::generator().sec(true);
+        assert_eq!(S256Point::parse_sec(&[]), Err(SecError::Empty));
+        assert_eq!(
+            S256Point::parse_sec(&sec[..32]),
+            Err(SecError::InvalidLength(32))
+        );
+
+        let mut bad_prefix = sec.clone();
+        bad_prefix[0] = 0x05;
+        assert_eq!(
+            S256Point::parse_sec(&bad_prefix),
+            Err(SecError::InvalidPrefix(0x05))
+        );
+
+        // x = 5 has no matching y on secp256k1
+        let mut not_on_curve = vec![0x02; 1];
+        not_on_curve.extend_from_slice(&Fe256::from_u64(5).to_bytes());
+        assert_eq!(
+            S256Point::parse_sec(&not_on_curve),
+            Err(SecError::NotOnCurve)
+        );
+
+        let mut out_of_range = vec![0x02; 1];
+        out_of_range.extend_from_slice(&[0xff; 32]);
+        assert_eq!(
+            S256Point::parse_sec(&out_of_range),
+            Err(SecError::CoordinateOutOfRange)
+        );
+
+        let mut wrong_y = S256Point::generator().sec(false);
+        wrong_y[64] ^= 1;
+        assert_eq!(S256Point::parse_sec(&wrong_y), Err(SecError::NotOnCurve));
+    }
 }
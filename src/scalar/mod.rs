@@ -0,0 +1,429 @@
+use num_bigint::BigUint;
+use std::fmt::{Display, Formatter, Result};
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::fe256::{
+    add_limbs, biguint_to_bytes, geq, limbs_from_bytes, limbs_to_bytes, mask, mul_wide,
+    reduce_once, sub_limbs,
+};
+use crate::hex;
+
+/// The group order n of secp256k1, as little endian 64-bit limbs
+const N: [u64; 4] = [
+    0xbfd25e8cd0364141,
+    0xbaaedce6af48a03b,
+    0xfffffffffffffffe,
+    0xffffffffffffffff,
+];
+
+/// 2^256 - n, used to fold the high half of a product into the low half
+const N_COMPLEMENT: [u64; 3] = [0x402da1732fc9bebf, 0x4551231950b75fc4, 0x1];
+
+/// n - 2, the exponent of the inverse by Fermat's little theorem
+const N_MINUS_2: [u64; 4] = [
+    0xbfd25e8cd036413f,
+    0xbaaedce6af48a03b,
+    0xfffffffffffffffe,
+    0xffffffffffffffff,
+];
+
+/// n / 2, rounded down, the largest low-s value
+const N_HALF: [u64; 4] = [
+    0xdfe92f46681b20a0,
+    0x5d576e7357a4501d,
+    0xffffffffffffffff,
+    0x7fffffffffffffff,
+];
+
+/// -lambda, where lambda is the cube root of unity modulo n such that
+/// lambda * (x, y) = (beta * x, y) for every point
+const MINUS_LAMBDA: [u64; 4] = [
+    0xe0cfc810b51283cf,
+    0xa880b9fc8ec739c2,
+    0x5ad9e3fd77ed9ba4,
+    0xac9c52b33fa3cf1f,
+];
+
+/// -b1 and -b2, from the short basis (a1, b1), (a2, b2) of the lattice of pairs
+/// (k1, k2) with k1 + k2 * lambda = 0 (mod n)
+const MINUS_B1: [u64; 4] = [0x6f547fa90abfe4c3, 0xe4437ed6010e8828, 0, 0];
+const MINUS_B2: [u64; 4] = [
+    0xd765cda83db1562c,
+    0x8a280ac50774346d,
+    0xfffffffffffffffe,
+    0xffffffffffffffff,
+];
+
+/// round(2^384 * b2 / n) and round(2^384 * -b1 / n), to compute the rounded divisions
+/// of the GLV decomposition with one multiplication and a shift
+const G1: [u64; 4] = [
+    0xe893209a45dbb031,
+    0x3daa8a1471e8ca7f,
+    0xe86c90e49284eb15,
+    0x3086d221a7d46bcd,
+];
+const G2: [u64; 4] = [
+    0x1571b4ae8ac47f71,
+    0x221208ac9df506c6,
+    0x6f547fa90abfe4c4,
+    0xe4437ed6010e8828,
+];
+
+/// Scalar is an integer modulo the secp256k1 group order n, used for secrets,
+/// nonces and signature values
+/// It is stored on the stack as four little endian 64-bit limbs, always reduced below n
+/// Arithmetic, including inversion, runs in constant time, without branches on the value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Scalar([u64; 4]);
+
+impl Scalar {
+    pub const ZERO: Scalar = Scalar([0, 0, 0, 0]);
+    pub const ONE: Scalar = Scalar([1, 0, 0, 0]);
+
+    /// Creates a scalar from a small number
+    pub fn from_u64(number: u64) -> Self {
+        Scalar([number, 0, 0, 0])
+    }
+
+    /// Creates a scalar from 32 big endian bytes
+    /// # Arguments
+    /// * `bytes` - The number, big endian
+    /// # Returns
+    /// * `Option<Scalar>` - The scalar, or None if the number is not smaller than n
+    pub fn from_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let limbs = limbs_from_bytes(bytes);
+        if geq(&limbs, &N) {
+            return None;
+        }
+        Some(Scalar(limbs))
+    }
+
+    /// Creates a scalar from 32 big endian bytes, reducing the number modulo n
+    /// # Arguments
+    /// * `bytes` - The number, big endian
+    /// # Returns
+    /// * `Scalar` - The number modulo n
+    pub fn from_bytes_reduced(bytes: &[u8; 32]) -> Self {
+        // 2^256 < 2n, so one subtraction is enough
+        Scalar(reduce_once(&limbs_from_bytes(bytes), false, &N))
+    }
+
+    /// Creates a scalar from a number
+    /// # Arguments
+    /// * `number` - The number
+    /// # Returns
+    /// * `Option<Scalar>` - The scalar, or None if the number is not smaller than n
+    pub fn from_biguint(number: &BigUint) -> Option<Self> {
+        Self::from_bytes(&biguint_to_bytes(number)?)
+    }
+
+    /// Creates a scalar from a number of any size, reducing it modulo n
+    pub fn from_biguint_reduced(number: &BigUint) -> Self {
+        match biguint_to_bytes(number) {
+            Some(bytes) => Self::from_bytes_reduced(&bytes),
+            None => {
+                let n = BigUint::from_bytes_be(&limbs_to_bytes(&N));
+                Self::from_biguint(&(number % n)).unwrap()
+            }
+        }
+    }
+
+    /// Returns the scalar as 32 big endian bytes
+    pub fn to_bytes(&self) -> [u8; 32] {
+        limbs_to_bytes(&self.0)
+    }
+
+    /// Returns the scalar as a number
+    pub fn to_biguint(&self) -> BigUint {
+        BigUint::from_bytes_be(&self.to_bytes())
+    }
+
+    /// Returns whether the scalar is zero
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0, 0, 0, 0]
+    }
+
+    /// Returns whether the scalar is greater than n / 2, i.e. a high-s signature value
+    pub fn is_high(&self) -> bool {
+        !geq(&N_HALF, &self.0)
+    }
+
+    /// Returns bit i of the scalar, counting from the least significant bit
+    pub fn bit(&self, i: usize) -> bool {
+        (self.0[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    /// Returns the 4-bit window i of the scalar, counting from the least significant one
+    pub(crate) fn window(&self, i: usize) -> usize {
+        ((self.0[i / 16] >> ((i % 16) * 4)) & 0xf) as usize
+    }
+
+    /// Splits the scalar k into k1 and k2 with k = k1 + k2 * lambda (mod n), following the
+    /// GLV method, so that k * P = k1 * P + k2 * lambda * P needs half as many doublings
+    /// Both halves are below 2^128 in absolute value, so each is either small or n minus
+    /// a small value, which `is_high` tells apart
+    /// # Returns
+    /// * `(Scalar, Scalar)` - The halves k1 and k2
+    pub(crate) fn split_lambda(&self) -> (Scalar, Scalar) {
+        let c1 = Scalar(mul_shift_384(&self.0, &G1));
+        let c2 = Scalar(mul_shift_384(&self.0, &G2));
+        let k2 = c1 * Scalar(MINUS_B1) + c2 * Scalar(MINUS_B2);
+        let k1 = k2 * Scalar(MINUS_LAMBDA) + *self;
+        (k1, k2)
+    }
+
+    /// Returns the width-w non-adjacent form of the scalar, for variable time multiplication
+    /// Every digit is zero or odd with absolute value below 2^(w-1), and any w consecutive
+    /// digits hold at most one non-zero digit, so about one digit in w + 1 needs an addition
+    /// # Arguments
+    /// * `width` - The window width w, from 2 to 8
+    /// # Returns
+    /// * `Vec<i8>` - The digits, least significant first
+    pub(crate) fn wnaf(&self, width: usize) -> Vec<i8> {
+        assert!((2..=8).contains(&width), "wNAF width must be from 2 to 8");
+        // One more limb, since subtracting a negative digit can carry past 2^256
+        let mut k = [self.0[0], self.0[1], self.0[2], self.0[3], 0];
+        let mut digits = Vec::with_capacity(257);
+        while k.iter().any(|&limb| limb != 0) {
+            let mut digit = 0i64;
+            if k[0] & 1 == 1 {
+                digit = (k[0] & ((1 << width) - 1)) as i64;
+                if digit >= 1 << (width - 1) {
+                    digit -= 1 << width;
+                }
+                // k -= digit, leaving k divisible by 2^w
+                let mut borrow = digit as i128;
+                for limb in k.iter_mut() {
+                    let t = *limb as i128 - borrow;
+                    *limb = t as u64;
+                    borrow = -(t >> 64);
+                }
+            }
+            digits.push(digit as i8);
+            for i in 0..5 {
+                k[i] = (k[i] >> 1) | k.get(i + 1).map_or(0, |next| next << 63);
+            }
+        }
+        digits
+    }
+
+    /// Returns the multiplicative inverse, computed as self^(n-2)
+    /// The exponent is public, so the inversion takes the same time for every scalar
+    /// The inverse of zero is zero
+    pub fn invert(&self) -> Self {
+        let mut result = Scalar::ONE;
+        for i in (0..256).rev() {
+            result = result * result;
+            if (N_MINUS_2[i / 64] >> (i % 64)) & 1 == 1 {
+                result = result * *self;
+            }
+        }
+        result
+    }
+}
+
+// Formats the Scalar as 64 hex digits
+impl Display for Scalar {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", hex::encode(&self.to_bytes()))
+    }
+}
+
+// Scalar addition
+impl Add for Scalar {
+    type Output = Scalar;
+
+    fn add(self, other: Scalar) -> Scalar {
+        let (sum, carry) = add_limbs(&self.0, &other.0);
+        Scalar(reduce_once(&sum, carry, &N))
+    }
+}
+
+// Scalar subtraction
+impl Sub for Scalar {
+    type Output = Scalar;
+
+    fn sub(self, other: Scalar) -> Scalar {
+        let (difference, borrow) = sub_limbs(&self.0, &other.0);
+        Scalar(add_limbs(&difference, &N.map(|limb| limb & mask(borrow))).0)
+    }
+}
+
+// Scalar negation
+impl Neg for Scalar {
+    type Output = Scalar;
+
+    fn neg(self) -> Scalar {
+        Scalar::ZERO - self
+    }
+}
+
+// Scalar multiplication
+impl Mul for Scalar {
+    type Output = Scalar;
+
+    fn mul(self, other: Scalar) -> Scalar {
+        Scalar(reduce_wide(mul_wide(&self.0, &other.0)))
+    }
+}
+
+/// Returns a * b / 2^384, rounded to the nearest integer
+fn mul_shift_384(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let product = mul_wide(a, b);
+    let round = product[5] >> 63;
+    // The quotient is below 2^128, so adding the rounding bit cannot overflow
+    add_limbs(&[product[6], product[7], 0, 0], &[round, 0, 0, 0]).0
+}
+
+/// Reduces a 512-bit product modulo n by folding the high half into the low half,
+/// using 2^256 = 2^256 - n (mod n)
+/// Each fold shrinks the high half from 256 to 130, 4 and at most 1 bit, and the last one
+/// leaves it empty, so four folds always suffice and the work never depends on the value
+fn reduce_wide(mut wide: [u64; 8]) -> [u64; 4] {
+    for _ in 0..4 {
+        let mut folded = [0u64; 8];
+        folded[..4].copy_from_slice(&wide[..4]);
+        for i in 0..4 {
+            let mut carry = 0u128;
+            for j in 0..8 - i {
+                let product = match N_COMPLEMENT.get(j) {
+                    Some(&c) => wide[i + 4] as u128 * c as u128,
+                    None => 0,
+                };
+                let t = folded[i + j] as u128 + product + carry;
+                folded[i + j] = t as u64;
+                carry = t >> 64;
+            }
+        }
+        wide = folded;
+    }
+
+    reduce_once(&[wide[0], wide[1], wide[2], wide[3]], false, &N)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order() -> BigUint {
+        BigUint::from_bytes_be(&limbs_to_bytes(&N))
+    }
+
+    fn scalar(number: &BigUint) -> Scalar {
+        Scalar::from_biguint(number).unwrap()
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(Scalar::from_bytes(&limbs_to_bytes(&N)), None);
+        assert_eq!(
+            Scalar::from_bytes_reduced(&limbs_to_bytes(&N)),
+            Scalar::ZERO
+        );
+        assert_eq!(Scalar::from_bytes_reduced(&[0xff; 32]).to_biguint(), {
+            let max = BigUint::from_bytes_be(&[0xff; 32]);
+            max % order()
+        });
+        let big = (BigUint::from(1u32) << 300) + 5u32;
+        assert_eq!(
+            Scalar::from_biguint_reduced(&big).to_biguint(),
+            &big % order()
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_matches_biguint() {
+        let n = order();
+        let values = [
+            BigUint::from(0u32),
+            BigUint::from(1u32),
+            &n - 1u32,
+            &n >> 1u32,
+            BigUint::parse_bytes(
+                b"8b387de39861728c92ec9f589c303b1038ff60eb3963b12cd212263a1d1e0f00",
+                16,
+            )
+            .unwrap(),
+        ];
+        for a in &values {
+            for b in &values {
+                assert_eq!((scalar(a) + scalar(b)).to_biguint(), (a + b) % &n);
+                assert_eq!((scalar(a) - scalar(b)).to_biguint(), (a + &n - b) % &n);
+                assert_eq!((scalar(a) * scalar(b)).to_biguint(), (a * b) % &n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wnaf() {
+        let scalars = [
+            Scalar::ZERO,
+            Scalar::from_u64(7),
+            Scalar::from_u64(0xdeadbeef),
+            -Scalar::ONE,
+            Scalar::from_bytes_reduced(&[0xff; 32]),
+        ];
+        for scalar in scalars {
+            for width in [2, 4, 5, 8] {
+                let digits = scalar.wnaf(width);
+                let mut value = Scalar::ZERO;
+                for (i, &digit) in digits.iter().enumerate().rev() {
+                    value = value + value;
+                    if digit > 0 {
+                        value = value + Scalar::from_u64(digit as u64);
+                    } else if digit < 0 {
+                        value = value - Scalar::from_u64(-digit as u64);
+                    }
+                    assert!(
+                        digit == 0 || (digit % 2 != 0 && (digit as i32).abs() < 1 << (width - 1))
+                    );
+                    let window = &digits[i..(i + width).min(digits.len())];
+                    assert!(digit == 0 || window[1..].iter().all(|&d| d == 0));
+                }
+                assert_eq!(value, scalar);
+                assert!(digits.len() <= 257);
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_lambda() {
+        let lambda = -Scalar(MINUS_LAMBDA);
+        assert_eq!(lambda * lambda * lambda, Scalar::ONE);
+
+        let small = |k: Scalar| {
+            let k = if k.is_high() { -k } else { k };
+            k.0[2] == 0 && k.0[3] == 0
+        };
+        let scalars = [
+            Scalar::ZERO,
+            Scalar::ONE,
+            -Scalar::ONE,
+            lambda,
+            Scalar::from_bytes_reduced(&[0xff; 32]),
+            Scalar::from_bytes_reduced(&[0x80; 32]),
+            Scalar::from_bytes_reduced(&[0x13; 32]),
+        ];
+        for k in scalars {
+            let (k1, k2) = k.split_lambda();
+            assert_eq!(k1 + k2 * lambda, k);
+            assert!(small(k1) && small(k2), "{}", k);
+        }
+        assert_eq!(lambda.split_lambda(), (Scalar::ZERO, Scalar::ONE));
+    }
+
+    #[test]
+    fn test_invert() {
+        let x = Scalar::from_u64(12345);
+        assert_eq!(x * x.invert(), Scalar::ONE);
+        assert_eq!((-x).invert(), -x.invert());
+    }
+
+    #[test]
+    fn test_is_high() {
+        let half = scalar(&(order() >> 1u32));
+        assert!(!half.is_high());
+        assert!((half + Scalar::ONE).is_high());
+        assert!(!Scalar::ONE.is_high());
+    }
+}
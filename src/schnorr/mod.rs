@@ -0,0 +1,266 @@
+use std::fmt::{Display, Formatter};
+
+use crate::fe256::Fe256;
+use crate::hash::tagged_hash;
+use crate::hex;
+use crate::jacobian::JacobianPoint;
+use crate::s256point::{S256Point, SecError};
+use crate::scalar::Scalar;
+
+/// Errors that can occur when parsing a BIP340 Schnorr signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchnorrError {
+    /// The signature is not 64 bytes long
+    InvalidLength(usize),
+    /// The nonce x coordinate r is not smaller than the field prime p
+    ROutOfRange,
+    /// The s value is not smaller than the group order n
+    SOutOfRange,
+}
+
+// Formats the SchnorrError
+impl Display for SchnorrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchnorrError::InvalidLength(len) => {
+                write!(f, "Invalid Schnorr signature length {}", len)
+            }
+            SchnorrError::ROutOfRange => write!(f, "Schnorr signature r not below the field prime"),
+            SchnorrError::SOutOfRange => write!(f, "Schnorr signature s not below the group order"),
+        }
+    }
+}
+
+impl std::error::Error for SchnorrError {}
+
+/// SchnorrSignature is a BIP340 signature: the x coordinate of the nonce point R,
+/// whose y coordinate is even, and the value s
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchnorrSignature {
+    r: Fe256,
+    s: Scalar,
+}
+
+impl SchnorrSignature {
+    pub fn new(r: Fe256, s: Scalar) -> SchnorrSignature {
+        SchnorrSignature { r, s }
+    }
+
+    pub fn get_r(&self) -> &Fe256 {
+        &self.r
+    }
+
+    pub fn get_s(&self) -> &Scalar {
+        &self.s
+    }
+
+    /// Parses a signature from its 64 bytes, r followed by s
+    /// # Arguments
+    /// * `bytes` - The serialized signature
+    /// # Returns
+    /// * `Result<SchnorrSignature, SchnorrError>` - The signature, or why it is not valid
+    pub fn parse(bytes: &[u8]) -> Result<SchnorrSignature, SchnorrError> {
+        if bytes.len() != 64 {
+            return Err(SchnorrError::InvalidLength(bytes.len()));
+        }
+        let r =
+            Fe256::from_bytes(bytes[..32].try_into().unwrap()).ok_or(SchnorrError::ROutOfRange)?;
+        let s =
+            Scalar::from_bytes(bytes[32..].try_into().unwrap()).ok_or(SchnorrError::SOutOfRange)?;
+        Ok(SchnorrSignature { r, s })
+    }
+
+    /// Serializes the signature as r followed by s, 32 bytes each
+    pub fn serialize(&self) -> [u8; 64] {
+        let mut result = [0u8; 64];
+        result[..32].copy_from_slice(&self.r.to_bytes());
+        result[32..].copy_from_slice(&self.s.to_bytes());
+        result
+    }
+}
+
+// Formats the SchnorrSignature as the hex of its 64 bytes
+impl Display for SchnorrSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(&self.serialize()))
+    }
+}
+
+/// XOnlyPublicKey is a BIP340 public key, given only by its x coordinate
+/// It stands for the point with that x coordinate and an even y coordinate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XOnlyPublicKey(S256Point);
+
+impl XOnlyPublicKey {
+    /// Returns the x-only key of a point, along with whether the point has an odd y
+    /// coordinate, in which case the key stands for its negation
+    /// # Arguments
+    /// * `point` - The public key point
+    /// # Panics
+    /// If the point is the point at infinity
+    /// # Returns
+    /// * `(XOnlyPublicKey, bool)` - The key and whether y was odd
+    pub fn from_point(point: &S256Point) -> (XOnlyPublicKey, bool) {
+        let y = point
+            .get_y()
+            .expect("The point at infinity has no x-only key");
+        let odd = y.is_odd();
+        let point = if odd {
+            S256Point::from_coordinates(*point.get_x().unwrap(), -*y)
+        } else {
+            *point
+        };
+        (XOnlyPublicKey(point), odd)
+    }
+
+    /// Parses a key from its 32 byte x coordinate, lifting it to the point with even y
+    /// # Arguments
+    /// * `bytes` - The x coordinate
+    /// # Returns
+    /// * `Result<XOnlyPublicKey, SecError>` - The key, or why the bytes are not a valid x
+    pub fn parse(bytes: &[u8]) -> Result<XOnlyPublicKey, SecError> {
+        if bytes.len() != 32 {
+            return Err(SecError::InvalidLength(bytes.len()));
+        }
+        let mut sec = vec![0x02];
+        sec.extend_from_slice(bytes);
+        Ok(XOnlyPublicKey(S256Point::parse_sec(&sec)?))
+    }
+
+    /// Serializes the key as its 32 byte x coordinate
+    pub fn serialize(&self) -> [u8; 32] {
+        self.0.get_x().unwrap().to_bytes()
+    }
+
+    /// Returns the point with even y the key stands for
+    pub fn to_point(&self) -> S256Point {
+        self.0
+    }
+
+    /// Verifies a BIP340 Schnorr signature
+    /// # Arguments
+    /// * `message` - The signed message, of any length
+    /// * `signature` - The signature to verify
+    /// # Returns
+    /// * `bool` - True if the signature is valid, false otherwise
+    pub fn verify_schnorr(&self, message: &[u8], signature: &SchnorrSignature) -> bool {
+        let e = challenge(&signature.r, &self.serialize(), message);
+        // R = s * G - e * P
+        let total = JacobianPoint::multi_mul_with_generator(
+            &signature.s,
+            &[(-e, JacobianPoint::from_affine(&self.0))],
+        )
+        .to_affine();
+        match (total.get_x(), total.get_y()) {
+            (Some(x), Some(y)) => !y.is_odd() && *x == signature.r,
+            _ => false,
+        }
+    }
+}
+
+// Formats the XOnlyPublicKey as the hex of its x coordinate
+impl Display for XOnlyPublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(&self.serialize()))
+    }
+}
+
+/// Computes the BIP340 challenge e = hash(r || P || m) mod n
+pub(crate) fn challenge(r: &Fe256, public_key: &[u8; 32], message: &[u8]) -> Scalar {
+    let mut data = r.to_bytes().to_vec();
+    data.extend_from_slice(public_key);
+    data.extend_from_slice(message);
+    Scalar::from_bytes_reduced(tagged_hash("BIP0340/challenge", &data).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::private_key::PrivateKey;
+
+    /// The test vectors from the BIP340 reference, bip-0340/test-vectors.csv
+    const TEST_VECTORS: &str = include_str!("test-vectors.csv");
+
+    fn decode_hex(s: &str) -> Vec<u8> {
+        crate::hex::decode(&s.to_lowercase()).unwrap()
+    }
+
+    #[test]
+    fn test_vectors() {
+        for line in TEST_VECTORS.lines().skip(1) {
+            let fields: Vec<&str> = line.split(',').collect();
+            let (index, secret, public_key, aux_rand, message, signature, result) = (
+                fields[0], fields[1], fields[2], fields[3], fields[4], fields[5], fields[6],
+            );
+            let message = decode_hex(message);
+            let expected = result == "TRUE";
+
+            if !secret.is_empty() {
+                let private_key = PrivateKey::new(secret.to_lowercase().as_bytes());
+                let (x_only, _) = XOnlyPublicKey::from_point(private_key.get_public_key());
+                assert_eq!(
+                    x_only.serialize().to_vec(),
+                    decode_hex(public_key),
+                    "{}",
+                    index
+                );
+                let aux_rand: [u8; 32] = decode_hex(aux_rand).try_into().unwrap();
+                let signed = private_key.sign_schnorr(&message, &aux_rand);
+                assert_eq!(
+                    signed.serialize().to_vec(),
+                    decode_hex(signature),
+                    "{}",
+                    index
+                );
+            }
+
+            let valid = match (
+                XOnlyPublicKey::parse(&decode_hex(public_key)),
+                SchnorrSignature::parse(&decode_hex(signature)),
+            ) {
+                (Ok(key), Ok(signature)) => key.verify_schnorr(&message, &signature),
+                _ => false,
+            };
+            assert_eq!(valid, expected, "vector {}: {}", index, fields[7]);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            SchnorrSignature::parse(&[0u8; 63]),
+            Err(SchnorrError::InvalidLength(63))
+        );
+        assert_eq!(
+            SchnorrSignature::parse(&[0xff; 64]),
+            Err(SchnorrError::ROutOfRange)
+        );
+        let mut bytes = [0xff; 64];
+        bytes[..32].copy_from_slice(&[0u8; 32]);
+        assert_eq!(
+            SchnorrSignature::parse(&bytes),
+            Err(SchnorrError::SOutOfRange)
+        );
+        assert_eq!(
+            XOnlyPublicKey::parse(&[0x02; 33]),
+            Err(SecError::InvalidLength(33))
+        );
+    }
+
+    #[test]
+    fn test_from_point() {
+        // 3 * G has an even y, and -3 * G an odd one
+        let point = S256Point::generator() * Scalar::from_u64(3);
+        let (x_only, odd) = XOnlyPublicKey::from_point(&point);
+        assert!(!odd);
+        assert_eq!(x_only.to_point(), point);
+
+        let negated = S256Point::generator() * -Scalar::from_u64(3);
+        assert_eq!(XOnlyPublicKey::from_point(&negated), (x_only, true));
+        assert_eq!(XOnlyPublicKey::parse(&x_only.serialize()).unwrap(), x_only);
+        assert_eq!(
+            x_only.to_string(),
+            "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+        );
+    }
+}
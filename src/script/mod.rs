@@ -0,0 +1,541 @@
+use num_bigint::BigUint;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter, Result};
+use std::io::Read;
+use std::ops::Add;
+
+use crate::error::Error;
+use crate::hash::{hash160, Hash160};
+use crate::hex;
+use crate::op::{
+    self, op_name, OP_0, OP_1, OP_CHECKSIG, OP_CODESEPARATOR, OP_DUP, OP_ELSE, OP_ENDIF, OP_EQUAL,
+    OP_EQUALVERIFY, OP_HASH160, OP_IF, OP_NOTIF, OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4,
+};
+use crate::varint::{encode_varint, read_varint};
+
+/// Cmd is a single script command, either an opcode or an element of data to push
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cmd {
+    Op(u8),
+    Data(Vec<u8>),
+}
+
+/// Script is a list of commands run by the stack-based Script interpreter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    cmds: Vec<Cmd>,
+}
+
+impl Script {
+    /// Creates a new Script
+    /// # Arguments
+    /// * `cmds` - The commands of the script
+    pub fn new(cmds: Vec<Cmd>) -> Self {
+        Script { cmds }
+    }
+
+    /// Creates the pay-to-pubkey-hash locking script for a public key hash
+    /// # Arguments
+    /// * `h160` - The hash160 of the SEC encoded public key
+    pub fn p2pkh(h160: &Hash160) -> Self {
+        Script::new(vec![
+            Cmd::Op(OP_DUP),
+            Cmd::Op(OP_HASH160),
+            Cmd::Data(h160.as_bytes().to_vec()),
+            Cmd::Op(OP_EQUALVERIFY),
+            Cmd::Op(OP_CHECKSIG),
+        ])
+    }
+
+    /// Creates the pay-to-script-hash locking script for a redeem script hash
+    /// # Arguments
+    /// * `h160` - The hash160 of the serialized redeem script
+    pub fn p2sh(h160: &Hash160) -> Self {
+        Script::new(vec![
+            Cmd::Op(OP_HASH160),
+            Cmd::Data(h160.as_bytes().to_vec()),
+            Cmd::Op(OP_EQUAL),
+        ])
+    }
+
+    /// Creates the segwit locking script for a witness program: the version as OP_0 to
+    /// OP_16, followed by a push of the program
+    /// # Arguments
+    /// * `version` - The witness version, 0 to 16
+    /// * `program` - The witness program, such as a key hash for version 0
+    /// # Panics
+    /// If the version is above 16
+    pub fn witness_program(version: u8, program: &[u8]) -> Self {
+        let version = match version {
+            0 => OP_0,
+            1..=16 => OP_1 + version - 1,
+            _ => panic!("Invalid witness version {}", version),
+        };
+        Script::new(vec![Cmd::Op(version), Cmd::Data(program.to_vec())])
+    }
+
+    /// Returns the commands of the script
+    pub fn get_cmds(&self) -> &[Cmd] {
+        &self.cmds
+    }
+
+    /// Parses a script prefixed with its length as a varint
+    /// # Arguments
+    /// * `reader` - The reader to parse the script from
+    /// # Returns
+    /// * `Result<Script, Error>` - The script, `Error::Io` if the data ends early or
+    ///   `Error::InvalidScript` if a push runs past the end of the script
+    pub fn parse<R: Read>(reader: &mut R) -> std::result::Result<Script, Error> {
+        let len = read_varint(reader)?;
+        let mut bytes = Vec::new();
+        reader.take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(Error::Io(std::io::ErrorKind::UnexpectedEof));
+        }
+        Script::parse_raw(&bytes)
+    }
+
+    /// Parses a script without a length prefix
+    /// # Arguments
+    /// * `bytes` - The serialized commands
+    /// # Returns
+    /// * `Result<Script, Error>` - The script, or `Error::InvalidScript` if a push runs
+    ///   past the end of the script
+    pub fn parse_raw(bytes: &[u8]) -> std::result::Result<Script, Error> {
+        let mut cmds = Vec::new();
+        let mut rest = bytes;
+        while let Some((&current, tail)) = rest.split_first() {
+            rest = tail;
+            let len = match current {
+                1..=75 => current as usize,
+                OP_PUSHDATA1 => read_push_len(&mut rest, 1)?,
+                OP_PUSHDATA2 => read_push_len(&mut rest, 2)?,
+                OP_PUSHDATA4 => read_push_len(&mut rest, 4)?,
+                _ => {
+                    cmds.push(Cmd::Op(current));
+                    continue;
+                }
+            };
+            if rest.len() < len {
+                return Err(Error::InvalidScript);
+            }
+            let (data, tail) = rest.split_at(len);
+            cmds.push(Cmd::Data(data.to_vec()));
+            rest = tail;
+        }
+        Ok(Script { cmds })
+    }
+
+    /// Serializes the commands without a length prefix
+    /// Data is pushed with the shortest encoding for its length
+    pub fn raw_serialize(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        for cmd in &self.cmds {
+            match cmd {
+                Cmd::Op(op) => result.push(*op),
+                Cmd::Data(data) => {
+                    let len = data.len();
+                    if len <= 75 {
+                        result.push(len as u8);
+                    } else if len <= 0xff {
+                        result.push(OP_PUSHDATA1);
+                        result.push(len as u8);
+                    } else if len <= 0xffff {
+                        result.push(OP_PUSHDATA2);
+                        result.extend_from_slice(&(len as u16).to_le_bytes());
+                    } else {
+                        result.push(OP_PUSHDATA4);
+                        result.extend_from_slice(&(len as u32).to_le_bytes());
+                    }
+                    result.extend_from_slice(data);
+                }
+            }
+        }
+        result
+    }
+
+    /// Serializes the script prefixed with its length as a varint
+    pub fn serialize(&self) -> Vec<u8> {
+        let raw = self.raw_serialize();
+        let mut result = encode_varint(raw.len() as u64);
+        result.extend(raw);
+        result
+    }
+
+    /// Returns whether the script is OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+    pub fn is_p2pkh(&self) -> bool {
+        matches!(
+            self.cmds.as_slice(),
+            [Cmd::Op(OP_DUP), Cmd::Op(OP_HASH160), Cmd::Data(h160), Cmd::Op(OP_EQUALVERIFY), Cmd::Op(OP_CHECKSIG)]
+                if h160.len() == 20
+        )
+    }
+
+    /// Returns whether the script is OP_HASH160 <20 bytes> OP_EQUAL
+    pub fn is_p2sh(&self) -> bool {
+        matches!(
+            self.cmds.as_slice(),
+            [Cmd::Op(OP_HASH160), Cmd::Data(h160), Cmd::Op(OP_EQUAL)] if h160.len() == 20
+        )
+    }
+
+    /// Returns whether the script is OP_0 <20 bytes>, a native segwit P2WPKH output
+    pub fn is_p2wpkh(&self) -> bool {
+        matches!(
+            self.cmds.as_slice(),
+            [Cmd::Op(OP_0), Cmd::Data(h160)] if h160.len() == 20
+        )
+    }
+
+    /// Returns whether the script is OP_0 <32 bytes>, a native segwit P2WSH output
+    pub fn is_p2wsh(&self) -> bool {
+        matches!(
+            self.cmds.as_slice(),
+            [Cmd::Op(OP_0), Cmd::Data(h256)] if h256.len() == 32
+        )
+    }
+
+    /// Runs the script, usually a script_sig combined with a script_pubkey
+    /// # Arguments
+    /// * `z` - The signature hash checked by OP_CHECKSIG and OP_CHECKMULTISIG
+    /// # Returns
+    /// * `bool` - True if the script runs without failing and leaves a true value on top
+    pub fn evaluate(&self, z: &BigUint) -> bool {
+        self.evaluate_with(&|_, _| z.clone())
+    }
+
+    /// Runs the script, computing the signature hash from the sighash type of each signature
+    /// A data push followed by exactly OP_HASH160 <20 bytes> OP_EQUAL is handled as
+    /// pay-to-script-hash (BIP16): if the hash matches, the pushed redeem script runs next
+    /// # Arguments
+    /// * `sighash` - Computes the signature hash for a sighash type and, once an
+    ///   OP_CODESEPARATOR has run, the commands after the last one, which are signed
+    ///   instead of the whole script
+    /// # Returns
+    /// * `bool` - True if the script runs without failing and leaves a true value on top
+    pub fn evaluate_with(&self, sighash: &dyn Fn(u32, Option<&Script>) -> BigUint) -> bool {
+        self.run(sighash, true)
+    }
+
+    /// Runs the stack items of a witness combined with the script they satisfy
+    /// Unlike evaluate_with, a trailing OP_HASH160 <20 bytes> OP_EQUAL is an ordinary
+    /// hash check, since a witness script is not a redeem script
+    /// # Arguments
+    /// * `sighash` - Computes the signature hash, as for evaluate_with
+    /// # Returns
+    /// * `bool` - True if the script runs without failing and leaves a true value on top
+    pub fn evaluate_witness(&self, sighash: &dyn Fn(u32, Option<&Script>) -> BigUint) -> bool {
+        self.run(sighash, false)
+    }
+
+    /// Runs the commands, treating the pay-to-script-hash pattern as BIP16 if `p2sh` is set
+    fn run(&self, sighash: &dyn Fn(u32, Option<&Script>) -> BigUint, p2sh: bool) -> bool {
+        if !conditionals_balanced(&self.cmds) {
+            return false;
+        }
+        let mut cmds: VecDeque<Cmd> = self.cmds.iter().cloned().collect();
+        let mut stack = Vec::new();
+        let mut altstack = Vec::new();
+        let mut subscript = None;
+        while let Some(cmd) = cmds.pop_front() {
+            match cmd {
+                Cmd::Op(op) => {
+                    if op == OP_CODESEPARATOR {
+                        subscript = Some(Script::new(cmds.iter().cloned().collect()));
+                    }
+                    let sighash = |hash_type| sighash(hash_type, subscript.as_ref());
+                    if !op::execute(op, &mut stack, &mut altstack, &mut cmds, &sighash) {
+                        return false;
+                    }
+                }
+                Cmd::Data(data) => {
+                    if let Some(h160) = p2sh_hash(&cmds).filter(|_| p2sh) {
+                        if hash160(&data).as_bytes()[..] != h160[..] {
+                            return false;
+                        }
+                        let Ok(redeem_script) = Script::parse_raw(&data) else {
+                            return false;
+                        };
+                        if !conditionals_balanced(&redeem_script.cmds) {
+                            return false;
+                        }
+                        cmds.clear();
+                        cmds.extend(redeem_script.cmds);
+                        subscript = None;
+                    } else {
+                        stack.push(data);
+                    }
+                }
+            }
+        }
+        stack.pop().is_some_and(|top| op::is_true(&top))
+    }
+}
+
+/// Reads the little endian length of an OP_PUSHDATA1/2/4 push
+fn read_push_len(rest: &mut &[u8], size: usize) -> std::result::Result<usize, Error> {
+    if rest.len() < size {
+        return Err(Error::InvalidScript);
+    }
+    let (len_bytes, tail) = rest.split_at(size);
+    *rest = tail;
+    Ok(len_bytes
+        .iter()
+        .rev()
+        .fold(0usize, |len, &byte| (len << 8) | byte as usize))
+}
+
+/// Returns whether every OP_ELSE and OP_ENDIF belongs to an earlier OP_IF or OP_NOTIF, and
+/// every OP_IF and OP_NOTIF has an OP_ENDIF
+fn conditionals_balanced(cmds: &[Cmd]) -> bool {
+    let mut depth = 0usize;
+    for cmd in cmds {
+        match cmd {
+            Cmd::Op(OP_IF) | Cmd::Op(OP_NOTIF) => depth += 1,
+            Cmd::Op(OP_ELSE) if depth == 0 => return false,
+            Cmd::Op(OP_ENDIF) => match depth.checked_sub(1) {
+                Some(outer) => depth = outer,
+                None => return false,
+            },
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+/// Returns the hash if the commands are exactly OP_HASH160 <20 bytes> OP_EQUAL
+fn p2sh_hash(cmds: &VecDeque<Cmd>) -> Option<&[u8]> {
+    if cmds.len() != 3 || cmds[0] != Cmd::Op(OP_HASH160) || cmds[2] != Cmd::Op(OP_EQUAL) {
+        return None;
+    }
+    match &cmds[1] {
+        Cmd::Data(h160) if h160.len() == 20 => Some(h160),
+        _ => None,
+    }
+}
+
+// Combines two scripts, usually a script_sig followed by a script_pubkey
+impl Add for Script {
+    type Output = Script;
+
+    fn add(self, other: Script) -> Script {
+        let mut cmds = self.cmds;
+        cmds.extend(other.cmds);
+        Script { cmds }
+    }
+}
+
+// Formats the Script as opcode names and hex data separated by spaces
+impl Display for Script {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let cmds: Vec<String> = self
+            .cmds
+            .iter()
+            .map(|cmd| match cmd {
+                Cmd::Op(op) => match op_name(*op) {
+                    Some(name) => name.to_string(),
+                    None => format!("OP_[{}]", op),
+                },
+                Cmd::Data(data) => hex::encode(data),
+            })
+            .collect();
+        write!(f, "{}", cmds.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::op::{OP_2, OP_3, OP_5, OP_ADD, OP_CHECKMULTISIG};
+
+    const SCRIPT_SIG_HEX: &str = "6a47304402207899531a52d59a6de200179928ca900254a36b8dff8bb75f5f5d71b1cdc26125022008b422690b8461cb52c3cc30330b23d574351872b7c361e9aae3649071c1a7160121035d5c93d9ac96881f19ba1f686f15f009ded7c62efe85a872e6a19b43c15a2937";
+
+    #[test]
+    fn test_parse() {
+        let bytes = hex::decode(SCRIPT_SIG_HEX).unwrap();
+        let script = Script::parse(&mut bytes.as_slice()).unwrap();
+        let cmds = script.get_cmds();
+        assert_eq!(cmds.len(), 2);
+        assert_eq!(
+            cmds[0],
+            Cmd::Data(hex::decode("304402207899531a52d59a6de200179928ca900254a36b8dff8bb75f5f5d71b1cdc26125022008b422690b8461cb52c3cc30330b23d574351872b7c361e9aae3649071c1a71601").unwrap())
+        );
+        assert_eq!(
+            cmds[1],
+            Cmd::Data(
+                hex::decode("035d5c93d9ac96881f19ba1f686f15f009ded7c62efe85a872e6a19b43c15a2937")
+                    .unwrap()
+            )
+        );
+        assert_eq!(script.serialize(), bytes);
+    }
+
+    #[test]
+    fn test_pushdata() {
+        for len in [75, 76, 255, 256, 520, 0x10000] {
+            let script = Script::new(vec![Cmd::Data(vec![0xab; len]), Cmd::Op(OP_EQUAL)]);
+            let raw = script.raw_serialize();
+            let expected_prefix = match len {
+                0..=75 => 1,
+                76..=0xff => 2,
+                0x100..=0xffff => 3,
+                _ => 5,
+            };
+            assert_eq!(raw.len(), expected_prefix + len + 1);
+            assert_eq!(Script::parse_raw(&raw).unwrap(), script);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(Script::parse_raw(&[0x02, 0x01]), Err(Error::InvalidScript));
+        assert_eq!(
+            Script::parse_raw(&[OP_PUSHDATA2, 0x01]),
+            Err(Error::InvalidScript)
+        );
+        assert_eq!(
+            Script::parse(&mut [0x03u8, 0x76].as_slice()),
+            Err(Error::Io(std::io::ErrorKind::UnexpectedEof))
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let h160 = Hash160::from_bytes([0x11; 20]);
+        assert!(Script::p2pkh(&h160).is_p2pkh());
+        assert!(!Script::p2pkh(&h160).is_p2sh());
+        assert!(Script::p2sh(&h160).is_p2sh());
+        assert!(!Script::p2sh(&h160).is_p2pkh());
+        assert!(!Script::new(vec![
+            Cmd::Op(OP_HASH160),
+            Cmd::Data(vec![0x11; 19]),
+            Cmd::Op(OP_EQUAL)
+        ])
+        .is_p2sh());
+
+        let p2wpkh = Script::witness_program(0, h160.as_bytes());
+        assert_eq!(
+            hex::encode(&p2wpkh.raw_serialize()),
+            "00141111111111111111111111111111111111111111"
+        );
+        assert!(p2wpkh.is_p2wpkh());
+        assert!(!p2wpkh.is_p2wsh());
+        assert!(Script::witness_program(0, &[0x11; 32]).is_p2wsh());
+        assert!(!Script::witness_program(1, &[0x11; 32]).is_p2wsh());
+        assert_eq!(
+            Script::witness_program(16, &[0x11; 2]).get_cmds()[0],
+            Cmd::Op(op::OP_16)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let h160 = Hash160::from_bytes([0x11; 20]);
+        assert_eq!(
+            Script::p2pkh(&h160).to_string(),
+            "OP_DUP OP_HASH160 1111111111111111111111111111111111111111 OP_EQUALVERIFY OP_CHECKSIG"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_checksig() {
+        let z = BigUint::parse_bytes(
+            b"7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+            16,
+        )
+        .unwrap();
+        let sec = hex::decode("04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34").unwrap();
+        let sig = hex::decode("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601").unwrap();
+        let script_pubkey = Script::new(vec![Cmd::Data(sec), Cmd::Op(OP_CHECKSIG)]);
+        let script_sig = Script::new(vec![Cmd::Data(sig)]);
+        let combined = script_sig + script_pubkey;
+        assert!(combined.evaluate(&z));
+        assert!(!combined.evaluate(&(z + 1u32)));
+    }
+
+    #[test]
+    fn test_evaluate_p2sh() {
+        let redeem_script = Script::new(vec![
+            Cmd::Op(OP_2),
+            Cmd::Op(OP_3),
+            Cmd::Op(OP_ADD),
+            Cmd::Op(OP_5),
+            Cmd::Op(OP_EQUAL),
+        ]);
+        let redeem = redeem_script.raw_serialize();
+        let script_pubkey = Script::p2sh(&hash160(&redeem));
+        let script_sig = Script::new(vec![Cmd::Data(redeem.clone())]);
+        assert!((script_sig + script_pubkey).evaluate(&BigUint::ZERO));
+
+        let wrong_pubkey = Script::p2sh(&hash160(b"other"));
+        let script_sig = Script::new(vec![Cmd::Data(redeem)]);
+        assert!(!(script_sig + wrong_pubkey).evaluate(&BigUint::ZERO));
+    }
+
+    #[test]
+    fn test_evaluate_fails() {
+        let z = BigUint::ZERO;
+        assert!(!Script::default().evaluate(&z));
+        assert!(!Script::new(vec![Cmd::Op(crate::op::OP_0)]).evaluate(&z));
+        assert!(!Script::new(vec![Cmd::Op(crate::op::OP_RETURN), Cmd::Op(OP_2)]).evaluate(&z));
+        assert!(!Script::new(vec![Cmd::Op(OP_CHECKMULTISIG)]).evaluate(&z));
+    }
+
+    #[test]
+    fn test_evaluate_conditionals() {
+        let z = BigUint::ZERO;
+        // <condition> OP_IF OP_0 OP_ELSE OP_2 OP_IF OP_1 OP_ENDIF OP_ENDIF
+        let script = |condition| {
+            Script::new(vec![
+                Cmd::Op(condition),
+                Cmd::Op(OP_IF),
+                Cmd::Op(OP_0),
+                Cmd::Op(OP_ELSE),
+                Cmd::Op(OP_2),
+                Cmd::Op(OP_IF),
+                Cmd::Op(OP_1),
+                Cmd::Op(OP_ENDIF),
+                Cmd::Op(OP_ENDIF),
+            ])
+        };
+        assert!(!script(OP_1).evaluate(&z));
+        assert!(script(OP_0).evaluate(&z));
+
+        // OP_ELSE and OP_ENDIF without an OP_IF fail, even after a balanced block
+        assert!(!Script::new(vec![Cmd::Op(OP_1), Cmd::Op(OP_ENDIF)]).evaluate(&z));
+        assert!(!Script::new(vec![Cmd::Op(OP_1), Cmd::Op(OP_ELSE)]).evaluate(&z));
+        let unbalanced = script(OP_0) + Script::new(vec![Cmd::Op(OP_ENDIF)]);
+        assert!(!unbalanced.evaluate(&z));
+        assert!(!Script::new(vec![Cmd::Op(OP_1), Cmd::Op(OP_IF), Cmd::Op(OP_1)]).evaluate(&z));
+    }
+
+    #[test]
+    fn test_evaluate_codeseparator() {
+        // The signature commits to the commands after the last OP_CODESEPARATOR that ran
+        let z = BigUint::parse_bytes(
+            b"7c076ff316692a3d7eb3c3bb0f8b1488cf72e1afcd929e29307032997a838a3d",
+            16,
+        )
+        .unwrap();
+        let sec = hex::decode("04887387e452b8eacc4acfde10d9aaf7f6d9a0f975aabb10d006e4da568744d06c61de6d95231cd89026e286df3b6ae4a894a3378e393e93a0f45b666329a0ae34").unwrap();
+        let sig = hex::decode("3045022000eff69ef2b1bd93a66ed5219add4fb51e11a840f404876325a1e8ffe0529a2c022100c7207fee197d27c618aea621406f6bf5ef6fca38681d82b2f06fddbdce6feab601").unwrap();
+        let tail = vec![Cmd::Op(OP_ENDIF), Cmd::Data(sec), Cmd::Op(OP_CHECKSIG)];
+        for (condition, expected) in [(OP_1, Some(Script::new(tail.clone()))), (OP_0, None)] {
+            let mut cmds = vec![
+                Cmd::Data(sig.clone()),
+                Cmd::Op(condition),
+                Cmd::Op(OP_IF),
+                Cmd::Op(OP_CODESEPARATOR),
+            ];
+            cmds.extend(tail.clone());
+            let script = Script::new(cmds);
+            assert!(script.evaluate_with(&|_, subscript| {
+                if subscript == expected.as_ref() {
+                    z.clone()
+                } else {
+                    BigUint::ZERO
+                }
+            }));
+        }
+    }
+}
@@ -1,35 +1,154 @@
 use num_bigint::BigUint;
 use std::fmt::Display;
 
+use crate::error::{parse_hex, Error};
+use crate::s256point::S256Point;
+use crate::scalar::Scalar;
+
+/// Errors that can occur when parsing a DER encoded signature
+/// The rules are the strict ones from BIP66
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerError {
+    /// The signature is shorter than 8 bytes or longer than 72 bytes
+    InvalidLength(usize),
+    /// The signature does not start with the 0x30 sequence marker
+    InvalidSequenceMarker(u8),
+    /// An integer does not start with the 0x02 integer marker
+    InvalidIntegerMarker(u8),
+    /// A length byte does not match the data that follows it
+    BadLength,
+    /// There are bytes after the end of the sequence
+    TrailingData,
+    /// An integer has zero length
+    ZeroLengthInteger,
+    /// An integer has its sign bit set
+    NegativeInteger,
+    /// An integer is padded with an unnecessary zero byte
+    NonMinimalInteger,
+    /// An integer is not smaller than the group order n
+    IntegerOutOfRange,
+}
+
+// Formats the DerError
+impl Display for DerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DerError::InvalidLength(len) => write!(f, "Invalid DER signature length {}", len),
+            DerError::InvalidSequenceMarker(marker) => {
+                write!(f, "Invalid DER sequence marker 0x{:02x}", marker)
+            }
+            DerError::InvalidIntegerMarker(marker) => {
+                write!(f, "Invalid DER integer marker 0x{:02x}", marker)
+            }
+            DerError::BadLength => write!(f, "DER length byte does not match the data"),
+            DerError::TrailingData => write!(f, "Trailing data after DER signature"),
+            DerError::ZeroLengthInteger => write!(f, "Zero length DER integer"),
+            DerError::NegativeInteger => write!(f, "Negative DER integer"),
+            DerError::NonMinimalInteger => write!(f, "Non minimal DER integer encoding"),
+            DerError::IntegerOutOfRange => write!(f, "DER integer not below the group order"),
+        }
+    }
+}
+
+impl std::error::Error for DerError {}
+
 #[derive(Debug, Clone)]
 pub struct Signature {
-    r: BigUint,
-    s: BigUint,
+    r: Scalar,
+    s: Scalar,
 }
 
 impl Signature {
-    pub fn new(r: BigUint, s: BigUint) -> Signature {
+    pub fn new(r: Scalar, s: Scalar) -> Signature {
         Signature { r, s }
     }
 
+    /// Creates a signature from hex encoded r and s values
+    /// # Panics
+    /// If r or s is not valid hex or not smaller than n
     pub fn from_bytes(r: &[u8], s: &[u8]) -> Signature {
-        let r = BigUint::parse_bytes(r, 16).unwrap();
-        let s = BigUint::parse_bytes(s, 16).unwrap();
-        Signature { r, s }
+        Self::try_from_bytes(r, s).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Creates a signature from hex encoded r and s values
+    /// # Arguments
+    /// * `r` - The r value, hex encoded
+    /// * `s` - The s value, hex encoded
+    /// # Returns
+    /// * `Result<Signature, Error>` - The signature, `Error::InvalidHex` or
+    ///   `Error::NumberOutOfRange` if a value is not smaller than n
+    pub fn try_from_bytes(r: &[u8], s: &[u8]) -> Result<Signature, Error> {
+        Ok(Signature {
+            r: parse_scalar(r)?,
+            s: parse_scalar(s)?,
+        })
     }
 
-    pub fn get_r(&self) -> &BigUint {
+    pub fn get_r(&self) -> &Scalar {
         &self.r
     }
 
-    pub fn get_s(&self) -> &BigUint {
+    pub fn get_s(&self) -> &Scalar {
         &self.s
     }
+
+    /// Serializes the signature in DER format
+    /// # Returns
+    /// * `Vec<u8>` - The DER encoded signature
+    pub fn der(&self) -> Vec<u8> {
+        let r = encode_integer(&self.r);
+        let s = encode_integer(&self.s);
+        let mut result = vec![0x30, (r.len() + s.len()) as u8];
+        result.extend(r);
+        result.extend(s);
+        result
+    }
+
+    /// Parses a signature from its DER format following the strict BIP66 rules
+    /// # Arguments
+    /// * `der` - The DER encoded signature, without a sighash byte
+    /// # Returns
+    /// * `Result<Signature, DerError>` - The parsed signature, or the rule it breaks
+    pub fn parse_der(der: &[u8]) -> Result<Signature, DerError> {
+        // 0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S]
+        if der.len() < 8 || der.len() > 72 {
+            return Err(DerError::InvalidLength(der.len()));
+        }
+        if der[0] != 0x30 {
+            return Err(DerError::InvalidSequenceMarker(der[0]));
+        }
+        let total_len = der[1] as usize + 2;
+        if total_len > der.len() {
+            return Err(DerError::BadLength);
+        }
+        if total_len < der.len() {
+            return Err(DerError::TrailingData);
+        }
+
+        let r_len = der[3] as usize;
+        if 5 + r_len >= der.len() {
+            return Err(DerError::BadLength);
+        }
+        let s_len = der[5 + r_len] as usize;
+        if r_len + s_len + 6 != der.len() {
+            return Err(DerError::BadLength);
+        }
+
+        let r = parse_integer(der[2], &der[4..4 + r_len])?;
+        let s = parse_integer(der[4 + r_len], &der[6 + r_len..])?;
+
+        Ok(Signature { r, s })
+    }
 }
 
 impl Display for Signature {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Signature({},{})", self.r, self.s)
+        write!(
+            f,
+            "Signature({},{})",
+            self.r.to_biguint(),
+            self.s.to_biguint()
+        )
     }
 }
 
@@ -38,3 +157,164 @@ impl PartialEq for Signature {
         self.r == other.r && self.s == other.s
     }
 }
+
+/// Parses a hex encoded value that must be smaller than n
+fn parse_scalar(hex: &[u8]) -> Result<Scalar, Error> {
+    let number = parse_hex(hex)?;
+    Scalar::from_biguint(&number).ok_or_else(|| Error::NumberOutOfRange {
+        number,
+        prime: BigUint::parse_bytes(S256Point::BASE_ORDER, 16).unwrap(),
+    })
+}
+
+/// Encodes a scalar as a DER integer, prepending a zero byte if the high bit is set
+fn encode_integer(n: &Scalar) -> Vec<u8> {
+    let bytes = n.to_bytes();
+    let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(31);
+    let mut bytes = bytes[start..].to_vec();
+    if bytes[0] & 0x80 != 0 {
+        bytes.insert(0, 0x00);
+    }
+    let mut result = vec![0x02, bytes.len() as u8];
+    result.extend(bytes);
+    result
+}
+
+/// Parses the value of a DER integer after checking its marker and encoding
+fn parse_integer(marker: u8, value: &[u8]) -> Result<Scalar, DerError> {
+    if marker != 0x02 {
+        return Err(DerError::InvalidIntegerMarker(marker));
+    }
+    if value.is_empty() {
+        return Err(DerError::ZeroLengthInteger);
+    }
+    if value[0] & 0x80 != 0 {
+        return Err(DerError::NegativeInteger);
+    }
+    if value.len() > 1 && value[0] == 0x00 && value[1] & 0x80 == 0 {
+        return Err(DerError::NonMinimalInteger);
+    }
+
+    let value = value.strip_prefix(&[0x00]).unwrap_or(value);
+    if value.len() > 32 {
+        return Err(DerError::IntegerOutOfRange);
+    }
+    let mut bytes = [0u8; 32];
+    bytes[32 - value.len()..].copy_from_slice(value);
+    Scalar::from_bytes(&bytes).ok_or(DerError::IntegerOutOfRange)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_hex(s: &[u8]) -> Vec<u8> {
+        crate::hex::decode(std::str::from_utf8(s).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_try_from_bytes() {
+        assert_eq!(
+            Signature::try_from_bytes(b"12", b"zz"),
+            Err(Error::InvalidHex(String::from("zz")))
+        );
+        assert_eq!(
+            Signature::try_from_bytes(b"12", b"34"),
+            Ok(Signature::new(
+                Scalar::from_u64(0x12),
+                Scalar::from_u64(0x34)
+            ))
+        );
+        assert!(matches!(
+            Signature::try_from_bytes(S256Point::BASE_ORDER, b"34"),
+            Err(Error::NumberOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn test_der() {
+        let signature = Signature::from_bytes(
+            b"37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6",
+            b"8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec",
+        );
+        let der = decode_hex(b"3045022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c60221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec");
+        assert_eq!(signature.der(), der);
+        assert_eq!(Signature::parse_der(&der).unwrap(), signature);
+    }
+
+    #[test]
+    fn test_der_roundtrip() {
+        let cases = vec![
+            (b"1".as_slice(), b"2".as_slice()),
+            (b"80", b"7f"),
+            (b"0", b"0"),
+            (
+                b"fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+                b"00000000000000000000003b78ce563f89a0ed9414f5aa28ad0d96d6795f9c63",
+            ),
+        ];
+
+        for (r, s) in cases {
+            let signature = Signature::from_bytes(r, s);
+            assert_eq!(Signature::parse_der(&signature.der()).unwrap(), signature);
+        }
+    }
+
+    #[test]
+    fn test_parse_der_errors() {
+        let valid = Signature::new(Scalar::from_u64(0x80), Scalar::ONE).der();
+        assert_eq!(
+            valid,
+            vec![0x30, 0x07, 0x02, 0x02, 0x00, 0x80, 0x02, 0x01, 0x01]
+        );
+
+        assert_eq!(
+            Signature::parse_der(&valid[..7]),
+            Err(DerError::InvalidLength(7))
+        );
+
+        let mut bad = valid.clone();
+        bad[0] = 0x31;
+        assert_eq!(
+            Signature::parse_der(&bad),
+            Err(DerError::InvalidSequenceMarker(0x31))
+        );
+
+        let mut bad = valid.clone();
+        bad[1] = 0x08;
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::BadLength));
+
+        let mut bad = valid.clone();
+        bad.push(0x00);
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::TrailingData));
+
+        let mut bad = valid.clone();
+        bad[6] = 0x03;
+        assert_eq!(
+            Signature::parse_der(&bad),
+            Err(DerError::InvalidIntegerMarker(0x03))
+        );
+
+        let mut bad = valid.clone();
+        bad[3] = 0x03;
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::BadLength));
+
+        // R without its zero padding is negative
+        let bad = vec![0x30, 0x06, 0x02, 0x01, 0x80, 0x02, 0x01, 0x01];
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::NegativeInteger));
+
+        // R with an unnecessary zero byte
+        let bad = vec![0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x01];
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::NonMinimalInteger));
+
+        // S with zero length
+        let bad = vec![0x30, 0x06, 0x02, 0x02, 0x00, 0x80, 0x02, 0x00];
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::ZeroLengthInteger));
+
+        // R equal to the group order n
+        let mut bad = vec![0x30, 0x26, 0x02, 0x21, 0x00];
+        bad.extend_from_slice(&decode_hex(S256Point::BASE_ORDER));
+        bad.extend_from_slice(&[0x02, 0x01, 0x01]);
+        assert_eq!(Signature::parse_der(&bad), Err(DerError::IntegerOutOfRange));
+    }
+}
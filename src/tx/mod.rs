@@ -0,0 +1,1612 @@
+use std::fmt::{Display, Formatter, Result};
+use std::io::Read;
+
+use crate::error::Error;
+use crate::hash::{hash160, hash256, sha256, Hash160, Hash256};
+use crate::op::OP_CODESEPARATOR;
+use crate::private_key::PrivateKey;
+use crate::script::{Cmd, Script};
+use crate::varint::{encode_varint, read_varint};
+
+/// Tx is a Bitcoin transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tx {
+    version: u32,
+    tx_ins: Vec<TxIn>,
+    tx_outs: Vec<TxOut>,
+    locktime: u32,
+}
+
+/// TxIn is a transaction input, spending an output of a previous transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxIn {
+    prev_tx: Hash256,
+    prev_index: u32,
+    script_sig: Script,
+    sequence: u32,
+    witness: Vec<Vec<u8>>,
+    prev_output: Option<TxOut>,
+}
+
+/// TxOut is a transaction output, locking an amount of satoshis to a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    amount: u64,
+    script_pubkey: Script,
+}
+
+impl Tx {
+    /// The largest valid amount, and sum of amounts, in satoshis: 21 million bitcoins
+    pub const MAX_MONEY: u64 = 21_000_000 * 100_000_000;
+
+    /// Sighash type committing to all inputs and outputs
+    pub const SIGHASH_ALL: u32 = 1;
+    /// Sighash type committing to all inputs and no outputs
+    pub const SIGHASH_NONE: u32 = 2;
+    /// Sighash type committing to all inputs and the output with the same index
+    pub const SIGHASH_SINGLE: u32 = 3;
+    /// Sighash flag committing only to the signed input
+    pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+    /// Creates a new transaction
+    /// # Arguments
+    /// * `version` - The transaction version
+    /// * `tx_ins` - The inputs
+    /// * `tx_outs` - The outputs
+    /// * `locktime` - The block height or timestamp before which the transaction is not valid
+    /// # Returns
+    /// * `Tx` - The transaction
+    pub fn new(version: u32, tx_ins: Vec<TxIn>, tx_outs: Vec<TxOut>, locktime: u32) -> Self {
+        Tx {
+            version,
+            tx_ins,
+            tx_outs,
+            locktime,
+        }
+    }
+
+    /// Returns the version of the transaction
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the inputs of the transaction
+    pub fn get_tx_ins(&self) -> &[TxIn] {
+        &self.tx_ins
+    }
+
+    /// Returns the inputs of the transaction for modification, e.g. to set their previous outputs
+    pub fn get_tx_ins_mut(&mut self) -> &mut [TxIn] {
+        &mut self.tx_ins
+    }
+
+    /// Returns the outputs of the transaction
+    pub fn get_tx_outs(&self) -> &[TxOut] {
+        &self.tx_outs
+    }
+
+    /// Returns the locktime of the transaction
+    pub fn get_locktime(&self) -> u32 {
+        self.locktime
+    }
+
+    /// Returns whether any input carries witness data, in which case the transaction is
+    /// serialized in the segwit format of BIP144
+    pub fn is_segwit(&self) -> bool {
+        self.tx_ins.iter().any(|tx_in| !tx_in.witness.is_empty())
+    }
+
+    /// Returns the hash of the transaction in the byte order used to display it
+    /// The witness data is left out, so signing the inputs of a segwit transaction
+    /// does not change it
+    /// # Returns
+    /// * `Hash256` - The reversed hash256 of the transaction serialized without witness data
+    pub fn hash(&self) -> Hash256 {
+        hash256(&self.serialize_legacy()).reversed()
+    }
+
+    /// Returns the transaction id, as shown by block explorers
+    /// # Returns
+    /// * `String` - The hex encoded hash of the transaction
+    pub fn id(&self) -> String {
+        self.hash().to_string()
+    }
+
+    /// Returns the hash of the transaction including its witness data, in the byte order
+    /// used to display it
+    /// It is the same as `hash` for a transaction without witness data
+    /// # Returns
+    /// * `Hash256` - The reversed hash256 of the serialized transaction
+    pub fn witness_hash(&self) -> Hash256 {
+        hash256(&self.serialize()).reversed()
+    }
+
+    /// Returns the witness transaction id (wtxid)
+    /// # Returns
+    /// * `String` - The hex encoded witness hash of the transaction
+    pub fn wtxid(&self) -> String {
+        self.witness_hash().to_string()
+    }
+
+    /// Parses a transaction from a reader, in the legacy format or the segwit format
+    /// whose marker and flag follow the version
+    /// # Arguments
+    /// * `reader` - The reader holding the serialized transaction
+    /// # Returns
+    /// * `Result<Tx, Error>` - The transaction, `Error::Io` if the data ends early, or
+    ///   `Error::InvalidSegwitFlag` if the segwit flag is not 1
+    pub fn parse<R: Read>(reader: &mut R) -> std::result::Result<Tx, Error> {
+        let version = read_u32(reader)?;
+        let mut num_inputs = read_varint(reader)?;
+        // A legacy transaction never has zero inputs, so a zero count is the segwit marker
+        let segwit = num_inputs == 0;
+        if segwit {
+            let mut flag = [0u8; 1];
+            reader.read_exact(&mut flag)?;
+            if flag[0] != 1 {
+                return Err(Error::InvalidSegwitFlag(flag[0]));
+            }
+            num_inputs = read_varint(reader)?;
+        }
+        let mut tx_ins = (0..num_inputs)
+            .map(|_| TxIn::parse(reader))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let num_outputs = read_varint(reader)?;
+        let tx_outs = (0..num_outputs)
+            .map(|_| TxOut::parse(reader))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if segwit {
+            for tx_in in &mut tx_ins {
+                let num_items = read_varint(reader)?;
+                tx_in.witness = (0..num_items)
+                    .map(|_| {
+                        let len = read_varint(reader)?;
+                        read_bytes(reader, len)
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+            }
+        }
+        let locktime = read_u32(reader)?;
+
+        Ok(Tx {
+            version,
+            tx_ins,
+            tx_outs,
+            locktime,
+        })
+    }
+
+    /// Serializes the transaction, in the segwit format if any input has witness data
+    /// # Returns
+    /// * `Vec<u8>` - The serialized transaction
+    pub fn serialize(&self) -> Vec<u8> {
+        if !self.is_segwit() {
+            return self.serialize_legacy();
+        }
+        let mut result = self.version.to_le_bytes().to_vec();
+        // The marker and the flag
+        result.extend_from_slice(&[0x00, 0x01]);
+        self.serialize_inputs_and_outputs(&mut result);
+        for tx_in in &self.tx_ins {
+            result.extend(encode_varint(tx_in.witness.len() as u64));
+            for item in &tx_in.witness {
+                result.extend(encode_varint(item.len() as u64));
+                result.extend_from_slice(item);
+            }
+        }
+        result.extend_from_slice(&self.locktime.to_le_bytes());
+        result
+    }
+
+    /// Returns the fee of the transaction
+    /// # Returns
+    /// * `Result<i64, Error>` - The amount of the spent outputs minus the amount of the outputs,
+    ///   `Error::MissingPrevOutput` if a spent output is not known, or
+    ///   `Error::AmountOutOfRange` if the spent outputs or the outputs add up to more than
+    ///   21 million bitcoins
+    pub fn fee(&self) -> std::result::Result<i64, Error> {
+        let mut input_sum = 0u64;
+        for index in 0..self.tx_ins.len() {
+            input_sum = add_amount(input_sum, self.prev_output(index)?.amount)?;
+        }
+        let mut output_sum = 0u64;
+        for tx_out in &self.tx_outs {
+            output_sum = add_amount(output_sum, tx_out.amount)?;
+        }
+        // Both sums are at most MAX_MONEY, so they fit an i64
+        Ok(input_sum as i64 - output_sum as i64)
+    }
+
+    /// Computes the legacy signature hash of an input
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `hash_type` - The sighash type, e.g. `Tx::SIGHASH_ALL`
+    /// # Returns
+    /// * `Result<Hash256, Error>` - The hash to sign, whose `to_biguint` is the `z` of the signature.
+    ///   Fails if the input or its previous output is missing, or with `Error::InvalidScript`
+    ///   if the input spends a pay-to-script-hash output without a valid redeem script
+    pub fn sig_hash(&self, index: usize, hash_type: u32) -> std::result::Result<Hash256, Error> {
+        let script_code = self.script_code(index)?.ok_or(Error::InvalidScript)?;
+        Ok(self.sig_hash_with_script_code(index, &script_code, hash_type))
+    }
+
+    /// Signs a pay-to-pubkey-hash input with SIGHASH_ALL, replacing its script_sig
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `private_key` - The key of the public key hash locking the previous output
+    /// # Returns
+    /// * `Result<(), Error>` - Fails if the input or its previous output is missing, or with
+    ///   `Error::UnsupportedScript` if the previous output is not pay-to-pubkey-hash
+    pub fn sign_input(
+        &mut self,
+        index: usize,
+        private_key: &PrivateKey,
+    ) -> std::result::Result<(), Error> {
+        self.sign_input_with_hash_type(index, private_key, Tx::SIGHASH_ALL)
+    }
+
+    /// Signs a pay-to-pubkey-hash input, replacing its script_sig
+    /// The SEC format of the public key is the one whose hash locks the previous output
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `private_key` - The key of the public key hash locking the previous output
+    /// * `hash_type` - The sighash type, e.g. `Tx::SIGHASH_ALL`
+    /// # Returns
+    /// * `Result<(), Error>` - Fails if the input or its previous output is missing, or with
+    ///   `Error::UnsupportedScript` if the previous output is not pay-to-pubkey-hash or is
+    ///   locked to another key
+    pub fn sign_input_with_hash_type(
+        &mut self,
+        index: usize,
+        private_key: &PrivateKey,
+        hash_type: u32,
+    ) -> std::result::Result<(), Error> {
+        let script_pubkey = &self.prev_output(index)?.script_pubkey;
+        if !script_pubkey.is_p2pkh() {
+            return Err(Error::UnsupportedScript);
+        }
+        let public_key = private_key.get_public_key();
+        let h160 = &script_pubkey.get_cmds()[2];
+        let compressed = if *h160 == Cmd::Data(public_key.hash160(true).as_bytes().to_vec()) {
+            true
+        } else if *h160 == Cmd::Data(public_key.hash160(false).as_bytes().to_vec()) {
+            false
+        } else {
+            return Err(Error::UnsupportedScript);
+        };
+
+        let z = self
+            .sig_hash_with_script_code(index, script_pubkey, hash_type)
+            .to_biguint();
+        let mut sig = private_key.sign_biguint(z, None).der();
+        sig.push(hash_type as u8);
+        self.tx_ins[index].script_sig =
+            Script::new(vec![Cmd::Data(sig), Cmd::Data(public_key.sec(compressed))]);
+        Ok(())
+    }
+
+    /// Verifies that the script_sig of an input unlocks the output it spends
+    /// Inputs spending a version 0 witness program, directly or nested in P2SH, are
+    /// unlocked by their witness instead, checked with the BIP143 signature hash
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// # Returns
+    /// * `Result<bool, Error>` - Whether the input is valid, or an error if the input or its
+    ///   previous output is missing
+    pub fn verify_input(&self, index: usize) -> std::result::Result<bool, Error> {
+        if let Some(program) = self.witness_program(index)? {
+            return self.verify_witness(index, &program);
+        }
+        let Some(script_code) = self.script_code(index)? else {
+            return Ok(false);
+        };
+        let script_pubkey = self.prev_output(index)?.script_pubkey.clone();
+        let script = self.tx_ins[index].script_sig.clone() + script_pubkey;
+        Ok(script.evaluate_with(&|hash_type, subscript| {
+            self.sig_hash_with_script_code(index, subscript.unwrap_or(&script_code), hash_type)
+                .to_biguint()
+        }))
+    }
+
+    /// Verifies the whole transaction: the amounts must not add up to more than 21 million
+    /// bitcoins, the outputs must not spend more than the inputs and every input must be valid
+    /// # Returns
+    /// * `Result<bool, Error>` - Whether the transaction is valid, or
+    ///   `Error::MissingPrevOutput` if a spent output is not known
+    pub fn verify(&self) -> std::result::Result<bool, Error> {
+        match self.fee() {
+            Ok(fee) if fee >= 0 => {}
+            Ok(_) | Err(Error::AmountOutOfRange) => return Ok(false),
+            Err(err) => return Err(err),
+        }
+        for index in 0..self.tx_ins.len() {
+            if !self.verify_input(index)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Computes the BIP143 signature hash of a segwit version 0 input
+    /// The input spends P2WPKH or P2WSH, either directly or nested in P2SH, where its
+    /// script_sig must already push the witness program as the redeem script. For P2WSH
+    /// the last item of the witness must already be the witness script, which is signed
+    /// whole, as for a signature checked before any OP_CODESEPARATOR
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `hash_type` - The sighash type, e.g. `Tx::SIGHASH_ALL`
+    /// # Returns
+    /// * `Result<Hash256, Error>` - The hash to sign, whose `to_biguint` is the `z` of the signature.
+    ///   Fails if the input or its previous output is missing, with
+    ///   `Error::UnsupportedScript` if the input does not spend a version 0 witness program,
+    ///   or with `Error::InvalidScript` if a P2WSH input has no witness script
+    pub fn segwit_sig_hash(
+        &self,
+        index: usize,
+        hash_type: u32,
+    ) -> std::result::Result<Hash256, Error> {
+        let program = self
+            .witness_program(index)?
+            .ok_or(Error::UnsupportedScript)?;
+        let script_code = self
+            .segwit_script_code(index, &program)
+            .ok_or(Error::InvalidScript)?;
+        let amount = self.prev_output(index)?.amount;
+        Ok(self.sig_hash_bip143(index, &script_code, amount, hash_type))
+    }
+
+    /// Creates the signature of a segwit version 0 input, to be placed in its witness
+    /// The input must be set up as `segwit_sig_hash` describes
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `private_key` - The signing key
+    /// * `hash_type` - The sighash type, e.g. `Tx::SIGHASH_ALL`
+    /// # Returns
+    /// * `Result<Vec<u8>, Error>` - The DER signature followed by the sighash type byte, or
+    ///   the error of `segwit_sig_hash`
+    pub fn segwit_signature(
+        &self,
+        index: usize,
+        private_key: &PrivateKey,
+        hash_type: u32,
+    ) -> std::result::Result<Vec<u8>, Error> {
+        let z = self.segwit_sig_hash(index, hash_type)?.to_biguint();
+        let mut sig = private_key.sign_biguint(z, None).der();
+        sig.push(hash_type as u8);
+        Ok(sig)
+    }
+
+    /// Signs a P2WPKH or P2SH-P2WPKH input with SIGHASH_ALL, replacing its witness and
+    /// script_sig
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `private_key` - The key whose compressed public key hash locks the previous output
+    /// # Returns
+    /// * `Result<(), Error>` - Fails if the input or its previous output is missing, or with
+    ///   `Error::UnsupportedScript` if the previous output is neither P2WPKH nor the
+    ///   P2SH-P2WPKH output of the key
+    pub fn sign_segwit_input(
+        &mut self,
+        index: usize,
+        private_key: &PrivateKey,
+    ) -> std::result::Result<(), Error> {
+        self.sign_segwit_input_with_hash_type(index, private_key, Tx::SIGHASH_ALL)
+    }
+
+    /// Signs a P2WPKH or P2SH-P2WPKH input, replacing its witness and script_sig
+    /// The witness holds the signature and the compressed SEC public key, and the
+    /// script_sig is empty for P2WPKH or pushes the redeem script for P2SH-P2WPKH
+    /// # Arguments
+    /// * `index` - The index of the input
+    /// * `private_key` - The key whose compressed public key hash locks the previous output
+    /// * `hash_type` - The sighash type, e.g. `Tx::SIGHASH_ALL`
+    /// # Returns
+    /// * `Result<(), Error>` - Fails if the input or its previous output is missing, or with
+    ///   `Error::UnsupportedScript` if the previous output is neither the P2WPKH nor the
+    ///   P2SH-P2WPKH output of the key
+    pub fn sign_segwit_input_with_hash_type(
+        &mut self,
+        index: usize,
+        private_key: &PrivateKey,
+        hash_type: u32,
+    ) -> std::result::Result<(), Error> {
+        let public_key = private_key.get_public_key();
+        let script_pubkey = &self.prev_output(index)?.script_pubkey;
+        let witness_program = Script::witness_program(0, public_key.hash160(true).as_bytes());
+        let redeem_script = witness_program.raw_serialize();
+        let script_sig = if *script_pubkey == witness_program {
+            Script::default()
+        } else if *script_pubkey == Script::p2sh(&hash160(&redeem_script)) {
+            Script::new(vec![Cmd::Data(redeem_script)])
+        } else {
+            return Err(Error::UnsupportedScript);
+        };
+
+        self.tx_ins[index].script_sig = script_sig;
+        let sig = self.segwit_signature(index, private_key, hash_type)?;
+        self.tx_ins[index].witness = vec![sig, public_key.sec(true)];
+        Ok(())
+    }
+
+    /// Returns the output spent by an input
+    fn prev_output(&self, index: usize) -> std::result::Result<&TxOut, Error> {
+        self.tx_ins
+            .get(index)
+            .ok_or(Error::InputIndexOutOfRange(index))?
+            .prev_output
+            .as_ref()
+            .ok_or(Error::MissingPrevOutput(index))
+    }
+
+    /// Returns the script an input signs: the script_pubkey it spends or, for
+    /// pay-to-script-hash, the redeem script at the end of its script_sig
+    /// None if the redeem script is missing or cannot be parsed
+    fn script_code(&self, index: usize) -> std::result::Result<Option<Script>, Error> {
+        let script_pubkey = &self.prev_output(index)?.script_pubkey;
+        if !script_pubkey.is_p2sh() {
+            return Ok(Some(script_pubkey.clone()));
+        }
+        match self.tx_ins[index].script_sig.get_cmds().last() {
+            Some(Cmd::Data(redeem_script)) => Ok(Script::parse_raw(redeem_script).ok()),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the version 0 witness program an input spends, either as its script_pubkey
+    /// or as the redeem script its script_sig pushes for a P2SH output
+    /// None if the input spends neither P2WPKH nor P2WSH
+    fn witness_program(&self, index: usize) -> std::result::Result<Option<Vec<u8>>, Error> {
+        let script_pubkey = &self.prev_output(index)?.script_pubkey;
+        let script = if script_pubkey.is_p2sh() {
+            let [Cmd::Data(redeem_script)] = self.tx_ins[index].script_sig.get_cmds() else {
+                return Ok(None);
+            };
+            if Cmd::Data(hash160(redeem_script).as_bytes().to_vec()) != script_pubkey.get_cmds()[1]
+            {
+                return Ok(None);
+            }
+            match Script::parse_raw(redeem_script) {
+                Ok(script) => script,
+                Err(_) => return Ok(None),
+            }
+        } else {
+            script_pubkey.clone()
+        };
+        match script.get_cmds() {
+            [_, Cmd::Data(program)] if script.is_p2wpkh() || script.is_p2wsh() => {
+                Ok(Some(program.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the serialized script a segwit version 0 input signs: the P2PKH script of
+    /// the key hash for P2WPKH, or the witness script at the end of the witness for P2WSH
+    /// None if a P2WSH witness is empty
+    fn segwit_script_code(&self, index: usize, program: &[u8]) -> Option<Vec<u8>> {
+        match Hash160::from_slice(program) {
+            Some(h160) => Some(Script::p2pkh(&h160).raw_serialize()),
+            None => self.tx_ins[index].witness.last().cloned(),
+        }
+    }
+
+    /// Verifies the witness of an input spending a version 0 witness program
+    /// A P2WPKH witness must be a signature and a public key, and a P2WSH witness ends
+    /// with the witness script whose sha256 is the program. After an OP_CODESEPARATOR, the
+    /// signatures commit to the rest of the witness script, OP_CODESEPARATORs included
+    fn verify_witness(&self, index: usize, program: &[u8]) -> std::result::Result<bool, Error> {
+        let tx_in = &self.tx_ins[index];
+        let prev_output = self.prev_output(index)?;
+        // A native segwit input must leave its script_sig empty
+        if !prev_output.script_pubkey.is_p2sh() && !tx_in.script_sig.get_cmds().is_empty() {
+            return Ok(false);
+        }
+        let Some(script_code) = self.segwit_script_code(index, program) else {
+            return Ok(false);
+        };
+        let items = if program.len() == 20 {
+            if tx_in.witness.len() != 2 {
+                return Ok(false);
+            }
+            &tx_in.witness[..]
+        } else {
+            if sha256(&script_code).as_bytes()[..] != program[..] {
+                return Ok(false);
+            }
+            &tx_in.witness[..tx_in.witness.len() - 1]
+        };
+        let Ok(script) = Script::parse_raw(&script_code) else {
+            return Ok(false);
+        };
+
+        let stack = Script::new(items.iter().cloned().map(Cmd::Data).collect());
+        Ok((stack + script).evaluate_witness(&|hash_type, subscript| {
+            let script_code = match subscript {
+                Some(subscript) => &subscript.raw_serialize(),
+                None => &script_code,
+            };
+            self.sig_hash_bip143(index, script_code, prev_output.amount, hash_type)
+                .to_biguint()
+        }))
+    }
+
+    /// Computes the BIP143 signature hash of an input signing the given serialized script
+    /// It commits to the amount of the spent output, and hashes the outpoints, sequences
+    /// and outputs once for all inputs instead of serializing a modified transaction
+    fn sig_hash_bip143(
+        &self,
+        index: usize,
+        script_code: &[u8],
+        amount: u64,
+        hash_type: u32,
+    ) -> Hash256 {
+        let base_type = hash_type & 0x1f;
+        let anyone_can_pay = hash_type & Tx::SIGHASH_ANYONECANPAY != 0;
+        let zero = [0u8; 32];
+
+        let hash_prevouts = if anyone_can_pay {
+            zero
+        } else {
+            let outpoints: Vec<u8> = self.tx_ins.iter().flat_map(TxIn::outpoint).collect();
+            *hash256(&outpoints).as_bytes()
+        };
+        let hash_sequence =
+            if anyone_can_pay || base_type == Tx::SIGHASH_NONE || base_type == Tx::SIGHASH_SINGLE {
+                zero
+            } else {
+                let sequences: Vec<u8> = self
+                    .tx_ins
+                    .iter()
+                    .flat_map(|tx_in| tx_in.sequence.to_le_bytes())
+                    .collect();
+                *hash256(&sequences).as_bytes()
+            };
+        let hash_outputs = match base_type {
+            Tx::SIGHASH_NONE => zero,
+            Tx::SIGHASH_SINGLE => match self.tx_outs.get(index) {
+                Some(tx_out) => *hash256(&tx_out.serialize()).as_bytes(),
+                None => zero,
+            },
+            _ => {
+                let outputs: Vec<u8> = self.tx_outs.iter().flat_map(TxOut::serialize).collect();
+                *hash256(&outputs).as_bytes()
+            }
+        };
+
+        let tx_in = &self.tx_ins[index];
+        let mut data = self.version.to_le_bytes().to_vec();
+        data.extend_from_slice(&hash_prevouts);
+        data.extend_from_slice(&hash_sequence);
+        data.extend(tx_in.outpoint());
+        data.extend(encode_varint(script_code.len() as u64));
+        data.extend_from_slice(script_code);
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&tx_in.sequence.to_le_bytes());
+        data.extend_from_slice(&hash_outputs);
+        data.extend_from_slice(&self.locktime.to_le_bytes());
+        data.extend_from_slice(&hash_type.to_le_bytes());
+        hash256(&data)
+    }
+
+    /// Serializes the transaction in the legacy format, without witness data
+    fn serialize_legacy(&self) -> Vec<u8> {
+        let mut result = self.version.to_le_bytes().to_vec();
+        self.serialize_inputs_and_outputs(&mut result);
+        result.extend_from_slice(&self.locktime.to_le_bytes());
+        result
+    }
+
+    /// Appends the varint prefixed inputs and outputs, shared by both formats
+    fn serialize_inputs_and_outputs(&self, result: &mut Vec<u8>) {
+        result.extend(encode_varint(self.tx_ins.len() as u64));
+        for tx_in in &self.tx_ins {
+            result.extend(tx_in.serialize());
+        }
+        result.extend(encode_varint(self.tx_outs.len() as u64));
+        for tx_out in &self.tx_outs {
+            result.extend(tx_out.serialize());
+        }
+    }
+
+    /// Computes the legacy signature hash of an input signing the given script
+    /// The OP_CODESEPARATORs of the script are left out of the signed data
+    fn sig_hash_with_script_code(
+        &self,
+        index: usize,
+        script_code: &Script,
+        hash_type: u32,
+    ) -> Hash256 {
+        let base_type = hash_type & 0x1f;
+        let anyone_can_pay = hash_type & Tx::SIGHASH_ANYONECANPAY != 0;
+        if base_type == Tx::SIGHASH_SINGLE && index >= self.tx_outs.len() {
+            // Bitcoin Core signs the number one instead of failing
+            let mut one = [0u8; 32];
+            one[0] = 1;
+            return Hash256::from_bytes(one);
+        }
+
+        let script_code = Script::new(
+            script_code
+                .get_cmds()
+                .iter()
+                .filter(|cmd| **cmd != Cmd::Op(OP_CODESEPARATOR))
+                .cloned()
+                .collect(),
+        );
+        let tx_ins = self
+            .tx_ins
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !anyone_can_pay || *i == index)
+            .map(|(i, tx_in)| {
+                if i == index {
+                    TxIn::new(
+                        tx_in.prev_tx,
+                        tx_in.prev_index,
+                        script_code.clone(),
+                        tx_in.sequence,
+                    )
+                } else if base_type == Tx::SIGHASH_NONE || base_type == Tx::SIGHASH_SINGLE {
+                    TxIn::new(tx_in.prev_tx, tx_in.prev_index, Script::default(), 0)
+                } else {
+                    TxIn::new(
+                        tx_in.prev_tx,
+                        tx_in.prev_index,
+                        Script::default(),
+                        tx_in.sequence,
+                    )
+                }
+            })
+            .collect();
+        let tx_outs = match base_type {
+            Tx::SIGHASH_NONE => Vec::new(),
+            Tx::SIGHASH_SINGLE => self.tx_outs[..=index]
+                .iter()
+                .enumerate()
+                .map(|(i, tx_out)| {
+                    if i == index {
+                        tx_out.clone()
+                    } else {
+                        TxOut::new(u64::MAX, Script::default())
+                    }
+                })
+                .collect(),
+            _ => self.tx_outs.clone(),
+        };
+
+        let mut data = Tx::new(self.version, tx_ins, tx_outs, self.locktime).serialize();
+        data.extend_from_slice(&hash_type.to_le_bytes());
+        hash256(&data)
+    }
+}
+
+impl TxIn {
+    /// Creates a new transaction input
+    /// # Arguments
+    /// * `prev_tx` - The hash of the previous transaction, in display byte order
+    /// * `prev_index` - The index of the spent output in the previous transaction
+    /// * `script_sig` - The unlocking script
+    /// * `sequence` - The sequence number
+    /// # Returns
+    /// * `TxIn` - The transaction input
+    pub fn new(prev_tx: Hash256, prev_index: u32, script_sig: Script, sequence: u32) -> Self {
+        TxIn {
+            prev_tx,
+            prev_index,
+            script_sig,
+            sequence,
+            witness: Vec::new(),
+            prev_output: None,
+        }
+    }
+
+    /// Returns the hash of the previous transaction, in display byte order
+    pub fn get_prev_tx(&self) -> &Hash256 {
+        &self.prev_tx
+    }
+
+    /// Returns the index of the spent output in the previous transaction
+    pub fn get_prev_index(&self) -> u32 {
+        self.prev_index
+    }
+
+    /// Returns the unlocking script
+    pub fn get_script_sig(&self) -> &Script {
+        &self.script_sig
+    }
+
+    /// Returns the sequence number
+    pub fn get_sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    /// Returns the witness items of the input, empty for a legacy input
+    pub fn get_witness(&self) -> &[Vec<u8>] {
+        &self.witness
+    }
+
+    /// Sets the witness items of the input, e.g. the signatures and witness script of a
+    /// P2WSH input
+    /// # Arguments
+    /// * `witness` - The witness items, bottom of the stack first
+    pub fn set_witness(&mut self, witness: Vec<Vec<u8>>) {
+        self.witness = witness;
+    }
+
+    /// Returns the output spent by the input, if known
+    pub fn get_prev_output(&self) -> Option<&TxOut> {
+        self.prev_output.as_ref()
+    }
+
+    /// Sets the output spent by the input, needed to sign and verify it
+    /// # Arguments
+    /// * `prev_output` - The output at `prev_index` of the previous transaction
+    pub fn set_prev_output(&mut self, prev_output: TxOut) {
+        self.prev_output = Some(prev_output);
+    }
+
+    /// Parses a transaction input from a reader
+    /// The witness is not part of the input data, so it is left empty
+    /// # Arguments
+    /// * `reader` - The reader holding the serialized input
+    /// # Returns
+    /// * `Result<TxIn, Error>` - The input, or `Error::Io` if the data ends early
+    pub fn parse<R: Read>(reader: &mut R) -> std::result::Result<TxIn, Error> {
+        let mut prev_tx = [0u8; 32];
+        reader.read_exact(&mut prev_tx)?;
+        let prev_index = read_u32(reader)?;
+        let script_sig = Script::parse(reader)?;
+        let sequence = read_u32(reader)?;
+
+        Ok(TxIn {
+            prev_tx: Hash256::from_bytes(prev_tx).reversed(),
+            prev_index,
+            script_sig,
+            sequence,
+            witness: Vec::new(),
+            prev_output: None,
+        })
+    }
+
+    /// Serializes the transaction input, without its witness
+    /// # Returns
+    /// * `Vec<u8>` - The serialized input
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = self.outpoint();
+        result.extend(self.script_sig.serialize());
+        result.extend_from_slice(&self.sequence.to_le_bytes());
+        result
+    }
+
+    /// Serializes the outpoint the input spends: the previous transaction hash in
+    /// serialization byte order followed by the output index
+    fn outpoint(&self) -> Vec<u8> {
+        let mut result = self.prev_tx.reversed().as_bytes().to_vec();
+        result.extend_from_slice(&self.prev_index.to_le_bytes());
+        result
+    }
+}
+
+impl TxOut {
+    /// Creates a new transaction output
+    /// # Arguments
+    /// * `amount` - The amount in satoshis
+    /// * `script_pubkey` - The locking script
+    /// # Returns
+    /// * `TxOut` - The transaction output
+    pub fn new(amount: u64, script_pubkey: Script) -> Self {
+        TxOut {
+            amount,
+            script_pubkey,
+        }
+    }
+
+    /// Returns the amount in satoshis
+    pub fn get_amount(&self) -> u64 {
+        self.amount
+    }
+
+    /// Returns the locking script
+    pub fn get_script_pubkey(&self) -> &Script {
+        &self.script_pubkey
+    }
+
+    /// Parses a transaction output from a reader
+    /// # Arguments
+    /// * `reader` - The reader holding the serialized output
+    /// # Returns
+    /// * `Result<TxOut, Error>` - The output, or `Error::Io` if the data ends early
+    pub fn parse<R: Read>(reader: &mut R) -> std::result::Result<TxOut, Error> {
+        let mut amount = [0u8; 8];
+        reader.read_exact(&mut amount)?;
+        let script_pubkey = Script::parse(reader)?;
+
+        Ok(TxOut {
+            amount: u64::from_le_bytes(amount),
+            script_pubkey,
+        })
+    }
+
+    /// Serializes the transaction output
+    /// # Returns
+    /// * `Vec<u8>` - The serialized output
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = self.amount.to_le_bytes().to_vec();
+        result.extend(self.script_pubkey.serialize());
+        result
+    }
+}
+
+// Formats the Tx
+impl Display for Tx {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "tx: {}", self.id())?;
+        writeln!(f, "version: {}", self.version)?;
+        writeln!(f, "tx_ins:")?;
+        for tx_in in &self.tx_ins {
+            writeln!(f, "{}", tx_in)?;
+        }
+        writeln!(f, "tx_outs:")?;
+        for tx_out in &self.tx_outs {
+            writeln!(f, "{}", tx_out)?;
+        }
+        write!(f, "locktime: {}", self.locktime)
+    }
+}
+
+// Formats the TxIn as the outpoint it spends
+impl Display for TxIn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:{}", self.prev_tx, self.prev_index)
+    }
+}
+
+// Formats the TxOut as its amount and script
+impl Display for TxOut {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:{}", self.amount, self.script_pubkey)
+    }
+}
+
+/// Adds an amount to a sum of amounts, failing if the result is above `Tx::MAX_MONEY`
+fn add_amount(sum: u64, amount: u64) -> std::result::Result<u64, Error> {
+    sum.checked_add(amount)
+        .filter(|&total| total <= Tx::MAX_MONEY)
+        .ok_or(Error::AmountOutOfRange)
+}
+
+/// Reads a little endian u32
+fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Reads a given number of bytes, without allocating them all before they are read
+fn read_bytes<R: Read>(reader: &mut R, len: u64) -> std::result::Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(Error::Io(std::io::ErrorKind::UnexpectedEof));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    const TX_HEX: &str = "0100000001813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762dd5423e332166702cb75f40df79fea1288ac19430600";
+
+    // Coinbase transaction of the genesis block
+    const GENESIS_TX_HEX: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+    fn parse_hex_tx(s: &str) -> Tx {
+        Tx::parse(&mut hex::decode(s).unwrap().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let tx = parse_hex_tx(TX_HEX);
+        assert_eq!(tx.get_version(), 1);
+        assert_eq!(tx.get_locktime(), 410393);
+
+        assert_eq!(tx.get_tx_ins().len(), 1);
+        let tx_in = &tx.get_tx_ins()[0];
+        assert_eq!(
+            tx_in.get_prev_tx().to_string(),
+            "d1c789a9c60383bf715f3f6ad9d14b91fe55f3deb369fe5d9280cb1a01793f81"
+        );
+        assert_eq!(tx_in.get_prev_index(), 0);
+        assert_eq!(
+            hex::encode(&tx_in.get_script_sig().raw_serialize()),
+            "483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278a"
+        );
+        assert_eq!(tx_in.get_sequence(), 0xfffffffe);
+
+        assert_eq!(tx.get_tx_outs().len(), 2);
+        assert_eq!(tx.get_tx_outs()[0].get_amount(), 32454049);
+        assert_eq!(
+            hex::encode(&tx.get_tx_outs()[0].get_script_pubkey().raw_serialize()),
+            "76a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac"
+        );
+        assert_eq!(tx.get_tx_outs()[1].get_amount(), 10011545);
+        assert_eq!(
+            hex::encode(&tx.get_tx_outs()[1].get_script_pubkey().raw_serialize()),
+            "76a9141c4bc762dd5423e332166702cb75f40df79fea1288ac"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for (tx_hex, id) in [
+            (
+                TX_HEX,
+                "452c629d67e41baec3ac6f04fe744b4b9617f8f859c63b3002f8684e7a4fee03",
+            ),
+            (
+                GENESIS_TX_HEX,
+                "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            ),
+        ] {
+            let tx = parse_hex_tx(tx_hex);
+            assert_eq!(hex::encode(&tx.serialize()), tx_hex);
+            assert_eq!(tx.id(), id);
+            assert_eq!(tx.hash().reversed(), hash256(&tx.serialize()));
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        let tx = parse_hex_tx(TX_HEX);
+        let rebuilt = Tx::new(
+            tx.get_version(),
+            tx.get_tx_ins()
+                .iter()
+                .map(|tx_in| {
+                    TxIn::new(
+                        *tx_in.get_prev_tx(),
+                        tx_in.get_prev_index(),
+                        tx_in.get_script_sig().clone(),
+                        tx_in.get_sequence(),
+                    )
+                })
+                .collect(),
+            tx.get_tx_outs()
+                .iter()
+                .map(|tx_out| TxOut::new(tx_out.get_amount(), tx_out.get_script_pubkey().clone()))
+                .collect(),
+            tx.get_locktime(),
+        );
+        assert_eq!(rebuilt, tx);
+    }
+
+    // TX_HEX with a second input spending output 1 of the same transaction
+    const TX2_HEX: &str = "0100000002813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1000000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278afeffffff813f79011acb80925dfe69b3def355fe914bd1d96a3f5f71bf8303c6a989c7d1010000006b483045022100ed81ff192e75a3fd2304004dcadb746fa5e24c5031ccfcf21320b0277457c98f02207a986d955c6e0cb35d446a89d3f56100f4d7f67801c31967743a9c8e10615bed01210349fc4e631e3624a545de3f89f5d8684c7b8138bd94bdd531d2e213bf016b278affffffff02a135ef01000000001976a914bc3b654dca7e56b04dca18f2566cdaf02e8d9ada88ac99c39800000000001976a9141c4bc762dd5423e332166702cb75f40df79fea1288ac19430600";
+
+    const PREV_SCRIPT_PUBKEY_HEX: &str = "76a914a802fc56c704ce87c42d7c92eb75e7896bdc41ae88ac";
+
+    fn with_prev_outputs(mut tx: Tx, amount: u64) -> Tx {
+        let script_pubkey =
+            Script::parse_raw(&hex::decode(PREV_SCRIPT_PUBKEY_HEX).unwrap()).unwrap();
+        for tx_in in tx.get_tx_ins_mut() {
+            tx_in.set_prev_output(TxOut::new(amount, script_pubkey.clone()));
+        }
+        tx
+    }
+
+    #[test]
+    fn test_sig_hash() {
+        let tx = with_prev_outputs(parse_hex_tx(TX_HEX), 42505594);
+        assert_eq!(
+            tx.sig_hash(0, Tx::SIGHASH_ALL).unwrap().to_string(),
+            "27e0c5994dec7824e56dec6b2fcb342eb7cdb0d0957c2fce9882f715e85d81a6"
+        );
+        assert_eq!(
+            tx.sig_hash(1, Tx::SIGHASH_ALL),
+            Err(Error::InputIndexOutOfRange(1))
+        );
+        assert_eq!(
+            parse_hex_tx(TX_HEX).sig_hash(0, Tx::SIGHASH_ALL),
+            Err(Error::MissingPrevOutput(0))
+        );
+
+        // Checked against rust-bitcoin
+        let tx = with_prev_outputs(parse_hex_tx(TX2_HEX), 42505594);
+        for (index, hash_type, expected) in [
+            (
+                0,
+                0x01,
+                "8c98d30e0ccf10fa95e84f9711c4879395543578fb972af6871f4f711ee7da2c",
+            ),
+            (
+                0,
+                0x02,
+                "1ab1cb54234f4a6f5df3fff845f7c2791eaef946839bb1f04f3674c0671ea047",
+            ),
+            (
+                0,
+                0x03,
+                "96881a0ef8d7e15f231cc814ed40884440c1292ddd9721b54a469095e389c30f",
+            ),
+            (
+                0,
+                0x81,
+                "d598d6849114bb8573a0f51184fa46fb3ff7516ef4ba7a27356d691ff579c20d",
+            ),
+            (
+                0,
+                0x82,
+                "9527316bc8187d8f4261f07b0aa3db63af9c4e9aa2cdc4e6a325b1b9b4c8ee21",
+            ),
+            (
+                0,
+                0x83,
+                "26a64b6eeaec01c8ce05b905cf5fe9479b5311d1893cf28d13bf6d265cfb119a",
+            ),
+            (
+                1,
+                0x01,
+                "cb78578cca1608df093cee435d8ea8197fdfd3fe2bde0846f9b8f5d76477135b",
+            ),
+            (
+                1,
+                0x02,
+                "5959a166d4c85213b960ef343ae62aa9c14925f4f9e180c403a71b4255aacb0e",
+            ),
+            (
+                1,
+                0x03,
+                "92cc51a0614984757e08e6c05ac4588c425195ad3eac691535e31b8eac73a24f",
+            ),
+            (
+                1,
+                0x81,
+                "4082c3619ed4af02d30880f80fbe787b6ce9181e00decc4ce623e051334bdf62",
+            ),
+            (
+                1,
+                0x82,
+                "638021aa63c0d0c7052d771be463cf3d010afd1d9b32b36ab9025269339acf6b",
+            ),
+            (
+                1,
+                0x83,
+                "25f63afd6f82cc3ba9a3d392a8aeed412933f56d312aa96c9cf567924fc2a6d7",
+            ),
+        ] {
+            assert_eq!(tx.sig_hash(index, hash_type).unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_sig_hash_single_without_output() {
+        let mut tx = parse_hex_tx(TX2_HEX);
+        let tx_in = tx.get_tx_ins()[1].clone();
+        tx.tx_ins.push(tx_in);
+        let tx = with_prev_outputs(tx, 42505594);
+        assert_eq!(
+            tx.sig_hash(2, Tx::SIGHASH_SINGLE).unwrap().to_string(),
+            "0100000000000000000000000000000000000000000000000000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let tx = with_prev_outputs(parse_hex_tx(TX_HEX), 42505594);
+        assert_eq!(tx.fee(), Ok(40000));
+        assert_eq!(tx.verify_input(0), Ok(true));
+        assert_eq!(tx.verify(), Ok(true));
+
+        // Outputs spending more than the inputs
+        let tx = with_prev_outputs(parse_hex_tx(TX_HEX), 1000);
+        assert_eq!(tx.verify(), Ok(false));
+
+        // The second input reuses the signature of the first, which commits to another input
+        let tx = with_prev_outputs(parse_hex_tx(TX2_HEX), 42505594);
+        assert_eq!(tx.verify_input(1), Ok(false));
+    }
+
+    #[test]
+    fn test_fee_out_of_range() {
+        // Two spent outputs near u64::MAX would overflow their sum
+        let tx = with_prev_outputs(parse_hex_tx(TX2_HEX), u64::MAX - 1);
+        assert_eq!(tx.fee(), Err(Error::AmountOutOfRange));
+        assert_eq!(tx.verify(), Ok(false));
+
+        let tx = with_prev_outputs(parse_hex_tx(TX_HEX), Tx::MAX_MONEY);
+        assert_eq!(tx.fee(), Ok(Tx::MAX_MONEY as i64 - 42465594));
+        let tx = with_prev_outputs(parse_hex_tx(TX_HEX), Tx::MAX_MONEY + 1);
+        assert_eq!(tx.fee(), Err(Error::AmountOutOfRange));
+
+        // An output near u64::MAX, which would wrap to a negative i64
+        let mut tx = with_prev_outputs(parse_hex_tx(TX_HEX), 42505594);
+        tx.tx_outs[1] = TxOut::new(u64::MAX, tx.tx_outs[1].script_pubkey.clone());
+        assert_eq!(tx.fee(), Err(Error::AmountOutOfRange));
+        assert_eq!(tx.verify(), Ok(false));
+    }
+
+    #[test]
+    fn test_sign_input() {
+        let private_key = PrivateKey::new(b"845fed");
+        let mut tx = parse_hex_tx("010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d00000000ffffffff02408af701000000001976a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac80969800000000001976a914507b27411ccf7f16f10297de6cef3f291623eddf88ac00000000");
+        for compressed in [true, false] {
+            let h160 = private_key.get_public_key().hash160(compressed);
+            tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(50000000, Script::p2pkh(&h160)));
+            tx.sign_input(0, &private_key).unwrap();
+            assert_eq!(tx.verify(), Ok(true));
+            assert_eq!(
+                tx.get_tx_ins()[0].get_script_sig().get_cmds()[1],
+                Cmd::Data(private_key.get_public_key().sec(compressed))
+            );
+        }
+
+        tx.sign_input_with_hash_type(0, &private_key, Tx::SIGHASH_NONE | Tx::SIGHASH_ANYONECANPAY)
+            .unwrap();
+        assert_eq!(tx.verify_input(0), Ok(true));
+
+        let h160 = private_key.get_public_key().hash160(true);
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(50000000, Script::p2sh(&h160)));
+        assert_eq!(
+            tx.sign_input(0, &private_key),
+            Err(Error::UnsupportedScript)
+        );
+
+        // A key whose hashes do not lock the output cannot sign it
+        let other_key = PrivateKey::new(b"845fee");
+        let h160 = other_key.get_public_key().hash160(true);
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(50000000, Script::p2pkh(&h160)));
+        assert_eq!(
+            tx.sign_input(0, &private_key),
+            Err(Error::UnsupportedScript)
+        );
+    }
+
+    #[test]
+    fn test_verify_codeseparator() {
+        // A legacy signature commits to the script after the last OP_CODESEPARATOR, with
+        // any OP_CODESEPARATOR left out
+        let private_key = PrivateKey::new(b"845fed");
+        let sec = private_key.get_public_key().sec(true);
+        let mut tx = parse_hex_tx("010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d00000000ffffffff02408af701000000001976a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac80969800000000001976a914507b27411ccf7f16f10297de6cef3f291623eddf88ac00000000");
+        let script_pubkey = Script::new(vec![
+            Cmd::Op(crate::op::OP_NOP),
+            Cmd::Op(OP_CODESEPARATOR),
+            Cmd::Data(sec.clone()),
+            Cmd::Op(crate::op::OP_CHECKSIG),
+            Cmd::Op(OP_CODESEPARATOR),
+        ]);
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(50000000, script_pubkey.clone()));
+
+        let sign = |tx: &mut Tx, script_code: &Script| {
+            let z = tx
+                .sig_hash_with_script_code(0, script_code, Tx::SIGHASH_ALL)
+                .to_biguint();
+            let mut sig = private_key.sign_biguint(z, None).der();
+            sig.push(Tx::SIGHASH_ALL as u8);
+            tx.get_tx_ins_mut()[0].script_sig = Script::new(vec![Cmd::Data(sig)]);
+        };
+        let subscript = Script::new(script_pubkey.get_cmds()[2..4].to_vec());
+        sign(&mut tx, &subscript);
+        assert_eq!(tx.verify_input(0), Ok(true));
+        sign(&mut tx, &script_pubkey);
+        assert_eq!(tx.verify_input(0), Ok(false));
+    }
+
+    // The BIP143 native P2WPKH example, whose first input spends a P2PK output
+    const P2WPKH_UNSIGNED_HEX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const P2WPKH_SIGNED_HEX: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+
+    // The BIP143 P2SH-P2WPKH example
+    const P2SH_P2WPKH_UNSIGNED_HEX: &str = "0100000001db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a54770100000000feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac92040000";
+    const P2SH_P2WPKH_SIGNED_HEX: &str = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000";
+
+    // The BIP143 P2SH-P2WSH 6-of-6 multisig example, signed with the six sighash types
+    const P2SH_P2WSH_UNSIGNED_HEX: &str = "010000000136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000000ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac00000000";
+    const P2SH_P2WSH_SIGNED_HEX: &str = "0100000000010136641869ca081e70f394c6948e8af409e18b619df2ed74aa106c1ca29787b96e0100000023220020a16b5755f7f6f96dbd65f5f0d6ab9418b89af4b1f14a1bb8a09062c35f0dcb54ffffffff0200e9a435000000001976a914389ffce9cd9ae88dcc0631e88a821ffdbe9bfe2688acc0832f05000000001976a9147480a33f950689af511e6e84c138dbbd3c3ee41588ac080047304402206ac44d672dac41f9b00e28f4df20c52eeb087207e8d758d76d92c6fab3b73e2b0220367750dbbe19290069cba53d096f44530e4f98acaa594810388cf7409a1870ce01473044022068c7946a43232757cbdf9176f009a928e1cd9a1a8c212f15c1e11ac9f2925d9002205b75f937ff2f9f3c1246e547e54f62e027f64eefa2695578cc6432cdabce271502473044022059ebf56d98010a932cf8ecfec54c48e6139ed6adb0728c09cbe1e4fa0915302e022007cd986c8fa870ff5d2b3a89139c9fe7e499259875357e20fcbb15571c76795403483045022100fbefd94bd0a488d50b79102b5dad4ab6ced30c4069f1eaa69a4b5a763414067e02203156c6a5c9cf88f91265f5a942e96213afae16d83321c8b31bb342142a14d16381483045022100a5263ea0553ba89221984bd7f0b13613db16e7a70c549a86de0cc0444141a407022005c360ef0ae5a5d4f9f2f87a56c1546cc8268cab08c73501d6b3be2e1e1a8a08824730440220525406a1482936d5a21888260dc165497a90a15669636d8edca6b9fe490d309c022032af0c646a34a44d1f4576bf6a4a74b67940f8faa84c7df9abe12a01a11e2b4783cf56210307b8ae49ac90a048e9b53357a2354b3334e9c8bee813ecb98e99a7e07e8c3ba32103b28f0c28bfab54554ae8c658ac5c3e0ce6e79ad336331f78c428dd43eea8449b21034b8113d703413d57761b8b9781957b8c0ac1dfe69f492580ca4195f50376ba4a21033400f6afecb833092a9a21cfdf1ed1376e58c5d1f47de74683123987e967a8f42103a6d48b1131e94ba04d9737d61acdaa1322008af9602b3b14862c07a1789aac162102d8b661b0b3302ee2f162b09e07a55ad5dfbe673a9f01d9f0c19617681024306b56ae00000000";
+
+    fn script_from_hex(s: &str) -> Script {
+        Script::parse_raw(&hex::decode(s).unwrap()).unwrap()
+    }
+
+    fn with_p2wpkh_prev_outputs(mut tx: Tx) -> Tx {
+        let tx_ins = tx.get_tx_ins_mut();
+        tx_ins[0].set_prev_output(TxOut::new(
+            625000000,
+            script_from_hex(
+                "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac",
+            ),
+        ));
+        tx_ins[1].set_prev_output(TxOut::new(
+            600000000,
+            script_from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1"),
+        ));
+        tx
+    }
+
+    fn with_p2sh_p2wpkh_prev_output(mut tx: Tx) -> Tx {
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(
+            1000000000,
+            script_from_hex("a9144733f37cf4db86fbc2efed2500b4f4e49f31202387"),
+        ));
+        tx
+    }
+
+    fn with_p2sh_p2wsh_prev_output(mut tx: Tx) -> Tx {
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(
+            987654321,
+            script_from_hex("a9149993a429037b5d912407a71c252019287b8d27a587"),
+        ));
+        tx
+    }
+
+    #[test]
+    fn test_parse_segwit() {
+        // Transaction ids checked against rust-bitcoin
+        for (tx_hex, id, wtxid) in [
+            (
+                P2WPKH_SIGNED_HEX,
+                "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609",
+                "c36c38370907df2324d9ce9d149d191192f338b37665a82e78e76a12c909b762",
+            ),
+            (
+                P2SH_P2WPKH_SIGNED_HEX,
+                "ef48d9d0f595052e0f8cdcf825f7a5e50b6a388a81f206f3f4846e5ecd7a0c23",
+                "680f483b2bf6c5dcbf111e69e885ba248a41a5e92070cfb0afec3cfc49a9fabb",
+            ),
+            (
+                P2SH_P2WSH_SIGNED_HEX,
+                "27eae69aff1dd4388c0fa05cbbfe9a3983d1b0b5811ebcd4199b86f299370aac",
+                "65dab5dd46a501fc695822c73d779067f2feb7c49dc47d39f86fdb2e3960b3bd",
+            ),
+        ] {
+            let tx = parse_hex_tx(tx_hex);
+            assert!(tx.is_segwit());
+            assert_eq!(hex::encode(&tx.serialize()), tx_hex);
+            assert_eq!(tx.id(), id);
+            assert_eq!(tx.wtxid(), wtxid);
+        }
+
+        let tx = parse_hex_tx(P2WPKH_SIGNED_HEX);
+        assert!(tx.get_tx_ins()[0].get_witness().is_empty());
+        assert_eq!(
+            hex::encode(&tx.get_tx_ins()[1].get_witness()[1]),
+            "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357"
+        );
+        // Without witness data the transaction is serialized in the legacy format
+        let tx = parse_hex_tx(TX_HEX);
+        assert!(!tx.is_segwit());
+        assert_eq!(tx.wtxid(), tx.id());
+    }
+
+    #[test]
+    fn test_parse_segwit_errors() {
+        let mut bytes = hex::decode(P2SH_P2WPKH_SIGNED_HEX).unwrap();
+        for len in [5, bytes.len() - 40, bytes.len() - 1] {
+            assert_eq!(
+                Tx::parse(&mut &bytes[..len]),
+                Err(Error::Io(std::io::ErrorKind::UnexpectedEof))
+            );
+        }
+        bytes[5] = 0x02;
+        assert_eq!(
+            Tx::parse(&mut bytes.as_slice()),
+            Err(Error::InvalidSegwitFlag(2))
+        );
+    }
+
+    #[test]
+    fn test_segwit_sig_hash() {
+        // The signature hashes of BIP143
+        let tx = with_p2wpkh_prev_outputs(parse_hex_tx(P2WPKH_UNSIGNED_HEX));
+        assert_eq!(
+            tx.segwit_sig_hash(1, Tx::SIGHASH_ALL).unwrap().to_string(),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        assert_eq!(
+            tx.segwit_sig_hash(0, Tx::SIGHASH_ALL),
+            Err(Error::UnsupportedScript)
+        );
+
+        // The script_sig must push the redeem script before a nested input can be hashed
+        let mut tx = with_p2sh_p2wpkh_prev_output(parse_hex_tx(P2SH_P2WPKH_UNSIGNED_HEX));
+        assert_eq!(
+            tx.segwit_sig_hash(0, Tx::SIGHASH_ALL),
+            Err(Error::UnsupportedScript)
+        );
+        tx.get_tx_ins_mut()[0].script_sig = Script::new(vec![Cmd::Data(
+            hex::decode("001479091972186c449eb1ded22b78e40d009bdf0089").unwrap(),
+        )]);
+        assert_eq!(
+            tx.segwit_sig_hash(0, Tx::SIGHASH_ALL).unwrap().to_string(),
+            "64f3b0f4dd2bb3aa1ce8566d220cc74dda9df97d8490cc81d89d735c92e59fb6"
+        );
+
+        let signed = parse_hex_tx(P2SH_P2WSH_SIGNED_HEX);
+        let witness_script = signed.get_tx_ins()[0].get_witness().last().unwrap().clone();
+        let mut tx = with_p2sh_p2wsh_prev_output(parse_hex_tx(P2SH_P2WSH_UNSIGNED_HEX));
+        tx.get_tx_ins_mut()[0].script_sig = signed.get_tx_ins()[0].get_script_sig().clone();
+        assert_eq!(
+            tx.segwit_sig_hash(0, Tx::SIGHASH_ALL),
+            Err(Error::InvalidScript)
+        );
+        tx.get_tx_ins_mut()[0].set_witness(vec![witness_script]);
+        for (hash_type, expected) in [
+            (
+                0x01,
+                "185c0be5263dce5b4bb50a047973c1b6272bfbd0103a89444597dc40b248ee7c",
+            ),
+            (
+                0x02,
+                "e9733bc60ea13c95c6527066bb975a2ff29a925e80aa14c213f686cbae5d2f36",
+            ),
+            (
+                0x03,
+                "1e1f1c303dc025bd664acb72e583e933fae4cff9148bf78c157d1e8f78530aea",
+            ),
+            (
+                0x81,
+                "2a67f03e63a6a422125878b40b82da593be8d4efaafe88ee528af6e5a9955c6e",
+            ),
+            (
+                0x82,
+                "781ba15f3779d5542ce8ecb5c18716733a5ee42a6f51488ec96154934e2c890a",
+            ),
+            (
+                0x83,
+                "511e8e52ed574121fc1b654970395502128263f62662e076dc6baf05c2e6a99b",
+            ),
+        ] {
+            assert_eq!(
+                tx.segwit_sig_hash(0, hash_type).unwrap().to_string(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify_segwit() {
+        let tx = with_p2wpkh_prev_outputs(parse_hex_tx(P2WPKH_SIGNED_HEX));
+        assert_eq!(tx.verify_input(0), Ok(true));
+        assert_eq!(tx.verify_input(1), Ok(true));
+        assert_eq!(tx.verify(), Ok(true));
+        let tx = with_p2sh_p2wpkh_prev_output(parse_hex_tx(P2SH_P2WPKH_SIGNED_HEX));
+        assert_eq!(tx.verify(), Ok(true));
+        let tx = with_p2sh_p2wsh_prev_output(parse_hex_tx(P2SH_P2WSH_SIGNED_HEX));
+        assert_eq!(tx.verify(), Ok(true));
+
+        // The signature commits to the amount of the spent output
+        let mut tx = with_p2wpkh_prev_outputs(parse_hex_tx(P2WPKH_SIGNED_HEX));
+        let prev_output = tx.get_tx_ins()[1].get_prev_output().unwrap().clone();
+        tx.get_tx_ins_mut()[1].set_prev_output(TxOut::new(
+            prev_output.get_amount() - 1,
+            prev_output.get_script_pubkey().clone(),
+        ));
+        assert_eq!(tx.verify_input(1), Ok(false));
+
+        // A native segwit input with a script_sig, or without a witness, is not valid
+        let mut tx = with_p2wpkh_prev_outputs(parse_hex_tx(P2WPKH_SIGNED_HEX));
+        tx.get_tx_ins_mut()[1].script_sig = Script::new(vec![Cmd::Op(crate::op::OP_1)]);
+        assert_eq!(tx.verify_input(1), Ok(false));
+        let mut tx = with_p2wpkh_prev_outputs(parse_hex_tx(P2WPKH_SIGNED_HEX));
+        tx.get_tx_ins_mut()[1].set_witness(Vec::new());
+        assert_eq!(tx.verify_input(1), Ok(false));
+
+        // Dropping one of the six multisig signatures
+        let mut tx = with_p2sh_p2wsh_prev_output(parse_hex_tx(P2SH_P2WSH_SIGNED_HEX));
+        let mut witness = tx.get_tx_ins()[0].get_witness().to_vec();
+        witness.remove(3);
+        tx.get_tx_ins_mut()[0].set_witness(witness);
+        assert_eq!(tx.verify_input(0), Ok(false));
+    }
+
+    #[test]
+    fn test_sign_segwit_input() {
+        // Signing the BIP143 examples reproduces them, as the nonces are deterministic
+        let signed = parse_hex_tx(P2WPKH_SIGNED_HEX);
+        let mut tx = with_p2wpkh_prev_outputs(parse_hex_tx(P2WPKH_UNSIGNED_HEX));
+        tx.get_tx_ins_mut()[0].script_sig = signed.get_tx_ins()[0].get_script_sig().clone();
+        let private_key =
+            PrivateKey::new(b"619c335025c7f4012e556c2a58b2506e30b8511b53ade95ea316fd8c3286feb9");
+        tx.sign_segwit_input(1, &private_key).unwrap();
+        assert_eq!(hex::encode(&tx.serialize()), P2WPKH_SIGNED_HEX);
+        assert_eq!(
+            tx.sign_segwit_input(0, &private_key),
+            Err(Error::UnsupportedScript)
+        );
+        // The P2WPKH output is not locked to another key
+        assert_eq!(
+            tx.sign_segwit_input(1, &PrivateKey::new(b"845fed")),
+            Err(Error::UnsupportedScript)
+        );
+        assert_eq!(hex::encode(&tx.serialize()), P2WPKH_SIGNED_HEX);
+
+        let mut tx = with_p2sh_p2wpkh_prev_output(parse_hex_tx(P2SH_P2WPKH_UNSIGNED_HEX));
+        let private_key =
+            PrivateKey::new(b"eb696a065ef48a2192da5b28b694f87544b30fae8327c4510137a922f32c6dcf");
+        tx.sign_segwit_input(0, &private_key).unwrap();
+        assert_eq!(hex::encode(&tx.serialize()), P2SH_P2WPKH_SIGNED_HEX);
+        // The P2SH output is not the P2SH-P2WPKH output of another key
+        assert_eq!(
+            tx.sign_segwit_input(0, &PrivateKey::new(b"845fed")),
+            Err(Error::UnsupportedScript)
+        );
+
+        let mut tx = with_p2sh_p2wsh_prev_output(parse_hex_tx(P2SH_P2WSH_UNSIGNED_HEX));
+        let signed = parse_hex_tx(P2SH_P2WSH_SIGNED_HEX);
+        let witness_script = signed.get_tx_ins()[0].get_witness().last().unwrap().clone();
+        tx.get_tx_ins_mut()[0].script_sig = signed.get_tx_ins()[0].get_script_sig().clone();
+        tx.get_tx_ins_mut()[0].set_witness(vec![witness_script.clone()]);
+        let mut witness = vec![Vec::new()];
+        for (secret, hash_type) in [
+            (
+                "730fff80e1413068a05b57d6a58261f07551163369787f349438ea38ca80fac6",
+                0x01,
+            ),
+            (
+                "11fa3d25a17cbc22b29c44a484ba552b5a53149d106d3d853e22fdd05a2d8bb3",
+                0x02,
+            ),
+            (
+                "77bf4141a87d55bdd7f3cd0bdccf6e9e642935fec45f2f30047be7b799120661",
+                0x03,
+            ),
+            (
+                "14af36970f5025ea3e8b5542c0f8ebe7763e674838d08808896b63c3351ffe49",
+                0x81,
+            ),
+            (
+                "fe9a95c19eef81dde2b95c1284ef39be497d128e2aa46916fb02d552485e0323",
+                0x82,
+            ),
+            (
+                "428a7aee9f0c2af0cd19af3cf1c78149951ea528726989b2e83e4778d2c3f890",
+                0x83,
+            ),
+        ] {
+            let private_key = PrivateKey::new(secret.as_bytes());
+            witness.push(tx.segwit_signature(0, &private_key, hash_type).unwrap());
+        }
+        witness.push(witness_script);
+        tx.get_tx_ins_mut()[0].set_witness(witness);
+        assert_eq!(hex::encode(&tx.serialize()), P2SH_P2WSH_SIGNED_HEX);
+    }
+
+    #[test]
+    fn test_p2wsh() {
+        let private_key = PrivateKey::new(b"845fed");
+        let mut witness_script = Script::new(vec![
+            Cmd::Data(private_key.get_public_key().sec(true)),
+            Cmd::Op(crate::op::OP_CHECKSIG),
+        ])
+        .raw_serialize();
+        let mut tx = parse_hex_tx("010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d00000000ffffffff02408af701000000001976a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac80969800000000001976a914507b27411ccf7f16f10297de6cef3f291623eddf88ac00000000");
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(
+            50000000,
+            Script::witness_program(0, sha256(&witness_script).as_bytes()),
+        ));
+        tx.get_tx_ins_mut()[0].set_witness(vec![witness_script.clone()]);
+        let sig = tx
+            .segwit_signature(0, &private_key, Tx::SIGHASH_ALL)
+            .unwrap();
+        tx.get_tx_ins_mut()[0].set_witness(vec![sig.clone(), witness_script.clone()]);
+        assert_eq!(tx.verify(), Ok(true));
+        let txid = tx.id();
+
+        // The witness script must hash to the program
+        witness_script.push(crate::op::OP_NOP);
+        tx.get_tx_ins_mut()[0].set_witness(vec![sig, witness_script]);
+        assert_eq!(tx.verify(), Ok(false));
+        assert_eq!(tx.id(), txid);
+    }
+
+    // The BIP143 native P2WSH example, whose witness script is
+    // <key 1> OP_CHECKSIGVERIFY OP_CODESEPARATOR <key 2> OP_CHECKSIG and whose first input
+    // spends a P2PK output
+    const P2WSH_UNSIGNED_HEX: &str = "0100000002fe3dc9208094f3ffd12645477b3dc56f60ec4fa8e6f5d67c565d1c6b9216b36e0000000000ffffffff0815cf020f013ed6cf91d29f4202e8a58726b1ac6c79da47c23d1bee0a6925f80000000000ffffffff0100f2052a010000001976a914a30741f8145e5acadf23f751864167f32e0963f788ac00000000";
+    const P2WSH_SIGNED_HEX: &str = "01000000000102fe3dc9208094f3ffd12645477b3dc56f60ec4fa8e6f5d67c565d1c6b9216b36e000000004847304402200af4e47c9b9629dbecc21f73af989bdaa911f7e6f6c2e9394588a3aa68f81e9902204f3fcf6ade7e5abb1295b6774c8e0abd94ae62217367096bc02ee5e435b67da201ffffffff0815cf020f013ed6cf91d29f4202e8a58726b1ac6c79da47c23d1bee0a6925f80000000000ffffffff0100f2052a010000001976a914a30741f8145e5acadf23f751864167f32e0963f788ac000347304402200de66acf4527789bfda55fc5459e214fa6083f936b430a762c629656216805ac0220396f550692cd347171cbc1ef1f51e15282e837bb2b30860dc77c8f78bc8501e503473044022027dc95ad6b740fe5129e7e62a75dd00f291a2aeb1200b84b09d9e3789406b6c002201a9ecd315dd6a0e632ab20bbb98948bc0c6fb204f2c286963bb48517a7058e27034721026dccc749adc2a9d0d89497ac511f760f45c47dc5ed9cf352a58ac706453880aeadab210255a9626aebf5e29c0e6538428ba0d1dcf6ca98ffdf086aa8ced5e0d0215ea465ac00000000";
+
+    // The BIP143 native P2WSH example whose witness scripts are
+    // <condition> OP_IF OP_CODESEPARATOR OP_ENDIF <key> OP_CHECKSIG, signed with
+    // SIGHASH_SINGLE|SIGHASH_ANYONECANPAY
+    const P2WSH_IF_UNSIGNED_HEX: &str = "0100000002e9b542c5176808107ff1df906f46bb1f2583b16112b95ee5380665ba7fcfc0010000000000ffffffff80e68831516392fcd100d186b3c2c7b95c80b53c77e77c35ba03a66b429a2a1b0000000000ffffffff0280969800000000001976a914de4b231626ef508c9a74a8517e6783c0546d6b2888ac80969800000000001976a9146648a8cd4531e1ec47f35916de8e259237294d1e88ac00000000";
+    const P2WSH_IF_SIGNED_HEX: &str = "01000000000102e9b542c5176808107ff1df906f46bb1f2583b16112b95ee5380665ba7fcfc0010000000000ffffffff80e68831516392fcd100d186b3c2c7b95c80b53c77e77c35ba03a66b429a2a1b0000000000ffffffff0280969800000000001976a914de4b231626ef508c9a74a8517e6783c0546d6b2888ac80969800000000001976a9146648a8cd4531e1ec47f35916de8e259237294d1e88ac02483045022100f6a10b8604e6dc910194b79ccfc93e1bc0ec7c03453caaa8987f7d6c3413566002206216229ede9b4d6ec2d325be245c5b508ff0339bf1794078e20bfe0babc7ffe683270063ab68210392972e2eb617b2388771abe27235fd5ac44af8e61693261550447a4c3e39da98ac024730440220032521802a76ad7bf74d0e2c218b72cf0cbc867066e2e53db905ba37f130397e02207709e2188ed7f08f4c952d9d13986da504502b8c3be59617e043552f506c46ff83275163ab68210392972e2eb617b2388771abe27235fd5ac44af8e61693261550447a4c3e39da98ac00000000";
+
+    #[test]
+    fn test_p2wsh_codeseparator() {
+        // A signature commits to the witness script after the last OP_CODESEPARATOR run
+        let mut tx = parse_hex_tx(P2WSH_SIGNED_HEX);
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(
+            156250000,
+            script_from_hex(
+                "21036d5c20fa14fb2f635474c1dc4ef5909d4568e5569b79fc94d3448486e14685f8ac",
+            ),
+        ));
+        tx.get_tx_ins_mut()[1].set_prev_output(TxOut::new(
+            4900000000,
+            script_from_hex("00205d1b56b63d714eebe542309525f484b7e9d6f686b3781b6f61ef925d66d6f6a0"),
+        ));
+        assert_eq!(
+            tx.segwit_sig_hash(1, Tx::SIGHASH_SINGLE)
+                .unwrap()
+                .to_string(),
+            "82dde6e4f1e94d02c2b7ad03d2115d691f48d064e9d52f58194a6637e4194391"
+        );
+        let script_code =
+            hex::decode("210255a9626aebf5e29c0e6538428ba0d1dcf6ca98ffdf086aa8ced5e0d0215ea465ac")
+                .unwrap();
+        assert_eq!(
+            tx.sig_hash_bip143(1, &script_code, 4900000000, Tx::SIGHASH_SINGLE)
+                .to_string(),
+            "fef7bd749cce710c5c052bd796df1af0d935e59cea63736268bcbe2d2134fc47"
+        );
+        assert_eq!(tx.verify(), Ok(true));
+        let mut unsigned = parse_hex_tx(P2WSH_UNSIGNED_HEX);
+        unsigned.get_tx_ins_mut()[0].script_sig = tx.get_tx_ins()[0].get_script_sig().clone();
+        unsigned.get_tx_ins_mut()[1].set_witness(tx.get_tx_ins()[1].get_witness().to_vec());
+        assert_eq!(hex::encode(&unsigned.serialize()), P2WSH_SIGNED_HEX);
+
+        // The OP_CODESEPARATOR of the first witness script is skipped, and the one of the
+        // second runs, so its signature commits to the script from the OP_ENDIF
+        let mut tx = parse_hex_tx(P2WSH_IF_SIGNED_HEX);
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(
+            16777215,
+            script_from_hex("0020ba468eea561b26301e4cf69fa34bde4ad60c81e70f059f045ca9a79931004a4d"),
+        ));
+        tx.get_tx_ins_mut()[1].set_prev_output(TxOut::new(
+            16777215,
+            script_from_hex("0020d9bbfbe56af7c4b7f960a70d7ea107156913d9e5a26b0a71429df5e097ca6537"),
+        ));
+        let hash_type = Tx::SIGHASH_SINGLE | Tx::SIGHASH_ANYONECANPAY;
+        assert_eq!(
+            tx.segwit_sig_hash(0, hash_type).unwrap().to_string(),
+            "e9071e75e25b8a1e298a72f0d2e9f4f95a0f5cdf86a533cda597eb402ed13b3a"
+        );
+        let script_code =
+            hex::decode("68210392972e2eb617b2388771abe27235fd5ac44af8e61693261550447a4c3e39da98ac")
+                .unwrap();
+        assert_eq!(
+            tx.sig_hash_bip143(1, &script_code, 16777215, hash_type)
+                .to_string(),
+            "cd72f1f1a433ee9df816857fad88d8ebd97e09a75cd481583eb841c330275e54"
+        );
+        assert_eq!(tx.verify_input(0), Ok(true));
+        assert_eq!(tx.verify_input(1), Ok(true));
+        assert_eq!(tx.verify(), Ok(true));
+        assert_eq!(parse_hex_tx(P2WSH_IF_UNSIGNED_HEX).id(), tx.id());
+    }
+
+    #[test]
+    fn test_p2wsh_hash_lock() {
+        let preimage = b"programming bitcoin".to_vec();
+        let witness_script = Script::new(vec![
+            Cmd::Op(crate::op::OP_HASH160),
+            Cmd::Data(hash160(&preimage).as_bytes().to_vec()),
+            Cmd::Op(crate::op::OP_EQUAL),
+        ])
+        .raw_serialize();
+        let mut tx = parse_hex_tx("010000000199a24308080ab26e6fb65c4eccfadf76749bb5bfa8cb08f291320b3c21e56f0d0d00000000ffffffff02408af701000000001976a914d52ad7ca9b3d096a38e752c2018e6fbc40cdf26f88ac80969800000000001976a914507b27411ccf7f16f10297de6cef3f291623eddf88ac00000000");
+        tx.get_tx_ins_mut()[0].set_prev_output(TxOut::new(
+            50000000,
+            Script::witness_program(0, sha256(&witness_script).as_bytes()),
+        ));
+        // The preimage is checked against the hash, not run as a redeem script
+        tx.get_tx_ins_mut()[0].set_witness(vec![preimage, witness_script.clone()]);
+        assert_eq!(tx.verify(), Ok(true));
+
+        tx.get_tx_ins_mut()[0].set_witness(vec![b"wrong".to_vec(), witness_script]);
+        assert_eq!(tx.verify(), Ok(false));
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let bytes = hex::decode(TX_HEX).unwrap();
+        for len in [0, 3, 40, bytes.len() - 1] {
+            assert_eq!(
+                Tx::parse(&mut &bytes[..len]),
+                Err(Error::Io(std::io::ErrorKind::UnexpectedEof))
+            );
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::io::{Read, Result};
+
+/// Reads a variable length integer (CompactSize) from a reader
+/// # Arguments
+/// * `reader` - The reader to read the integer from
+/// # Returns
+/// * `Result<u64>` - The integer, or the I/O error that stopped the read
+pub fn read_varint<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut prefix = [0u8; 1];
+    reader.read_exact(&mut prefix)?;
+
+    let len = match prefix[0] {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => return Ok(n as u64),
+    };
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes[..len])?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Encodes an integer as a variable length integer (CompactSize)
+/// # Arguments
+/// * `n` - The integer to encode
+/// # Returns
+/// * `Vec<u8>` - One to nine bytes encoding the integer
+pub fn encode_varint(n: u64) -> Vec<u8> {
+    match n {
+        0..=0xfc => vec![n as u8],
+        0xfd..=0xffff => {
+            let mut result = vec![0xfd];
+            result.extend_from_slice(&(n as u16).to_le_bytes());
+            result
+        }
+        0x10000..=0xffff_ffff => {
+            let mut result = vec![0xfe];
+            result.extend_from_slice(&(n as u32).to_le_bytes());
+            result
+        }
+        _ => {
+            let mut result = vec![0xff];
+            result.extend_from_slice(&n.to_le_bytes());
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_varint() {
+        let cases: Vec<(u64, Vec<u8>)> = vec![
+            (0, vec![0x00]),
+            (0xfc, vec![0xfc]),
+            (0xfd, vec![0xfd, 0xfd, 0x00]),
+            (0xffff, vec![0xfd, 0xff, 0xff]),
+            (0x10000, vec![0xfe, 0x00, 0x00, 0x01, 0x00]),
+            (
+                0x1_0000_0000,
+                vec![0xff, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+            ),
+        ];
+
+        for (n, encoded) in cases {
+            assert_eq!(encode_varint(n), encoded);
+            assert_eq!(read_varint(&mut encoded.as_slice()).unwrap(), n);
+        }
+    }
+
+    #[test]
+    fn test_read_varint_eof() {
+        assert!(read_varint(&mut [0xfdu8, 0x01].as_slice()).is_err());
+        assert!(read_varint(&mut [0u8; 0].as_slice()).is_err());
+    }
+}